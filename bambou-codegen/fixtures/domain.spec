@@ -0,0 +1,17 @@
+{
+    "model": {
+        "rest_name": "domain",
+        "resource_name": "domains",
+        "entity_name": "Domain"
+    },
+    "attributes": [
+        {
+            "name": "name",
+            "type": "string"
+        },
+        {
+            "name": "maintenanceMode",
+            "type": "enum"
+        }
+    ]
+}
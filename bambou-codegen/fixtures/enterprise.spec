@@ -0,0 +1,63 @@
+{
+    "model": {
+        "rest_name": "enterprise",
+        "resource_name": "enterprises",
+        "entity_name": "Enterprise",
+        "description": "An organization.\n\nIt holds the domains of its users."
+    },
+    "attributes": [
+        {
+            "name": "ID",
+            "type": "string"
+        },
+        {
+            "name": "name",
+            "type": "string",
+            "description": "The name of the enterprise."
+        },
+        {
+            "name": "externalID",
+            "type": "string"
+        },
+        {
+            "name": "type",
+            "type": "enum",
+            "allowed_choices": ["CSP", "ENTERPRISE"]
+        },
+        {
+            "name": "floatingIPsQuota",
+            "type": "integer"
+        },
+        {
+            "name": "lastUpdatedDate",
+            "type": "time"
+        },
+        {
+            "name": "BGPEnabled",
+            "type": "boolean"
+        },
+        {
+            "name": "allowedForwardingClasses",
+            "type": "list",
+            "subtype": "enum"
+        },
+        {
+            "name": "customFields",
+            "type": "object"
+        }
+    ],
+    "children": [
+        {
+            "rest_name": "domain",
+            "relationship": "child"
+        },
+        {
+            "rest_name": "domain",
+            "relationship": "member"
+        },
+        {
+            "rest_name": "vport",
+            "relationship": "child"
+        }
+    ]
+}
@@ -0,0 +1,18 @@
+//! Generated by bambou-codegen from the `domain` specification. Do not edit.
+
+use bambou::Session;
+
+#[derive(Clone, Debug, Serialize, Deserialize, RestEntity)]
+#[bambou(path = "domain", group = "domains")]
+pub struct Domain {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(skip)]
+    original: Option<::serde_json::Value>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "name", default)]
+    pub name: Option<String>,
+    #[serde(rename = "maintenanceMode", default)]
+    pub maintenance_mode: Option<String>,
+}
@@ -0,0 +1,43 @@
+//! Generated by bambou-codegen from the `enterprise` specification. Do not edit.
+
+use bambou::{Error, RestEntity, Session};
+
+use super::domain::Domain;
+
+/// An organization.
+///
+/// It holds the domains of its users.
+#[derive(Clone, Debug, Serialize, Deserialize, RestEntity)]
+#[bambou(path = "enterprise", group = "enterprises")]
+pub struct Enterprise {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(skip)]
+    original: Option<::serde_json::Value>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The name of the enterprise.
+    #[serde(rename = "name", default)]
+    pub name: Option<String>,
+    #[serde(rename = "externalID", default)]
+    pub external_id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub type_: Option<String>,
+    #[serde(rename = "floatingIPsQuota", default)]
+    pub floating_ips_quota: Option<i64>,
+    #[serde(rename = "lastUpdatedDate", default)]
+    pub last_updated_date: Option<i64>,
+    #[serde(rename = "BGPEnabled", default)]
+    pub bgp_enabled: Option<bool>,
+    #[serde(rename = "allowedForwardingClasses", default)]
+    pub allowed_forwarding_classes: Option<Vec<String>>,
+    #[serde(rename = "customFields", default)]
+    pub custom_fields: Option<::serde_json::Value>,
+}
+
+impl Enterprise {
+    /// Fetch the `domains` children of the entity.
+    pub fn domains(&self) -> Result<Vec<Domain>, Error> {
+        self.fetch_children()
+    }
+}
@@ -0,0 +1,29 @@
+//! Generated by bambou-codegen from the `me` specification. Do not edit.
+
+use bambou::{Error, RestEntity, Session};
+
+use super::enterprise::Enterprise;
+
+/// The user of the session.
+#[derive(Clone, Debug, Serialize, Deserialize, RestEntity)]
+#[bambou(path = "me", group = "me", root)]
+pub struct Me {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(skip)]
+    original: Option<::serde_json::Value>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The login of the user.
+    #[serde(rename = "userName", default)]
+    pub user_name: Option<String>,
+    #[serde(rename = "APIKey", default)]
+    pub api_key: Option<String>,
+}
+
+impl Me {
+    /// Fetch the `enterprises` children of the entity.
+    pub fn enterprises(&self) -> Result<Vec<Enterprise>, Error> {
+        self.fetch_children()
+    }
+}
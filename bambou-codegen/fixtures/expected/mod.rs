@@ -0,0 +1,9 @@
+//! Generated by bambou-codegen. Do not edit.
+
+pub mod domain;
+pub mod enterprise;
+pub mod me;
+
+pub use self::domain::Domain;
+pub use self::enterprise::Enterprise;
+pub use self::me::Me;
@@ -0,0 +1,26 @@
+{
+    "model": {
+        "rest_name": "me",
+        "resource_name": "me",
+        "entity_name": "Me",
+        "description": "The user of the session.",
+        "root": true
+    },
+    "attributes": [
+        {
+            "name": "ID",
+            "type": "string"
+        },
+        {
+            "name": "userName",
+            "type": "string",
+            "description": "The login of the user."
+        }
+    ],
+    "children": [
+        {
+            "rest_name": "enterprise",
+            "relationship": "root"
+        }
+    ]
+}
@@ -0,0 +1,294 @@
+//! Generation of the Rust source of the entities.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use spec::{Attribute, Spec};
+
+const KEYWORDS: &'static [&'static str] =
+    &["abstract", "alignof", "as", "become", "box", "break", "const", "continue", "crate", "do",
+      "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+      "macro", "match", "mod", "move", "mut", "offsetof", "override", "priv", "proc", "pub",
+      "pure", "ref", "return", "self", "sizeof", "static", "struct", "super", "trait", "true",
+      "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+      "session", "original"];
+
+/// Return the source of the module of an entity. `known` holds the specifications of all the
+/// entities being generated: the children that are not among them are skipped.
+pub fn entity(spec: &Spec, known: &[&Spec]) -> String {
+    let model = &spec.model;
+    let mut out = String::new();
+    writeln!(out,
+             "//! Generated by bambou-codegen from the `{}` specification. Do not edit.\n",
+             model.rest_name)
+            .unwrap();
+    let children = children(spec, known);
+    if children.is_empty() {
+        writeln!(out, "use bambou::Session;").unwrap();
+    } else {
+        writeln!(out, "use bambou::{{Error, RestEntity, Session}};").unwrap();
+        writeln!(out, "").unwrap();
+        for child in &children {
+            writeln!(out,
+                     "use super::{}::{};",
+                     module_name(&child.model.rest_name),
+                     child.model.entity_name)
+                    .unwrap();
+        }
+    }
+
+    writeln!(out, "").unwrap();
+    if let Some(ref description) = model.description {
+        doc(&mut out, "", description);
+    }
+    writeln!(out, "#[derive(Clone, Debug, Serialize, Deserialize, RestEntity)]").unwrap();
+    write!(out,
+           "#[bambou(path = \"{}\", group = \"{}\"",
+           model.rest_name,
+           model.resource_name)
+            .unwrap();
+    if model.root {
+        write!(out, ", root").unwrap();
+    }
+    writeln!(out, ")]").unwrap();
+    writeln!(out, "pub struct {} {{", model.entity_name).unwrap();
+    writeln!(out, "    #[serde(skip)]").unwrap();
+    writeln!(out, "    session: Option<Session>,").unwrap();
+    writeln!(out, "    #[serde(skip)]").unwrap();
+    writeln!(out, "    original: Option<::serde_json::Value>,").unwrap();
+    writeln!(out,
+             "    #[serde(rename = \"ID\", default, skip_serializing_if = \"Option::is_none\")]")
+            .unwrap();
+    writeln!(out, "    pub id: Option<String>,").unwrap();
+
+    let mut has_api_key = false;
+    for attribute in &spec.attributes {
+        if attribute.name == "ID" {
+            continue;
+        }
+        let field = field_name(&attribute.name);
+        has_api_key |= field == "api_key";
+        if let Some(ref description) = attribute.description {
+            doc(&mut out, "    ", description);
+        }
+        writeln!(out, "    #[serde(rename = \"{}\", default)]", attribute.name).unwrap();
+        writeln!(out, "    pub {}: Option<{}>,", field, field_type(attribute)).unwrap();
+    }
+    if model.root && !has_api_key {
+        writeln!(out, "    #[serde(rename = \"APIKey\", default)]").unwrap();
+        writeln!(out, "    pub api_key: Option<String>,").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    if !children.is_empty() {
+        writeln!(out, "").unwrap();
+        writeln!(out, "impl {} {{", model.entity_name).unwrap();
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                writeln!(out, "").unwrap();
+            }
+            writeln!(out,
+                     "    /// Fetch the `{}` children of the entity.",
+                     child.model.resource_name)
+                    .unwrap();
+            writeln!(out,
+                     "    pub fn {}(&self) -> Result<Vec<{}>, Error> {{",
+                     field_name(&child.model.resource_name),
+                     child.model.entity_name)
+                    .unwrap();
+            writeln!(out, "        self.fetch_children()").unwrap();
+            writeln!(out, "    }}").unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+/// Return the source of the module that declares the modules of all the entities.
+pub fn module(specs: &[Spec]) -> String {
+    let mut out = String::new();
+    writeln!(out, "//! Generated by bambou-codegen. Do not edit.\n").unwrap();
+    for spec in specs {
+        writeln!(out, "pub mod {};", module_name(&spec.model.rest_name)).unwrap();
+    }
+    writeln!(out, "").unwrap();
+    for spec in specs {
+        writeln!(out,
+                 "pub use self::{}::{};",
+                 module_name(&spec.model.rest_name),
+                 spec.model.entity_name)
+                .unwrap();
+    }
+    out
+}
+
+/// Return the name of the module of an entity.
+pub fn module_name(rest_name: &str) -> String {
+    field_name(rest_name)
+}
+
+/// Return the specifications of the children of an entity, without duplicates.
+fn children<'a>(spec: &Spec, known: &[&'a Spec]) -> Vec<&'a Spec> {
+    let mut seen = HashSet::new();
+    spec.children
+        .iter()
+        .filter_map(|child| {
+                        known
+                            .iter()
+                            .find(|known| known.model.rest_name == child.rest_name)
+                            .cloned()
+                    })
+        .filter(|child| seen.insert(child.model.rest_name.clone()))
+        .collect()
+}
+
+/// Return the Rust type of an attribute, without the `Option`.
+fn field_type(attribute: &Attribute) -> String {
+    match attribute.attribute_type.as_str() {
+        "list" => {
+            let item = match attribute.subtype.as_ref().map(|s| s.as_str()) {
+                Some("string") | Some("enum") => "String",
+                Some("boolean") => "bool",
+                Some("integer") | Some("time") => "i64",
+                Some("float") => "f64",
+                _ => "::serde_json::Value",
+            };
+            format!("Vec<{}>", item)
+        }
+        // The allowed choices of an enum are only documented: a new API version may add choices
+        // that would otherwise fail to deserialize.
+        "string" | "enum" => "String".to_string(),
+        "boolean" => "bool".to_string(),
+        "integer" | "time" => "i64".to_string(),
+        "float" => "f64".to_string(),
+        _ => "::serde_json::Value".to_string(),
+    }
+}
+
+/// Convert a camel case name such as `externalID`, `APIKey` or `floatingIPsQuota` to a snake case
+/// field name such as `external_id`, `api_key` or `floating_ips_quota`. Rust keywords, and the
+/// names of the fields added to every entity (`session` and `original`), are suffixed with an
+/// underscore.
+fn field_name(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map_or(false, |c| c.is_lowercase());
+            // The plural of an acronym, such as `IPs`, is a single word.
+            let plural = chars.get(i + 1) == Some(&'s') &&
+                         chars.get(i + 2).map_or(true, |c| !c.is_lowercase());
+            if previous.is_lowercase() || previous.is_numeric() ||
+               (previous.is_uppercase() && next_is_lower && !plural) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    if KEYWORDS.contains(&out.as_str()) {
+        out.push('_');
+    }
+    out
+}
+
+/// Write a description as a doc comment.
+fn doc(out: &mut String, indent: &str, description: &str) {
+    for line in description.lines() {
+        let line = line.trim_right();
+        if line.is_empty() {
+            writeln!(out, "{}///", indent).unwrap();
+        } else {
+            writeln!(out, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json;
+
+    use spec::{self, Attribute};
+    use super::*;
+
+    fn attribute(attribute_type: &str, subtype: Option<&str>) -> Attribute {
+        let json = r#"{"name": "attribute", "type": ""}"#;
+        let mut attribute = serde_json::from_str::<Attribute>(json).unwrap();
+        attribute.attribute_type = attribute_type.to_string();
+        attribute.subtype = subtype.map(|subtype| subtype.to_string());
+        attribute
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("name"), "name");
+        assert_eq!(field_name("externalID"), "external_id");
+        assert_eq!(field_name("APIKey"), "api_key");
+        assert_eq!(field_name("enterpriseProfileID"), "enterprise_profile_id");
+        assert_eq!(field_name("lastUpdatedDate"), "last_updated_date");
+        assert_eq!(field_name("BGPPeers"), "bgp_peers");
+        assert_eq!(field_name("ECMPCount"), "ecmp_count");
+        assert_eq!(field_name("floatingIPsQuota"), "floating_ips_quota");
+        assert_eq!(field_name("gatewayIDs"), "gateway_ids");
+    }
+
+    #[test]
+    fn reserved_field_names() {
+        assert_eq!(field_name("type"), "type_");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(field_name("session"), "session_");
+        assert_eq!(field_name("original"), "original_");
+    }
+
+    #[test]
+    fn field_types() {
+        assert_eq!(field_type(&attribute("string", None)), "String");
+        assert_eq!(field_type(&attribute("enum", None)), "String");
+        assert_eq!(field_type(&attribute("boolean", None)), "bool");
+        assert_eq!(field_type(&attribute("integer", None)), "i64");
+        assert_eq!(field_type(&attribute("time", None)), "i64");
+        assert_eq!(field_type(&attribute("float", None)), "f64");
+        assert_eq!(field_type(&attribute("object", None)), "::serde_json::Value");
+        assert_eq!(field_type(&attribute("list", Some("string"))), "Vec<String>");
+        assert_eq!(field_type(&attribute("list", Some("enum"))), "Vec<String>");
+        assert_eq!(field_type(&attribute("list", Some("boolean"))), "Vec<bool>");
+        assert_eq!(field_type(&attribute("list", Some("integer"))), "Vec<i64>");
+        assert_eq!(field_type(&attribute("list", Some("float"))), "Vec<f64>");
+        assert_eq!(field_type(&attribute("list", Some("object"))), "Vec<::serde_json::Value>");
+        assert_eq!(field_type(&attribute("list", None)), "Vec<::serde_json::Value>");
+    }
+
+    #[test]
+    fn sources() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let specs = spec::read_dir(&fixtures).unwrap();
+        let names = specs
+            .iter()
+            .map(|spec| spec.model.rest_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["domain", "enterprise", "me"]);
+
+        let known = specs.iter().collect::<Vec<_>>();
+        assert_eq!(entity(&specs[0], &known),
+                   include_str!("../fixtures/expected/domain.rs"));
+        // The duplicated `domain` child is only fetched once, and the unknown `vport` child is
+        // skipped.
+        assert_eq!(entity(&specs[1], &known),
+                   include_str!("../fixtures/expected/enterprise.rs"));
+        // The root entity gets an API key.
+        assert_eq!(entity(&specs[2], &known),
+                   include_str!("../fixtures/expected/me.rs"));
+        assert_eq!(module(&specs), include_str!("../fixtures/expected/mod.rs"));
+    }
+
+    #[test]
+    fn unknown_children_are_skipped() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let specs = spec::read_dir(&fixtures).unwrap();
+        let source = entity(&specs[1], &[&specs[1]]);
+        assert!(source.contains("use bambou::Session;\n"));
+        assert!(!source.contains("impl Enterprise"));
+    }
+}
@@ -0,0 +1,60 @@
+//! Generate the entities of a VSD API version from its specifications.
+//!
+//! ```text
+//! bambou-codegen <specifications directory> <output directory>
+//! ```
+//!
+//! The specifications directory holds the `.spec` files published for the API version. A module
+//! is written to the output directory for every entity, along with a `mod.rs` declaring them. The
+//! generated structs derive `RestEntity` with `bambou-derive`, and `Serialize` and `Deserialize`
+//! with `serde_derive`, so the crate including them must depend on `bambou`, `bambou-derive`,
+//! `serde_derive` and `serde_json`.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+mod generate;
+mod spec;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("usage: bambou-codegen <specifications directory> <output directory>");
+        process::exit(2);
+    }
+    if let Err(msg) = run(Path::new(&args[0]), Path::new(&args[1])) {
+        eprintln!("error: {}", msg);
+        process::exit(1);
+    }
+}
+
+fn run(specs_dir: &Path, output_dir: &Path) -> Result<(), String> {
+    let specs = spec::read_dir(specs_dir)?;
+    if specs.is_empty() {
+        return Err(format!("no .spec file in {}", specs_dir.display()));
+    }
+    fs::create_dir_all(output_dir).map_err(|e| format!("{}: {}", output_dir.display(), e))?;
+
+    let known = specs.iter().collect::<Vec<_>>();
+    for spec in &specs {
+        let name = format!("{}.rs", generate::module_name(&spec.model.rest_name));
+        write(output_dir.join(name), &generate::entity(spec, &known))?;
+    }
+    write(output_dir.join("mod.rs"), &generate::module(&specs))?;
+    println!("generated {} entities in {}", specs.len(), output_dir.display());
+    Ok(())
+}
+
+fn write(path: PathBuf, source: &str) -> Result<(), String> {
+    File::create(&path)
+        .and_then(|mut file| file.write_all(source.as_bytes()))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,75 @@
+//! The specifications of the VSD API, as published in the `vsd-api-specifications` repository.
+//! Each entity is described by a `<rest name>.spec` JSON file.
+
+use std::fs::{self, File};
+use std::path::Path;
+
+use serde_json;
+
+/// The specification of an entity.
+#[derive(Debug, Deserialize)]
+pub struct Spec {
+    pub model: Model,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub children: Vec<Child>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Model {
+    /// The rest path of the entity, e.g. `"enterprise"`.
+    pub rest_name: String,
+    /// The rest path of the entity's group, e.g. `"enterprises"`.
+    pub resource_name: String,
+    /// The name of the entity, e.g. `"Enterprise"`.
+    pub entity_name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub root: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Attribute {
+    /// The serialized name of the attribute, e.g. `"externalID"`.
+    pub name: String,
+    /// The type of the attribute: `string`, `boolean`, `integer`, `float`, `time`, `enum`,
+    /// `list` or `object`.
+    #[serde(rename = "type")]
+    pub attribute_type: String,
+    /// The type of the items of a `list` attribute.
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub allowed_choices: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Child {
+    /// The rest path of the child entity.
+    pub rest_name: String,
+    /// `child`, `member`, `alarm`...
+    #[serde(default)]
+    pub relationship: Option<String>,
+}
+
+/// Read all the `.spec` files of a directory, sorted by rest name.
+pub fn read_dir(dir: &Path) -> Result<Vec<Spec>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut specs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().map_or(true, |ext| ext != "spec") {
+            continue;
+        }
+        let file = File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let spec: Spec = serde_json::from_reader(file)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        specs.push(spec);
+    }
+    specs.sort_by(|a, b| a.model.rest_name.cmp(&b.model.rest_name));
+    Ok(specs)
+}
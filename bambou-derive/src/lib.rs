@@ -0,0 +1,849 @@
+//! Custom derive for `bambou::RestEntity`.
+//!
+//! ```rust,ignore
+//! #[macro_use]
+//! extern crate bambou_derive;
+//!
+//! #[derive(Serialize, Deserialize, RestEntity)]
+//! #[bambou(path = "enterprise", group = "enterprises", key = "name")]
+//! struct Enterprise {
+//!     #[serde(skip)]
+//!     session: Option<Session>,
+//!     #[serde(rename = "ID")]
+//!     id: Option<String>,
+//!     name: String,
+//! }
+//! ```
+//!
+//! The following attributes are supported on the struct:
+//!
+//! - `path`: the rest path of the entity (required).
+//! - `group`: the rest path of the entity's group. It defaults to the path.
+//! - `key`: the serialized name of the entity's alternate key, if any.
+//! - `root`: the entity is a root of the API. `RestRootEntity` is implemented as well.
+//! - `display`: `Display` is implemented as well (see below).
+//! - `builder`: a builder is generated as well (see below).
+//!
+//! The struct must have a field holding an `Option<Session>`, a field holding the ID as an
+//! `Option<String>`, and for root entities, a field holding the API key as an `Option<String>`.
+//! They are the fields named `session`, `id` and `api_key`, unless other fields are marked with
+//! `#[bambou(session)]`, `#[bambou(id)]` or `#[bambou(api_key)]`.
+//!
+//! If the struct has a field named `etag` or marked with `#[bambou(etag)]`, holding an
+//! `Option<bambou::EntityTag>` that is not serialized, the entity keeps its `ETag` in it.
+//!
+//! If the struct has a field named `original` or marked with `#[bambou(original)]`, holding an
+//! `Option<serde_json::Value>` that is not serialized, the entity tracks its changes: only the
+//! modified attributes are sent when it is saved.
+//!
+//! The fields marked with `#[bambou(key_attribute)]` are returned by `key_attributes`, and shown
+//! by `pretty`. An `Option` field is only shown when it is set. With the `display` attribute on
+//! the struct, `Display` is implemented as well, and writes `pretty()`:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize, RestEntity)]
+//! #[bambou(path = "enterprise", group = "enterprises", display)]
+//! struct Enterprise {
+//!     // ...
+//!     #[bambou(key_attribute)]
+//!     name: String,
+//! }
+//!
+//! // enterprise 5d3f... (name: acme)
+//! println!("{}", enterprise);
+//! ```
+//!
+//! With the `builder` attribute on the struct, a builder is generated as well:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize, RestEntity)]
+//! #[bambou(path = "enterprise", group = "enterprises", builder)]
+//! struct Enterprise {
+//!     // ...
+//!     #[bambou(required)]
+//!     name: String,
+//!     description: Option<String>,
+//! }
+//!
+//! let enterprise = Enterprise::builder().name("acme").build();
+//! ```
+//!
+//! The builder has a setter for every field except the session, `ETag` and original fields,
+//! taking anything that converts into the field's type, or into `T` for an `Option<T>` field.
+//! `build` is only available once the fields marked with `#[bambou(required)]` are set, so
+//! forgetting one is a compilation error. The other fields default to `Default::default()`.
+
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+use syn::{Body, DeriveInput, Field, Ident, Lit, MetaItem, NestedMetaItem, PathParameters, Ty,
+          VariantData};
+
+#[proc_macro_derive(RestEntity, attributes(bambou))]
+pub fn derive_rest_entity(input: TokenStream) -> TokenStream {
+    let input = syn::parse_derive_input(&input.to_string()).unwrap();
+    match expand(&input) {
+        Ok(tokens) => tokens.parse().unwrap(),
+        Err(msg) => panic!("#[derive(RestEntity)] on {}: {}", input.ident, msg),
+    }
+}
+
+/// The attributes of the entity, as given by the struct's `#[bambou(...)]` attributes.
+#[derive(Default)]
+struct EntityAttributes {
+    path: Option<String>,
+    group: Option<String>,
+    key: Option<String>,
+    root: bool,
+    builder: bool,
+    display: bool,
+}
+
+fn expand(input: &DeriveInput) -> Result<quote::Tokens, String> {
+    let fields = match input.body {
+        Body::Struct(VariantData::Struct(ref fields)) => fields,
+        _ => return Err("only structs with named fields are supported".to_string()),
+    };
+    let attributes = entity_attributes(input)?;
+    let path = attributes.path.ok_or("the `path` attribute is required")?;
+    let group = attributes.group.unwrap_or_else(|| path.clone());
+    let root = attributes.root;
+    let builder = attributes.builder;
+    let display = attributes.display;
+    let session = find_field(fields, "session")?;
+    let id = find_field(fields, "id")?;
+    let alternate_key = match attributes.key {
+        Some(key) => {
+            quote! {
+                fn alternate_key() -> Option<&'static str> {
+                    Some(#key)
+                }
+            }
+        }
+        None => quote!{},
+    };
+    let etag = match find_field(fields, "etag") {
+        Ok(etag) => {
+            quote! {
+                fn etag(&self) -> Option<&::bambou::EntityTag> {
+                    self.#etag.as_ref()
+                }
+
+                fn set_etag(&mut self, etag: Option<::bambou::EntityTag>) {
+                    self.#etag = etag;
+                }
+            }
+        }
+        Err(_) => quote!{},
+    };
+    let original = match find_field(fields, "original") {
+        Ok(original) => {
+            quote! {
+                fn tracks_changes() -> bool {
+                    true
+                }
+
+                fn original(&self) -> Option<&::serde_json::Value> {
+                    self.#original.as_ref()
+                }
+
+                fn set_original(&mut self, original: ::serde_json::Value) {
+                    self.#original = Some(original);
+                }
+            }
+        }
+        Err(_) => quote!{},
+    };
+    let key_attributes = expand_key_attributes(fields)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut tokens = quote! {
+        impl #impl_generics ::bambou::RestEntity for #name #ty_generics #where_clause {
+            fn set_session(&mut self, session: ::bambou::Session) {
+                self.#session = Some(session);
+            }
+
+            fn get_session(&self) -> Option<&::bambou::Session> {
+                self.#session.as_ref()
+            }
+
+            fn path() -> &'static str {
+                #path
+            }
+
+            fn group_path() -> &'static str {
+                #group
+            }
+
+            #alternate_key
+
+            fn id(&self) -> Option<&str> {
+                self.#id.as_ref().map(|id| id.as_str())
+            }
+
+            fn is_root(&self) -> bool {
+                #root
+            }
+
+            #etag
+
+            #original
+
+            #key_attributes
+        }
+    };
+
+    if root {
+        let api_key = find_field(fields, "api_key")?;
+        tokens.append(quote! {
+            impl #impl_generics ::bambou::RestRootEntity for #name #ty_generics #where_clause {
+                fn get_api_key(&self) -> Option<&str> {
+                    self.#api_key.as_ref().map(|key| key.as_str())
+                }
+            }
+        });
+    }
+    if display {
+        tokens.append(quote! {
+            impl #impl_generics ::std::fmt::Display for #name #ty_generics #where_clause {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                    f.write_str(&::bambou::RestEntity::pretty(self))
+                }
+            }
+        });
+    }
+    if builder {
+        tokens.append(expand_builder(input, fields)?);
+    }
+    Ok(tokens)
+}
+
+/// Generate `key_attributes`, returning the fields marked with `#[bambou(key_attribute)]`.
+fn expand_key_attributes(fields: &[Field]) -> Result<quote::Tokens, String> {
+    let mut pushes = Vec::new();
+    for field in fields.iter().filter(|field| has_marker(field, "key_attribute")) {
+        let ident = field.ident.clone().ok_or("only structs with named fields are supported")?;
+        let label = ident.as_ref().to_string();
+        let value = match option_inner(&field.ty) {
+            Some(_) => quote!(self.#ident.as_ref()),
+            None => quote!(Some(&self.#ident)),
+        };
+        pushes.push(quote! {
+            if let Some(value) = #value {
+                attributes.push((#label, ::std::string::ToString::to_string(value)));
+            }
+        });
+    }
+    if pushes.is_empty() {
+        return Ok(quote!{});
+    }
+    Ok(quote! {
+        fn key_attributes(&self) -> Vec<(&'static str, String)> {
+            let mut attributes = Vec::new();
+            #(#pushes)*
+            attributes
+        }
+    })
+}
+
+/// Generate `<Entity>Builder` and `<Entity>::builder`. The builder has a type parameter per
+/// required field, `Missing` until the field is set and `Set` afterwards, and `build` is only
+/// implemented when they are all `Set`.
+fn expand_builder(input: &DeriveInput, fields: &[Field]) -> Result<quote::Tokens, String> {
+    if !input.generics.lifetimes.is_empty() || !input.generics.ty_params.is_empty() {
+        return Err("the builder does not support generic structs".to_string());
+    }
+    // The fields that are not set by the user.
+    let internal = ["session", "etag", "original"]
+        .iter()
+        .filter_map(|role| find_field(fields, role).ok())
+        .collect::<Vec<_>>();
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let builder = Ident::new(format!("{}Builder", name));
+    let required = fields
+        .iter()
+        .filter(|field| is_required(field))
+        .filter_map(|field| field.ident.clone())
+        .collect::<Vec<_>>();
+    let params = (0..required.len())
+        .map(|i| Ident::new(format!("__R{}", i)))
+        .collect::<Vec<_>>();
+    let missing = required
+        .iter()
+        .map(|_| quote!(::bambou::builder::Missing))
+        .collect::<Vec<_>>();
+    let set = required
+        .iter()
+        .map(|_| quote!(::bambou::builder::Set))
+        .collect::<Vec<_>>();
+
+    let mut storage = Vec::new();
+    let mut init = Vec::new();
+    let mut setters = Vec::new();
+    let mut build = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().ok_or("only structs with named fields are supported")?;
+        if internal.contains(&ident) {
+            build.push(quote!(#ident: ::std::default::Default::default()));
+            continue;
+        }
+        let ty = &field.ty;
+        let (value_ty, value) = match option_inner(ty) {
+            Some(inner) => (inner, quote!(Some(value.into()))),
+            None => (ty, quote!(value.into())),
+        };
+        match required.iter().position(|r| *r == ident) {
+            Some(index) => {
+                storage.push(quote!(#ident: Option<#ty>));
+                init.push(quote!(#ident: None));
+                build.push(quote!(#ident: self.#ident.expect("required field not set")));
+                // The setter changes the type parameter of the field to `Set`, and moves the
+                // other fields to the new builder.
+                let mut result_params = params.iter().map(|p| quote!(#p)).collect::<Vec<_>>();
+                result_params[index] = quote!(::bambou::builder::Set);
+                let moved = storage_fields(fields, &internal)
+                    .into_iter()
+                    .filter(|other| *other != ident)
+                    .collect::<Vec<_>>();
+                let moved_from = moved.clone();
+                setters.push(quote! {
+                    pub fn #ident<__V>(self, value: __V) -> #builder<#(#result_params),*>
+                        where __V: Into<#value_ty>
+                    {
+                        #builder {
+                            #ident: Some(#value),
+                            #(#moved: self.#moved_from,)*
+                            __marker: ::std::marker::PhantomData,
+                        }
+                    }
+                });
+            }
+            None => {
+                storage.push(quote!(#ident: #ty));
+                init.push(quote!(#ident: ::std::default::Default::default()));
+                build.push(quote!(#ident: self.#ident));
+                setters.push(quote! {
+                    pub fn #ident<__V>(mut self, value: __V) -> Self
+                        where __V: Into<#value_ty>
+                    {
+                        self.#ident = #value;
+                        self
+                    }
+                });
+            }
+        }
+    }
+
+    let params_decl = params.clone();
+    let params_use = params.clone();
+    let params_marker = params.clone();
+    Ok(quote! {
+        #vis struct #builder<#(#params_decl),*> {
+            #(#storage,)*
+            __marker: ::std::marker::PhantomData<(#(#params_marker,)*)>,
+        }
+
+        impl #name {
+            pub fn builder() -> #builder<#(#missing),*> {
+                #builder {
+                    #(#init,)*
+                    __marker: ::std::marker::PhantomData,
+                }
+            }
+        }
+
+        impl<#(#params),*> #builder<#(#params_use),*> {
+            #(#setters)*
+        }
+
+        impl #builder<#(#set),*> {
+            pub fn build(self) -> #name {
+                #name {
+                    #(#build,)*
+                }
+            }
+        }
+    })
+}
+
+/// Return the names of the fields stored in the builder.
+fn storage_fields(fields: &[Field], internal: &[Ident]) -> Vec<Ident> {
+    fields
+        .iter()
+        .filter_map(|field| field.ident.clone())
+        .filter(|ident| !internal.contains(ident))
+        .collect()
+}
+
+/// Return true if the field is marked with `#[bambou(required)]`.
+fn is_required(field: &Field) -> bool {
+    has_marker(field, "required")
+}
+
+/// Return true if the field is marked with `#[bambou(<marker>)]`.
+fn has_marker(field: &Field, marker: &str) -> bool {
+    bambou_items(&field.attrs)
+        .iter()
+        .any(|item| match **item {
+                 MetaItem::Word(ref name) => name == marker,
+                 _ => false,
+             })
+}
+
+/// Return `T` if `ty` is `Option<T>`.
+fn option_inner(ty: &Ty) -> Option<&Ty> {
+    let segment = match *ty {
+        Ty::Path(None, ref path) => path.segments.last(),
+        _ => None,
+    };
+    match segment {
+        Some(segment) if segment.ident == "Option" => {
+            match segment.parameters {
+                PathParameters::AngleBracketed(ref data) if data.types.len() == 1 => {
+                    data.types.first()
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Return the items of the `#[bambou(...)]` attributes in `attrs`.
+fn bambou_items(attrs: &[syn::Attribute]) -> Vec<&MetaItem> {
+    attrs
+        .iter()
+        .filter_map(|attr| match attr.value {
+                        MetaItem::List(ref name, ref items) if name == "bambou" => Some(items),
+                        _ => None,
+                    })
+        .flat_map(|items| items.iter())
+        .filter_map(|item| match *item {
+                        NestedMetaItem::MetaItem(ref item) => Some(item),
+                        NestedMetaItem::Literal(_) => None,
+                    })
+        .collect()
+}
+
+fn entity_attributes(input: &DeriveInput) -> Result<EntityAttributes, String> {
+    let mut attributes = EntityAttributes::default();
+    for item in bambou_items(&input.attrs) {
+        match *item {
+            MetaItem::NameValue(ref name, Lit::Str(ref value, _)) => {
+                match name.as_ref() {
+                    "path" => attributes.path = Some(value.clone()),
+                    "group" => attributes.group = Some(value.clone()),
+                    "key" => attributes.key = Some(value.clone()),
+                    other => return Err(format!("unknown attribute `{}`", other)),
+                }
+            }
+            MetaItem::Word(ref name) if name == "root" => attributes.root = true,
+            MetaItem::Word(ref name) if name == "builder" => attributes.builder = true,
+            MetaItem::Word(ref name) if name == "display" => attributes.display = true,
+            ref other => return Err(format!("unknown attribute `{}`", other.name())),
+        }
+    }
+    Ok(attributes)
+}
+
+/// Return the field marked with `#[bambou(<role>)]`, or else the field named `<role>`.
+fn find_field(fields: &[Field], role: &str) -> Result<Ident, String> {
+    let marked = fields
+        .iter()
+        .find(|field| has_marker(field, role));
+    marked
+        .or_else(|| {
+                     fields
+                         .iter()
+                         .find(|field| field.ident.as_ref().map_or(false, |i| i == role))
+                 })
+        .and_then(|field| field.ident.clone())
+        .ok_or_else(|| format!("no `{}` field", role))
+}
+
+#[cfg(test)]
+mod tests {
+    use syn;
+    use quote;
+
+    use super::expand;
+
+    fn expand_str(input: &str) -> Result<String, String> {
+        let input = syn::parse_derive_input(input).unwrap();
+        expand(&input).map(|tokens| tokens.to_string())
+    }
+
+    fn assert_contains(expanded: &str, tokens: quote::Tokens) {
+        let tokens = tokens.to_string();
+        assert!(expanded.contains(&tokens),
+                "`{}` not found in `{}`",
+                tokens,
+                expanded);
+    }
+
+    #[test]
+    fn entity() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise", group = "enterprises")]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                name: String,
+            }"#)
+                .unwrap();
+        let expected = quote! {
+            impl ::bambou::RestEntity for Enterprise {
+                fn set_session(&mut self, session: ::bambou::Session) {
+                    self.session = Some(session);
+                }
+
+                fn get_session(&self) -> Option<&::bambou::Session> {
+                    self.session.as_ref()
+                }
+
+                fn path() -> &'static str {
+                    "enterprise"
+                }
+
+                fn group_path() -> &'static str {
+                    "enterprises"
+                }
+
+                fn id(&self) -> Option<&str> {
+                    self.id.as_ref().map(|id| id.as_str())
+                }
+
+                fn is_root(&self) -> bool {
+                    false
+                }
+            }
+        };
+        assert_eq!(expanded, expected.to_string());
+    }
+
+    #[test]
+    fn group_defaults_to_path() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "me")]
+            struct Me {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded, quote!(fn group_path() -> &'static str { "me" }));
+    }
+
+    #[test]
+    fn marked_fields() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise {
+                #[bambou(session)]
+                s: Option<Session>,
+                #[bambou(id)]
+                identifier: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded, quote!(self.s = Some(session);));
+        assert_contains(&expanded, quote!(self.identifier.as_ref().map(|id| id.as_str())));
+    }
+
+    #[test]
+    fn root_and_key() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "me", key = "userName", root)]
+            struct Me {
+                session: Option<Session>,
+                id: Option<String>,
+                api_key: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded,
+                        quote!(fn alternate_key() -> Option<&'static str> { Some("userName") }));
+        assert_contains(&expanded, quote!(fn is_root(&self) -> bool { true }));
+        assert_contains(&expanded,
+                        quote! {
+                            impl ::bambou::RestRootEntity for Me {
+                                fn get_api_key(&self) -> Option<&str> {
+                                    self.api_key.as_ref().map(|key| key.as_str())
+                                }
+                            }
+                        });
+    }
+
+    #[test]
+    fn etag() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                #[bambou(etag)]
+                tag: Option<EntityTag>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded,
+                        quote! {
+                            fn etag(&self) -> Option<&::bambou::EntityTag> {
+                                self.tag.as_ref()
+                            }
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            fn set_etag(&mut self, etag: Option<::bambou::EntityTag>) {
+                                self.tag = etag;
+                            }
+                        });
+    }
+
+    #[test]
+    fn original() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                original: Option<Value>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded, quote!(fn tracks_changes() -> bool { true }));
+        assert_contains(&expanded,
+                        quote! {
+                            fn set_original(&mut self, original: ::serde_json::Value) {
+                                self.original = Some(original);
+                            }
+                        });
+    }
+
+    #[test]
+    fn without_etag_or_original() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#)
+                .unwrap();
+        assert!(!expanded.contains("etag"));
+        assert!(!expanded.contains("tracks_changes"));
+        assert!(!expanded.contains("key_attributes"));
+        assert!(!expanded.contains("Display"));
+    }
+
+    #[test]
+    fn key_attributes_and_display() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise", display)]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                #[bambou(key_attribute)]
+                name: String,
+                #[bambou(key_attribute)]
+                description: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded,
+                        quote! {
+                            if let Some(value) = Some(&self.name) {
+                                attributes.push(("name",
+                                                 ::std::string::ToString::to_string(value)));
+                            }
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            if let Some(value) = self.description.as_ref() {
+                                attributes.push(("description",
+                                                 ::std::string::ToString::to_string(value)));
+                            }
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            impl ::std::fmt::Display for Enterprise {
+                                fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                                    f.write_str(&::bambou::RestEntity::pretty(self))
+                                }
+                            }
+                        });
+    }
+
+    #[test]
+    fn builder() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise", builder)]
+            pub struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                #[bambou(required)]
+                name: String,
+                description: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded,
+                        quote! {
+                            pub struct EnterpriseBuilder<__R0> {
+                                id: Option<String>,
+                                name: Option<String>,
+                                description: Option<String>,
+                                __marker: ::std::marker::PhantomData<(__R0,)>,
+                            }
+                        });
+        assert_contains(&expanded,
+                        quote!(pub fn builder() -> EnterpriseBuilder<::bambou::builder::Missing>));
+        // Setting the required field sets its type parameter.
+        assert_contains(&expanded,
+                        quote! {
+                            pub fn name<__V>(self, value: __V)
+                                -> EnterpriseBuilder<::bambou::builder::Set>
+                                where __V: Into<String>
+                            {
+                                EnterpriseBuilder {
+                                    name: Some(value.into()),
+                                    id: self.id,
+                                    description: self.description,
+                                    __marker: ::std::marker::PhantomData,
+                                }
+                            }
+                        });
+        // The optional fields take the inner type of the option.
+        assert_contains(&expanded,
+                        quote! {
+                            pub fn description<__V>(mut self, value: __V) -> Self
+                                where __V: Into<String>
+                            {
+                                self.description = Some(value.into());
+                                self
+                            }
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            impl EnterpriseBuilder<::bambou::builder::Set> {
+                                pub fn build(self) -> Enterprise {
+                                    Enterprise {
+                                        session: ::std::default::Default::default(),
+                                        id: self.id,
+                                        name: self.name.expect("required field not set"),
+                                        description: self.description,
+                                    }
+                                }
+                            }
+                        });
+        // The session is not set by the builder.
+        assert!(!expanded.contains(&quote!(pub fn session).to_string()));
+    }
+
+    #[test]
+    fn builder_with_required_fields() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise", builder)]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+                #[bambou(required)]
+                name: String,
+                #[bambou(required)]
+                enterprise_profile_id: String,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded,
+                        quote! {
+                            pub fn builder() -> EnterpriseBuilder<::bambou::builder::Missing,
+                                                                  ::bambou::builder::Missing>
+                        });
+        // Each setter only sets the type parameter of its field.
+        assert_contains(&expanded,
+                        quote! {
+                            pub fn name<__V>(self, value: __V)
+                                -> EnterpriseBuilder<::bambou::builder::Set, __R1>
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            pub fn enterprise_profile_id<__V>(self, value: __V)
+                                -> EnterpriseBuilder<__R0, ::bambou::builder::Set>
+                        });
+        assert_contains(&expanded,
+                        quote! {
+                            impl EnterpriseBuilder<::bambou::builder::Set,
+                                                   ::bambou::builder::Set>
+                        });
+    }
+
+    #[test]
+    fn builder_without_required_fields() {
+        let expanded = expand_str(r#"
+            #[bambou(path = "enterprise", builder)]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#)
+                .unwrap();
+        assert_contains(&expanded, quote!(pub fn builder() -> EnterpriseBuilder<>));
+        assert_contains(&expanded,
+                        quote! {
+                            impl EnterpriseBuilder<> {
+                                pub fn build(self) -> Enterprise {
+                                    Enterprise {
+                                        session: ::std::default::Default::default(),
+                                        id: self.id,
+                                    }
+                                }
+                            }
+                        });
+    }
+
+    #[test]
+    fn errors() {
+        let missing_path = r#"
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#;
+        assert_eq!(expand_str(missing_path).unwrap_err(),
+                   "the `path` attribute is required");
+
+        let unknown = r#"
+            #[bambou(path = "enterprise", parent = "me")]
+            struct Enterprise {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#;
+        assert_eq!(expand_str(unknown).unwrap_err(), "unknown attribute `parent`");
+
+        let tuple = r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise(Option<Session>, Option<String>);"#;
+        assert_eq!(expand_str(tuple).unwrap_err(),
+                   "only structs with named fields are supported");
+
+        let no_id = r#"
+            #[bambou(path = "enterprise")]
+            struct Enterprise {
+                session: Option<Session>,
+            }"#;
+        assert_eq!(expand_str(no_id).unwrap_err(), "no `id` field");
+
+        let no_api_key = r#"
+            #[bambou(path = "me", root)]
+            struct Me {
+                session: Option<Session>,
+                id: Option<String>,
+            }"#;
+        assert_eq!(expand_str(no_api_key).unwrap_err(), "no `api_key` field");
+
+        let generic_builder = r#"
+            #[bambou(path = "enterprise", builder)]
+            struct Enterprise<T> {
+                session: Option<Session>,
+                id: Option<String>,
+                extra: T,
+            }"#;
+        assert_eq!(expand_str(generic_builder).unwrap_err(),
+                   "the builder does not support generic structs");
+    }
+}
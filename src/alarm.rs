@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::Url;
+
+use CancellationToken;
+use Error;
+use Filter;
+use RequestOptions;
+use Session;
+use XNuageFilter;
+
+/// The severity of an alarm, from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Severity {
+    Info,
+    Warning,
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    fn all() -> [Severity; 5] {
+        [Severity::Info, Severity::Warning, Severity::Minor, Severity::Major, Severity::Critical]
+    }
+
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Minor => "MINOR",
+            Severity::Major => "MAJOR",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+}
+
+/// An alarm raised by the server.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Alarm {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub severity: Severity,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// The time the alarm was raised, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub acknowledged: bool,
+    /// The type of the entity the alarm is about, e.g. `"gateway"`.
+    #[serde(rename = "targetObject", default)]
+    pub target_object: Option<String>,
+    #[serde(rename = "errorCondition", default)]
+    pub error_condition: Option<i64>,
+}
+
+/// The alarms to fetch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AlarmQuery {
+    min_severity: Option<Severity>,
+    since: Option<i64>,
+}
+
+impl AlarmQuery {
+    pub fn new() -> Self {
+        AlarmQuery::default()
+    }
+
+    /// Only fetch the alarms at least as severe as `severity`.
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Only fetch the alarms raised after `timestamp`, in milliseconds since the Unix epoch.
+    pub fn since(mut self, timestamp: i64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    /// Return true if `alarm` matches the query, for instance to filter the alarms received on
+    /// the event channel.
+    pub fn matches(&self, alarm: &Alarm) -> bool {
+        self.min_severity.map_or(true, |min| alarm.severity >= min) &&
+        self.since.map_or(true, |since| alarm.timestamp > since)
+    }
+
+    /// Return the `X-Nuage-Filter` expression matching the query, if any.
+    fn filter(&self) -> Option<Filter> {
+        let severities = self.min_severity.and_then(|min| {
+            Severity::all()
+                .iter()
+                .filter(|s| **s >= min)
+                .map(|s| Filter::field("severity").eq(s.as_str()))
+                .fold(None, |severities: Option<Filter>, s| match severities {
+                    Some(severities) => Some(severities.or(s)),
+                    None => Some(s),
+                })
+        });
+        let since = self.since
+            .map(|since| Filter::field("timestamp").gt(since));
+        match (severities, since) {
+            (Some(severities), Some(since)) => Some(severities.and(since)),
+            (Some(severities), None) => Some(severities),
+            (None, since) => since,
+        }
+    }
+
+    /// Return the request options matching the query.
+    pub fn options(&self) -> RequestOptions {
+        match self.filter() {
+            Some(filter) => RequestOptions::new().header(XNuageFilter(filter.to_string())),
+            None => RequestOptions::new(),
+        }
+    }
+}
+
+/// Polls the alarms of an entity, and returns the new ones each time. It is created by
+/// `Session::watch_alarms`.
+///
+/// Several alarms may be raised in the same millisecond, and only some of them be fetched by a
+/// poll. So each poll fetches the alarms raised since the millisecond of the latest alarm
+/// returned, included, and skips the ones already returned.
+///
+/// As an iterator, it blocks between polls, and only ends once its cancellation token, if any,
+/// is cancelled. The watcher only polls: to be notified of the alarms as soon as they are
+/// raised, subscribe to them on the event channel with `PushCenter::subscribe_alarms` instead.
+pub struct AlarmWatcher {
+    session: Session,
+    url: Url,
+    query: AlarmQuery,
+    interval: Duration,
+    first: bool,
+    /// The time of the latest alarm returned.
+    latest: Option<i64>,
+    /// The IDs of the alarms returned that were raised at `latest`.
+    seen: HashSet<String>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl AlarmWatcher {
+    pub fn new(session: Session, url: Url, query: AlarmQuery, interval: Duration) -> Self {
+        AlarmWatcher {
+            session: session,
+            url: url,
+            query: query,
+            interval: interval,
+            first: true,
+            latest: None,
+            seen: HashSet::new(),
+            cancellation: None,
+        }
+    }
+
+    /// Stop watching when `token` is cancelled: the wait before the next poll is cut short, and
+    /// the iterator ends.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Fetch the alarms raised since the last poll, without waiting.
+    pub fn poll(&mut self) -> Result<Vec<Alarm>, Error> {
+        let mut options = self.next_query().options();
+        if let Some(ref token) = self.cancellation {
+            options = options.cancellation(token.clone());
+        }
+        let fetched = self.session.fetch_alarms_at(self.url.clone(), &options)?;
+        Ok(self.skip_seen(fetched))
+    }
+
+    /// Return the query of the next poll: the alarms raised at the time of the latest alarm
+    /// returned or after.
+    fn next_query(&self) -> AlarmQuery {
+        match self.latest {
+            Some(latest) => self.query.clone().since(latest - 1),
+            None => self.query.clone(),
+        }
+    }
+
+    /// Remove the alarms already returned from `fetched`, and remember the others.
+    fn skip_seen(&mut self, fetched: Vec<Alarm>) -> Vec<Alarm> {
+        let alarms = fetched
+            .into_iter()
+            .filter(|alarm| Some(alarm.timestamp) != self.latest || !self.seen.contains(&alarm.id))
+            .collect::<Vec<_>>();
+        if let Some(latest) = alarms.iter().map(|alarm| alarm.timestamp).max() {
+            if self.latest.map_or(true, |previous| latest > previous) {
+                self.latest = Some(latest);
+                self.seen.clear();
+            }
+        }
+        if let Some(latest) = self.latest {
+            self.seen
+                .extend(alarms
+                            .iter()
+                            .filter(|alarm| alarm.timestamp == latest)
+                            .map(|alarm| alarm.id.clone()));
+        }
+        alarms
+    }
+}
+
+impl Iterator for AlarmWatcher {
+    type Item = Result<Vec<Alarm>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first {
+            self.first = false;
+        } else {
+            match self.cancellation {
+                Some(ref token) => {
+                    token.wait(self.interval);
+                }
+                None => thread::sleep(self.interval),
+            }
+        }
+        match self.poll() {
+            Err(Error::Cancelled) => None,
+            _ if self.cancellation.as_ref().map_or(false, |token| token.is_cancelled()) => None,
+            alarms => Some(alarms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::Url;
+
+    use SessionBuilder;
+    use super::*;
+
+    fn watcher(query: AlarmQuery) -> AlarmWatcher {
+        let url = "https://vsd:8443/nuage/api/v5_0/";
+        let session = SessionBuilder::plain(url, "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap();
+        AlarmWatcher::new(session,
+                          Url::parse("https://vsd:8443/nuage/api/v5_0/alarms").unwrap(),
+                          query,
+                          Duration::from_secs(10))
+    }
+
+    fn alarm(id: &str, timestamp: i64) -> Alarm {
+        Alarm {
+            id: id.to_string(),
+            name: None,
+            severity: Severity::Major,
+            description: None,
+            reason: None,
+            timestamp: timestamp,
+            acknowledged: false,
+            target_object: None,
+            error_condition: None,
+        }
+    }
+
+    fn ids(alarms: &[Alarm]) -> Vec<&str> {
+        alarms.iter().map(|alarm| alarm.id.as_str()).collect()
+    }
+
+    #[test]
+    fn filter() {
+        let filter = |query: AlarmQuery| query.filter().map(|filter| filter.to_string());
+        assert_eq!(filter(AlarmQuery::new()), None);
+        assert_eq!(filter(AlarmQuery::new().since(1000)),
+                   Some("timestamp > 1000".to_string()));
+        assert_eq!(filter(AlarmQuery::new().min_severity(Severity::Critical)),
+                   Some(r#"severity == "CRITICAL""#.to_string()));
+        assert_eq!(filter(AlarmQuery::new()
+                              .min_severity(Severity::Major)
+                              .since(1000)),
+                   Some(r#"(severity == "MAJOR" or severity == "CRITICAL") and timestamp > 1000"#
+                            .to_string()));
+    }
+
+    #[test]
+    fn matches() {
+        let query = AlarmQuery::new()
+            .min_severity(Severity::Major)
+            .since(1000);
+        assert!(query.matches(&alarm("a", 1001)));
+        assert!(!query.matches(&alarm("a", 1000)));
+        let mut minor = alarm("a", 2000);
+        minor.severity = Severity::Minor;
+        assert!(!query.matches(&minor));
+        assert!(AlarmQuery::new().matches(&minor));
+    }
+
+    #[test]
+    fn cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut watcher = watcher(AlarmQuery::new()).cancellation(token);
+        assert!(watcher.next().is_none());
+    }
+
+    #[test]
+    fn first_poll() {
+        let query = AlarmQuery::new().since(1000);
+        assert_eq!(watcher(query.clone()).next_query(), query);
+    }
+
+    #[test]
+    fn same_millisecond() {
+        let mut watcher = watcher(AlarmQuery::new());
+        let alarms = watcher.skip_seen(vec![alarm("a", 1000), alarm("b", 2000)]);
+        assert_eq!(ids(&alarms), ["a", "b"]);
+        // The millisecond of the latest alarm is fetched again.
+        assert_eq!(watcher.next_query(), AlarmQuery::new().since(1999));
+
+        // `c` was raised in the same millisecond as `b`, but was not fetched by the first poll.
+        let alarms = watcher.skip_seen(vec![alarm("b", 2000), alarm("c", 2000)]);
+        assert_eq!(ids(&alarms), ["c"]);
+        assert_eq!(watcher.next_query(), AlarmQuery::new().since(1999));
+
+        let alarms = watcher.skip_seen(vec![alarm("b", 2000), alarm("c", 2000)]);
+        assert!(alarms.is_empty());
+
+        let alarms = watcher.skip_seen(vec![alarm("c", 2000), alarm("d", 3000)]);
+        assert_eq!(ids(&alarms), ["d"]);
+        assert_eq!(watcher.next_query(), AlarmQuery::new().since(2999));
+    }
+
+    #[test]
+    fn no_alarm() {
+        let mut watcher = watcher(AlarmQuery::new().since(1000));
+        assert!(watcher.skip_seen(Vec::new()).is_empty());
+        assert_eq!(watcher.next_query(), AlarmQuery::new().since(1000));
+    }
+}
@@ -0,0 +1,477 @@
+//! A non-blocking counterpart of `Session`, built on the asynchronous client of `reqwest` and
+//! `tokio-core`. Requests return futures, so many of them can be in flight at the same time
+//! without spawning a thread for each.
+//!
+//! ```rust,ignore
+//! let mut core = Core::new()?;
+//! let session = SessionBuilder::new(url, "csproot", "csproot", "csp")?
+//!     .build_async(&core.handle())?;
+//! let me = core.run(session.connect(Me::default()))?;
+//! let enterprises = core.run(me.fetch_children::<Enterprise>())?;
+//! ```
+//!
+//! Response validation, events, the request queue, re-authentication after the API key expired
+//! and the retries of throttled requests are only available with the blocking session. The
+//! builder options that asynchronous sessions do not support make `SessionBuilder::build_async`
+//! fail with `Error::Unsupported`.
+
+use std::mem;
+use std::sync::Arc;
+
+use futures::{future, stream, Future, Stream};
+use hyper::mime::Mime;
+use reqwest::{StatusCode, Url};
+use reqwest::header::{ContentLength, ContentType, Headers};
+use reqwest::unstable::async::{Client, Decoder, Response};
+use serde::{self, Serialize};
+use serde_json;
+
+use auth::{AuthProvider, RequestParts};
+use outcome::{self, DeleteOutcome};
+use fetcher::DEFAULT_PAGE_SIZE;
+use codec;
+use path;
+use {Codec, Error, Method};
+use {XNuageCount, XNuagePage, XNuagePageSize};
+
+/// A future resolving to a `T` or an `Error`.
+pub type AsyncResult<T> = Box<Future<Item = T, Error = Error>>;
+
+/// A stream of `T`, that fails with an `Error`.
+pub type AsyncStream<T> = Box<Stream<Item = T, Error = Error>>;
+
+/// The asynchronous counterpart of `RestEntity`. Entities hold a clone of the session they were
+/// fetched with, so the trait only requires the entity's description: the requests are
+/// implemented by default.
+pub trait AsyncRestEntity: Serialize + for<'de> serde::Deserialize<'de> + 'static {
+    /// Give a session to the entity.
+    fn set_session(&mut self, session: AsyncSession);
+
+    /// Return the session of the entity, if any.
+    fn get_session(&self) -> Option<&AsyncSession>;
+
+    /// Return the rest path of the entity, without its ID.
+    fn path() -> &'static str;
+
+    /// Return the rest path of the entity's group.
+    fn group_path() -> &'static str;
+
+    /// Return the ID of the entity, if known.
+    fn id(&self) -> Option<&str>;
+
+    /// Must return true if the entity is a root of the API and false otherwise.
+    fn is_root(&self) -> bool;
+
+    /// Called every time the entity's attributes are populated from a server response. Does
+    /// nothing by default. See `RestEntity::on_fetched`.
+    fn on_fetched(&mut self) {}
+
+    /// Called before the entity is sent to the server to be saved or created. Does nothing by
+    /// default.
+    fn on_saving(&mut self) {}
+
+    /// Called after the entity has been created on the server, and populated from the response.
+    /// Does nothing by default.
+    fn on_created(&mut self) {}
+
+    /// Return a custom body and content type to send instead of the serialized entity when it is
+    /// saved or created. By default, the entity is serialized with the session's codec. See
+    /// `RestEntity::raw_body`.
+    fn raw_body(&self) -> Option<(Mime, Vec<u8>)> {
+        None
+    }
+
+    /// Fetch the entity from the server, and resolve to the populated entity.
+    fn fetch(self) -> AsyncResult<Self>
+        where Self: Sized
+    {
+        match self.get_session().cloned() {
+            Some(session) => session.fetch_entity(self),
+            None => Box::new(future::err(Error::NoSession)),
+        }
+    }
+
+    /// Update the entity on the server, and resolve to the entity as returned by the server.
+    fn save(self) -> AsyncResult<Self>
+        where Self: Sized
+    {
+        match self.get_session().cloned() {
+            Some(session) => session.save(self),
+            None => Box::new(future::err(Error::NoSession)),
+        }
+    }
+
+    /// Delete the entity from the server.
+    fn delete(self) -> AsyncResult<DeleteOutcome>
+        where Self: Sized
+    {
+        match self.get_session().cloned() {
+            Some(session) => session.delete(self),
+            None => Box::new(future::err(Error::NoSession)),
+        }
+    }
+
+    /// Fetch the children of the entity.
+    fn fetch_children<C>(&self) -> AsyncResult<Vec<C>>
+        where Self: Sized,
+              C: AsyncRestEntity
+    {
+        match self.get_session() {
+            Some(session) => session.fetch_children(self),
+            None => Box::new(future::err(Error::NoSession)),
+        }
+    }
+
+    /// Create a child of the entity, and resolve to the child as populated by the server.
+    fn create_child<C>(&self, child: C) -> AsyncResult<C>
+        where Self: Sized,
+              C: AsyncRestEntity
+    {
+        match self.get_session() {
+            Some(session) => session.create_child(self, child),
+            None => Box::new(future::err(Error::NoSession)),
+        }
+    }
+}
+
+/// The asynchronous counterpart of `RestRootEntity`.
+pub trait AsyncRestRootEntity: AsyncRestEntity {
+    /// Return the API key for the current session.
+    fn get_api_key(&self) -> Option<&str>;
+}
+
+/// An asynchronous session, created with `SessionBuilder::build_async`. Clones of a session
+/// share the same client and authentication provider, and are cheap.
+#[derive(Clone, Debug)]
+pub struct AsyncSession {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    client: Client,
+    url: Url,
+    auth: Arc<AuthProvider>,
+    codec: Arc<Codec>,
+    max_response_size: Option<u64>,
+    query: Vec<(String, String)>,
+    headers: Headers,
+}
+
+impl AsyncSession {
+    #[doc(hidden)]
+    pub fn new(client: Client,
+               url: Url,
+               auth: Arc<AuthProvider>,
+               codec: Arc<Codec>,
+               max_response_size: Option<u64>,
+               query: Vec<(String, String)>,
+               headers: Headers)
+               -> Self {
+        AsyncSession {
+            inner: Arc::new(Inner {
+                                client: client,
+                                url: url,
+                                auth: auth,
+                                codec: codec,
+                                max_response_size: max_response_size,
+                                query: query,
+                                headers: headers,
+                            }),
+        }
+    }
+
+    /// Return the base URL of the API.
+    pub fn url(&self) -> &Url {
+        &self.inner.url
+    }
+
+    /// Fetch the root entity, and use the API key it holds for the subsequent requests.
+    pub fn connect<R>(&self, root: R) -> AsyncResult<R>
+        where R: AsyncRestRootEntity
+    {
+        let auth = self.inner.auth.clone();
+        Box::new(self.fetch_entity(root)
+                     .map(move |root| {
+                              auth.set_api_key(root.get_api_key().map(|s| s.to_string().into()));
+                              root
+                          }))
+    }
+
+    /// Fetch an entity, and resolve to the populated entity.
+    pub fn fetch_entity<E>(&self, entity: E) -> AsyncResult<E>
+        where E: AsyncRestEntity
+    {
+        let url = match self.entity_url(&entity) {
+            Ok(url) => url,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        self.single(Method::Get, url, Headers::new(), None)
+    }
+
+    /// Save an entity, and resolve to the entity as returned by the server.
+    pub fn save<E>(&self, mut entity: E) -> AsyncResult<E>
+        where E: AsyncRestEntity
+    {
+        entity.on_saving();
+        let mut headers = Headers::new();
+        let request = self.entity_url(&entity)
+            .and_then(|url| Ok((url, self.entity_body(&entity, &mut headers)?)));
+        match request {
+            Ok((url, body)) => self.single(Method::Put, url, headers, Some(body)),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// Delete an entity.
+    pub fn delete<E>(&self, entity: E) -> AsyncResult<DeleteOutcome>
+        where E: AsyncRestEntity
+    {
+        let url = match self.entity_url(&entity) {
+            Ok(url) => url,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let session = self.clone();
+        Box::new(self.send(Method::Delete, url, Headers::new(), None)
+                     .and_then(move |(status, headers, body)| {
+            let codec = codec::for_response(&*session.inner.codec, &headers);
+            match status {
+                status if status.is_success() => Ok(DeleteOutcome::Deleted),
+                StatusCode::NotFound => Ok(DeleteOutcome::AlreadyGone),
+                StatusCode::MultipleChoices => {
+                    let choices = outcome::Choices::decode(&body, codec)?;
+                    Ok(DeleteOutcome::ConfirmationRequired { choices: choices })
+                }
+                status => Err(Error::from_response_with(status, &body, codec)),
+            }
+        }))
+    }
+
+    /// Fetch the children of an entity.
+    pub fn fetch_children<P, C>(&self, parent: &P) -> AsyncResult<Vec<C>>
+        where P: AsyncRestEntity,
+              C: AsyncRestEntity
+    {
+        let url = match self.children_url::<P, C>(parent) {
+            Ok(url) => url,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        self.list(Method::Get, url, None)
+    }
+
+    /// Create a child entity, and resolve to the child as populated by the server.
+    pub fn create_child<P, C>(&self, parent: &P, mut child: C) -> AsyncResult<C>
+        where P: AsyncRestEntity,
+              C: AsyncRestEntity
+    {
+        child.on_saving();
+        let mut headers = Headers::new();
+        let request = self.children_url::<P, C>(parent)
+            .and_then(|url| Ok((url, self.entity_body(&child, &mut headers)?)));
+        match request {
+            Ok((url, body)) => {
+                Box::new(self.single(Method::Post, url, headers, Some(body))
+                             .map(|mut child: C| {
+                                      child.on_created();
+                                      child
+                                  }))
+            }
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// Return a stream of the children of an entity. The children are fetched page by page as
+    /// the stream is polled, until all of them have been returned.
+    pub fn iter_children<P, C>(&self, parent: &P) -> AsyncStream<C>
+        where P: AsyncRestEntity,
+              C: AsyncRestEntity
+    {
+        let url = match self.children_url::<P, C>(parent) {
+            Ok(url) => url,
+            Err(e) => return Box::new(stream::once(Err(e))),
+        };
+        let session = self.clone();
+        // The state is the number of the next page, or `None` once the last page was fetched.
+        let pages = stream::unfold(Some(0), move |page| {
+            let page = match page {
+                Some(page) => page,
+                None => return None,
+            };
+            let mut headers = Headers::new();
+            headers.set(XNuagePage(page));
+            headers.set(XNuagePageSize(DEFAULT_PAGE_SIZE));
+            let next = session
+                .page(Method::Get, url.clone(), headers, None)
+                .map(move |(children, count): (Vec<C>, Option<u64>)| {
+                    let fetched = u64::from(page + 1) * u64::from(DEFAULT_PAGE_SIZE);
+                    let done = match count {
+                        Some(count) => children.is_empty() || fetched >= count,
+                        None => children.len() < DEFAULT_PAGE_SIZE as usize,
+                    };
+                    (children, if done { None } else { Some(page + 1) })
+                });
+            Some(next)
+        });
+        Box::new(pages.map(stream::iter_ok).flatten())
+    }
+
+    /// Send a request and resolve to the single entity in the response.
+    fn single<E>(&self,
+                 method: Method,
+                 url: Url,
+                 headers: Headers,
+                 body: Option<Vec<u8>>)
+                 -> AsyncResult<E>
+        where E: AsyncRestEntity
+    {
+        Box::new(self.page(method, url, headers, body)
+                     .and_then(|(mut entities, _): (Vec<E>, _)| {
+                                   entities.pop().ok_or(Error::NoEntity)
+                               }))
+    }
+
+    /// Send a request and resolve to the entities in the response, with the session set.
+    fn list<E>(&self, method: Method, url: Url, body: Option<Vec<u8>>) -> AsyncResult<Vec<E>>
+        where E: AsyncRestEntity
+    {
+        Box::new(self.page(method, url, Headers::new(), body)
+                     .map(|(entities, _)| entities))
+    }
+
+    /// Send a request and resolve to the entities in the response, with the session set, and to
+    /// the total number of entities if the server sent it.
+    fn page<E>(&self,
+               method: Method,
+               url: Url,
+               headers: Headers,
+               body: Option<Vec<u8>>)
+               -> AsyncResult<(Vec<E>, Option<u64>)>
+        where E: AsyncRestEntity
+    {
+        let session = self.clone();
+        Box::new(self.send(method, url, headers, body)
+                     .and_then(move |(status, headers, body)| {
+            if !status.is_success() {
+                let codec = codec::for_response(&*session.inner.codec, &headers);
+                return Err(Error::from_response_with(status, &body, codec));
+            }
+            let count = headers.get::<XNuageCount>().map(|count| count.0);
+            // The server answers with an empty body when there is no entity to return.
+            if body.is_empty() {
+                return Ok((Vec::new(), count));
+            }
+            let value = session.inner.codec.decode(&body)?;
+            let mut entities: Vec<E> = serde_json::from_value(value)?;
+            for entity in &mut entities {
+                entity.set_session(session.clone());
+                entity.on_fetched();
+            }
+            Ok((entities, count))
+        }))
+    }
+
+    /// Send a request with `headers` on top of the session's, and resolve to the status, the
+    /// headers and the body of the response.
+    fn send(&self,
+            method: Method,
+            mut url: Url,
+            extra_headers: Headers,
+            body: Option<Vec<u8>>)
+            -> AsyncResult<(StatusCode, Headers, Vec<u8>)> {
+        if !self.inner.query.is_empty() {
+            let mut pairs = url.query_pairs_mut();
+            for &(ref name, ref value) in &self.inner.query {
+                pairs.append_pair(name, value);
+            }
+        }
+        let mut headers = self.inner.headers.clone();
+        self.inner.auth.apply(&mut headers);
+        headers.extend(extra_headers.iter());
+        let signed = self.inner
+            .auth
+            .sign(&RequestParts {
+                       method: &method,
+                       url: &url,
+                       body: body.as_ref().map(|b| b.as_slice()).unwrap_or(&[]),
+                   },
+                  &mut headers);
+        if let Err(e) = signed {
+            return Box::new(future::err(e));
+        }
+        let mut request = self.inner.client.request(method, url);
+        request.headers(headers);
+        if let Some(body) = body {
+            request.body(body);
+        }
+        let limit = self.inner.max_response_size;
+        Box::new(request
+                     .send()
+                     .map_err(Error::from)
+                     .and_then(move |resp| read(resp, limit)))
+    }
+
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+        where T: Serialize
+    {
+        self.inner.codec.encode(&serde_json::to_value(value)?)
+    }
+
+    /// Return the body to send for an entity, and set the matching content type.
+    fn entity_body<E>(&self, entity: &E, headers: &mut Headers) -> Result<Vec<u8>, Error>
+        where E: AsyncRestEntity
+    {
+        match entity.raw_body() {
+            Some((content_type, body)) => {
+                headers.set(ContentType(content_type));
+                Ok(body)
+            }
+            None => self.encode(entity),
+        }
+    }
+
+    fn children_url<P, C>(&self, parent: &P) -> Result<Url, Error>
+        where P: AsyncRestEntity,
+              C: AsyncRestEntity
+    {
+        if parent.is_root() {
+            path::group(&self.inner.url, None, C::group_path())
+        } else {
+            let id = parent.id().ok_or(Error::MissingId)?;
+            path::group(&self.inner.url, Some((P::path(), id)), C::group_path())
+        }
+    }
+
+    fn entity_url<E>(&self, entity: &E) -> Result<Url, Error>
+        where E: AsyncRestEntity
+    {
+        if entity.is_root() {
+            return path::root(&self.inner.url, E::path());
+        }
+        let id = entity.id().ok_or(Error::MissingId)?;
+        path::entity(&self.inner.url, E::path(), id)
+    }
+}
+
+/// Read the whole body of a response, failing if it is larger than `limit` bytes.
+fn read(mut resp: Response, limit: Option<u64>) -> AsyncResult<(StatusCode, Headers, Vec<u8>)> {
+    if let (Some(limit), Some(&ContentLength(length))) =
+        (limit, resp.headers().get::<ContentLength>()) {
+        if length > limit {
+            return Box::new(future::err(Error::ResponseTooLarge(limit)));
+        }
+    }
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = mem::replace(resp.body_mut(), Decoder::empty());
+    Box::new(body.map_err(Error::from)
+                 .fold(Vec::new(), move |mut buf, chunk| {
+                         buf.extend_from_slice(&chunk);
+                         match limit {
+                             Some(limit) if buf.len() as u64 > limit => {
+                                 Err(Error::ResponseTooLarge(limit))
+                             }
+                             _ => Ok(buf),
+                         }
+                     })
+                 .map(move |buf| (status, headers, buf)))
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use reqwest::Url;
+use reqwest::header::{Headers, Authorization, Basic};
+
+use Error;
+use Method;
+use Secret;
+
+/// The parts of a request that a provider may need to authenticate it.
+#[derive(Debug)]
+pub struct RequestParts<'r> {
+    pub method: &'r Method,
+    /// The full URL of the request, including the query.
+    pub url: &'r Url,
+    /// The body of the request, empty if there is none.
+    pub body: &'r [u8],
+}
+
+/// An authentication scheme. The provider sets the authentication headers of every request sent
+/// by a session.
+///
+/// Providers are shared between threads, so the state they keep, such as an API key, must be
+/// behind a lock.
+pub trait AuthProvider: fmt::Debug + Send + Sync {
+    /// Set the authentication headers of a request. It is called right before every attempt to
+    /// send a request, and the headers are dropped once the request is sent, so providers should
+    /// not keep the encoded credentials around either.
+    fn apply(&self, headers: &mut Headers);
+
+    /// Set the authentication headers that depend on the request itself, such as a signature. It
+    /// is called after `apply`, once all the other headers have been set. Does nothing by
+    /// default.
+    fn sign(&self, _request: &RequestParts, _headers: &mut Headers) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Return the API key currently used by the provider, if any.
+    fn api_key(&self) -> Option<Secret> {
+        None
+    }
+
+    /// Called when the server hands out a new API key, for instance after connecting. Providers
+    /// that do not use API keys can ignore it, which is the default.
+    fn set_api_key(&self, _api_key: Option<Secret>) {}
+
+    /// Return true if the session can obtain a new API key by connecting again with this
+    /// provider, when a request is rejected because the API key expired. Providers that only
+    /// hold an API key must return false, since connecting again would send the same key.
+    /// Returns true by default.
+    fn can_reauthenticate(&self) -> bool {
+        true
+    }
+}
+
+/// The default authentication scheme: HTTP basic authentication with the user's password, and
+/// then with the API key obtained when connecting.
+#[derive(Debug)]
+pub struct BasicAuth {
+    username: String,
+    password: Secret,
+    api_key: RwLock<Option<Secret>>,
+}
+
+impl BasicAuth {
+    pub fn new(username: &str, password: Secret) -> Self {
+        BasicAuth {
+            username: username.to_owned(),
+            password: password,
+            api_key: RwLock::new(None),
+        }
+    }
+}
+
+impl AuthProvider for BasicAuth {
+    fn apply(&self, headers: &mut Headers) {
+        // Authorization: base64("login:password")
+        // or if we have an API Key already:
+        // Authorization: base64("login:api_key")
+        let api_key = self.api_key.read().unwrap();
+        let password = api_key.as_ref().unwrap_or(&self.password);
+        headers.set(Authorization(Basic {
+                                      username: self.username.clone(),
+                                      password: Some(password.expose().to_owned()),
+                                  }));
+    }
+
+    fn api_key(&self) -> Option<Secret> {
+        self.api_key.read().unwrap().clone()
+    }
+
+    fn set_api_key(&self, api_key: Option<Secret>) {
+        *self.api_key.write().unwrap() = api_key;
+    }
+}
+
+/// HTTP basic authentication with an API key only. The password is never sent.
+#[derive(Debug)]
+pub struct ApiKeyAuth {
+    username: String,
+    api_key: RwLock<Secret>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(username: &str, api_key: Secret) -> Self {
+        ApiKeyAuth {
+            username: username.to_owned(),
+            api_key: RwLock::new(api_key),
+        }
+    }
+}
+
+impl AuthProvider for ApiKeyAuth {
+    fn apply(&self, headers: &mut Headers) {
+        headers.set(Authorization(Basic {
+                                      username: self.username.clone(),
+                                      password: Some(self.api_key
+                                                         .read()
+                                                         .unwrap()
+                                                         .expose()
+                                                         .to_owned()),
+                                  }));
+    }
+
+    fn api_key(&self) -> Option<Secret> {
+        Some(self.api_key.read().unwrap().clone())
+    }
+
+    fn set_api_key(&self, api_key: Option<Secret>) {
+        if let Some(api_key) = api_key {
+            *self.api_key.write().unwrap() = api_key;
+        }
+    }
+
+    fn can_reauthenticate(&self) -> bool {
+        false
+    }
+}
+
+/// Authentication with a TLS client certificate, configured with `SessionBuilder::identity` or
+/// `SessionBuilder::client_cert_pem`. The certificate authenticates the connection, so no
+/// password is ever sent: only the API key obtained when connecting is, once known.
+#[derive(Debug)]
+pub struct CertificateAuth {
+    username: String,
+    api_key: RwLock<Option<Secret>>,
+}
+
+impl CertificateAuth {
+    pub fn new(username: &str) -> Self {
+        CertificateAuth {
+            username: username.to_owned(),
+            api_key: RwLock::new(None),
+        }
+    }
+}
+
+impl AuthProvider for CertificateAuth {
+    fn apply(&self, headers: &mut Headers) {
+        match *self.api_key.read().unwrap() {
+            Some(ref api_key) => {
+                headers.set(Authorization(Basic {
+                                              username: self.username.clone(),
+                                              password: Some(api_key.expose().to_owned()),
+                                          }))
+            }
+            None => headers.remove::<Authorization<Basic>>(),
+        };
+    }
+
+    fn api_key(&self) -> Option<Secret> {
+        self.api_key.read().unwrap().clone()
+    }
+
+    fn set_api_key(&self, api_key: Option<Secret>) {
+        *self.api_key.write().unwrap() = api_key;
+    }
+}
@@ -0,0 +1,185 @@
+use std::{error, fmt};
+use std::io::{self, Read};
+
+use reqwest::header::ContentLength;
+use serde::Deserialize;
+use serde_json::{self, Category};
+
+use Error;
+use Response;
+
+/// A response body, buffered in memory. Values that borrow from the body (`&str` or `Cow<str>`
+/// fields for instance) can be deserialized from it without copying, which is useful to scan
+/// large collections without allocating every string.
+pub struct Buffer {
+    response: Response,
+    bytes: Vec<u8>,
+}
+
+impl Buffer {
+    pub fn new(response: Response, bytes: Vec<u8>) -> Self {
+        Buffer {
+            response: response,
+            bytes: bytes,
+        }
+    }
+
+    /// Return the response the body was read from.
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    /// Return the raw body.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Deserialize the body, which must be JSON. The returned value may borrow from the buffer.
+    pub fn deserialize<'de, T>(&'de self) -> Result<T, Error>
+        where T: Deserialize<'de>
+    {
+        Ok(serde_json::from_slice(&self.bytes)?)
+    }
+}
+
+/// A reader that fails once more than `limit` bytes have been read from the underlying reader.
+/// The error it returns is turned into `Error::ResponseTooLarge` by `from_io`.
+pub struct Limited<R> {
+    inner: R,
+    limit: Option<u64>,
+    remaining: Option<u64>,
+}
+
+impl<R: Read> Limited<R> {
+    pub fn new(inner: R, limit: Option<u64>) -> Self {
+        Limited {
+            inner: inner,
+            limit: limit,
+            remaining: limit,
+        }
+    }
+}
+
+/// The error returned by `Limited` when the body exceeds the limit. It is private, so that it
+/// cannot be mistaken for an error of the underlying reader.
+#[derive(Debug)]
+struct TooLarge(u64);
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "response body exceeds the size limit of {} bytes", self.0)
+    }
+}
+
+impl error::Error for TooLarge {
+    fn description(&self) -> &str {
+        "response body exceeds the size limit"
+    }
+}
+
+/// Convert an error returned while reading a body through `Limited`.
+pub fn from_io(e: io::Error) -> Error {
+    let too_large = e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<TooLarge>())
+        .map(|too_large| too_large.0);
+    match too_large {
+        Some(limit) => Error::ResponseTooLarge(limit),
+        None => Error::Io(e),
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => return self.inner.read(buf),
+        };
+        // Read one byte more than allowed, so that we can tell the difference between a body that
+        // is exactly `limit` bytes long and a body that is too large.
+        let max = ::std::cmp::min(buf.len() as u64, remaining + 1) as usize;
+        let n = self.inner.read(&mut buf[..max])?;
+        if n as u64 > remaining {
+            let limit = self.limit.unwrap_or(remaining);
+            return Err(io::Error::new(io::ErrorKind::Other, TooLarge(limit)));
+        }
+        self.remaining = Some(remaining - n as u64);
+        Ok(n)
+    }
+}
+
+/// Convert an error returned while deserializing a body read through `Limited`.
+pub fn from_json(e: serde_json::Error) -> Error {
+    match e.classify() {
+        Category::Io => from_io(e.into()),
+        _ => Error::Json(e),
+    }
+}
+
+/// Check the announced size of a response against the limit, without reading the body.
+pub fn check_length(resp: &Response, limit: Option<u64>) -> Result<(), Error> {
+    if let (Some(limit), Some(&ContentLength(length))) =
+        (limit, resp.headers().get::<ContentLength>()) {
+        if length > limit {
+            return Err(Error::ResponseTooLarge(limit));
+        }
+    }
+    Ok(())
+}
+
+/// Read the whole body of a response, failing if it is larger than `limit` bytes.
+pub fn read(resp: &mut Response, limit: Option<u64>) -> Result<Vec<u8>, Error> {
+    check_length(resp, limit)?;
+    let mut buf = Vec::new();
+    Limited::new(resp, limit)
+        .read_to_end(&mut buf)
+        .map_err(from_io)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read};
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::Headers;
+
+    use {Error, Response};
+    use super::*;
+
+    fn response<R: Read + Send + 'static>(body: R) -> Response {
+        Response::new(StatusCode::Ok,
+                      Headers::new(),
+                      Url::parse("https://vsd:8443/nuage/api/v5_0/me").unwrap(),
+                      Box::new(body))
+    }
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "connection reset"))
+        }
+    }
+
+    #[test]
+    fn within_limit() {
+        let body = read(&mut response(Cursor::new(b"[{}]".to_vec())), Some(4)).unwrap();
+        assert_eq!(body, b"[{}]");
+    }
+
+    #[test]
+    fn over_limit() {
+        match read(&mut response(Cursor::new(b"[{}]".to_vec())), Some(3)) {
+            Err(Error::ResponseTooLarge(3)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other_errors_are_not_too_large() {
+        match read(&mut response(Failing), Some(3)) {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::Other => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
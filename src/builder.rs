@@ -0,0 +1,10 @@
+//! The markers used by the builders generated by `#[derive(RestEntity)]`, to track at compile
+//! time which required fields are set.
+
+/// A required field that is not set yet.
+#[derive(Debug)]
+pub struct Missing;
+
+/// A required field that is set.
+#[derive(Debug)]
+pub struct Set;
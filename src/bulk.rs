@@ -0,0 +1,48 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+use std::thread;
+
+use Session;
+
+/// Apply `f` to every item, on up to `parallelism` threads that each hold a clone of the
+/// session, and return the results in the order of the items.
+pub fn run<T, R, F>(session: &Session, items: Vec<T>, parallelism: usize, f: F) -> Vec<R>
+    where T: Send + 'static,
+          R: Send + 'static,
+          F: Fn(&Session, T) -> R + Send + Sync + 'static
+{
+    let len = items.len();
+    if parallelism <= 1 || len <= 1 {
+        return items.into_iter().map(|item| f(session, item)).collect();
+    }
+
+    let pending = Arc::new(Mutex::new(items.into_iter().enumerate()));
+    let f = Arc::new(f);
+    let (sender, receiver) = channel();
+    for _ in 0..parallelism.min(len) {
+        let pending = pending.clone();
+        let f = f.clone();
+        let sender = sender.clone();
+        let session = session.clone();
+        thread::spawn(move || loop {
+            // The lock is released before the item is processed.
+            let next = pending.lock().unwrap().next();
+            match next {
+                Some((index, item)) => {
+                    let _ = sender.send((index, f(&session, item)));
+                }
+                None => return,
+            }
+        });
+    }
+    drop(sender);
+
+    let mut results = (0..len).map(|_| None).collect::<Vec<_>>();
+    for (index, result) in receiver {
+        results[index] = Some(result);
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("bulk worker exited before finishing its item"))
+        .collect()
+}
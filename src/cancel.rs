@@ -0,0 +1,67 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use Error;
+
+/// A token to abort long-running operations from another thread, for instance when the user of
+/// a UI closes the view that started them.
+///
+/// The token is given to a `Fetcher`, to `RequestOptions` or to a `PushCenter`, and shared with
+/// the thread that may cancel them. Cancellation is checked between requests, between the pages
+/// of a paginated fetch and between the children of a stream: a request already sent is not
+/// interrupted, but nothing is sent after it and the waits before retrying are cut short. The
+/// cancelled operations fail with `Error::Cancelled`.
+///
+/// ```rust,ignore
+/// let token = CancellationToken::new();
+/// let fetcher = session.fetcher::<_, VPort>(&enterprise)?.cancellation(token.clone());
+/// thread::spawn(move || for vport in fetcher.iter() { /* ... */ });
+/// // Later, from the UI thread:
+/// token.cancel();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Cancel the operations using this token, or any of its clones. This cannot be undone.
+    pub fn cancel(&self) {
+        let (ref cancelled, ref condvar) = *self.inner;
+        *cancelled.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    /// Return whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Fail with `Error::Cancelled` if the token was cancelled.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Wait for `timeout`, or until the token is cancelled. Return whether it was cancelled.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (ref cancelled, ref condvar) = *self.inner;
+        let mut cancelled = cancelled.lock().unwrap();
+        while !*cancelled {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            cancelled = condvar.wait_timeout(cancelled, deadline - now).unwrap().0;
+        }
+        *cancelled
+    }
+}
@@ -0,0 +1,47 @@
+//! Platform keyring integration (Secret Service, Keychain or the Windows credential store).
+//!
+//! Credentials are stored under a service name chosen by the application, and keyed by user
+//! name. API keys are stored under the `<service>-api-key` service so that they do not overwrite
+//! the password.
+
+use keyring::Keyring;
+
+use Error;
+use Secret;
+
+fn api_key_service(service: &str) -> String {
+    format!("{}-api-key", service)
+}
+
+fn get(service: &str, username: &str) -> Result<Secret, Error> {
+    Keyring::new(service, username)
+        .get_password()
+        .map(Secret::from)
+        .map_err(|e| Error::Keyring(e.to_string()))
+}
+
+fn set(service: &str, username: &str, secret: &str) -> Result<(), Error> {
+    Keyring::new(service, username)
+        .set_password(secret)
+        .map_err(|e| Error::Keyring(e.to_string()))
+}
+
+/// Read the password of `username` from the keyring.
+pub fn get_password(service: &str, username: &str) -> Result<Secret, Error> {
+    get(service, username)
+}
+
+/// Store the password of `username` in the keyring.
+pub fn set_password(service: &str, username: &str, password: &str) -> Result<(), Error> {
+    set(service, username, password)
+}
+
+/// Read the API key of `username` from the keyring.
+pub fn get_api_key(service: &str, username: &str) -> Result<Secret, Error> {
+    get(&api_key_service(service), username)
+}
+
+/// Store the API key of `username` in the keyring.
+pub fn set_api_key(service: &str, username: &str, api_key: &str) -> Result<(), Error> {
+    set(&api_key_service(service), username, api_key)
+}
@@ -0,0 +1,138 @@
+use serde::Serialize;
+use serde_json::{self, Value, Map};
+
+use Error;
+
+/// A change of a single attribute between two versions of an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    /// The serialized name of the attribute, e.g. `"externalID"`.
+    pub field: String,
+    /// The value in the first version, or `Value::Null` if the attribute was absent.
+    pub old: Value,
+    /// The value in the second version, or `Value::Null` if the attribute was absent.
+    pub new: Value,
+}
+
+/// Compare the serialized attributes of `old` and `new` and return the attributes that differ,
+/// sorted by name.
+pub fn diff<T>(old: &T, new: &T) -> Result<Vec<FieldChange>, Error>
+    where T: Serialize
+{
+    Ok(diff_values(&serde_json::to_value(old)?, &serde_json::to_value(new)?))
+}
+
+/// Compare two serialized entities. If they are not both JSON objects, they are compared as a
+/// whole and the change is reported with an empty field name.
+pub fn diff_values(old: &Value, new: &Value) -> Vec<FieldChange> {
+    match (old.as_object(), new.as_object()) {
+        (Some(old), Some(new)) => diff_objects(old, new),
+        _ if old != new => {
+            vec![FieldChange {
+                     field: String::new(),
+                     old: old.clone(),
+                     new: new.clone(),
+                 }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn diff_objects(old: &Map<String, Value>, new: &Map<String, Value>) -> Vec<FieldChange> {
+    let mut fields = old.keys().chain(new.keys()).collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let old = old.get(field).unwrap_or(&Value::Null);
+            let new = new.get(field).unwrap_or(&Value::Null);
+            if old == new {
+                None
+            } else {
+                Some(FieldChange {
+                         field: field.clone(),
+                         old: old.clone(),
+                         new: new.clone(),
+                     })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, Value};
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Enterprise {
+        name: String,
+        #[serde(rename = "externalID", skip_serializing_if = "Option::is_none")]
+        external_id: Option<String>,
+        description: Option<String>,
+    }
+
+    fn value(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn changed_fields() {
+        let old = Enterprise {
+            name: "acme".to_string(),
+            external_id: None,
+            description: Some("corporation".to_string()),
+        };
+        let new = Enterprise {
+            name: "acme corp".to_string(),
+            external_id: Some("ext-1".to_string()),
+            description: Some("corporation".to_string()),
+        };
+        assert_eq!(diff(&old, &new).unwrap(),
+                   vec![FieldChange {
+                            field: "externalID".to_string(),
+                            old: Value::Null,
+                            new: Value::String("ext-1".to_string()),
+                        },
+                        FieldChange {
+                            field: "name".to_string(),
+                            old: Value::String("acme".to_string()),
+                            new: Value::String("acme corp".to_string()),
+                        }]);
+        assert!(diff(&old, &old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn removed_field() {
+        let changes = diff_values(&value(r#"{"a": 1, "b": 2}"#), &value(r#"{"a": 1}"#));
+        assert_eq!(changes,
+                   vec![FieldChange {
+                            field: "b".to_string(),
+                            old: value("2"),
+                            new: Value::Null,
+                        }]);
+    }
+
+    #[test]
+    fn nested_values() {
+        let changes = diff_values(&value(r#"{"a": {"b": [1, 2]}}"#),
+                                  &value(r#"{"a": {"b": [1, 3]}}"#));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "a");
+        assert_eq!(changes[0].new, value(r#"{"b": [1, 3]}"#));
+    }
+
+    #[test]
+    fn not_objects() {
+        assert_eq!(diff_values(&value("1"), &value("2")),
+                   vec![FieldChange {
+                            field: String::new(),
+                            old: value("1"),
+                            new: value("2"),
+                        }]);
+        assert!(diff_values(&value("[1]"), &value("[1]")).is_empty());
+    }
+}
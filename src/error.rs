@@ -1,14 +1,65 @@
-use std::{fmt, error};
+use std::{fmt, error, io};
+use std::time::Duration;
 use reqwest;
+use reqwest::{StatusCode, Url};
 use hyper;
+use serde_json;
+
+use codec::{Codec, Json};
 
 #[derive(Debug)]
 pub enum Error {
     InvalidUrl(hyper::error::ParseError),
     Reqwest(reqwest::Error),
+    Json(serde_json::Error),
+    /// A response body could not be deserialized into the expected type.
+    Deserialize {
+        source: serde_json::Error,
+        /// The start of the body, with the values of the attributes that look like credentials
+        /// redacted.
+        body_snippet: String,
+        /// The URL of the response.
+        url: Url,
+    },
+    Io(io::Error),
     MissingId,
     NoEntity,
     NoSession,
+    ResponseTooLarge(u64),
+    Codec(String),
+    Keyring(String),
+    UnexpectedStatus(StatusCode),
+    Negotiate(String),
+    MissingKey,
+    DuplicateKey(usize),
+    /// The entity changed on the server since its `ETag` was obtained, so the request was
+    /// rejected with `412 Precondition Failed`. Fetch the entity again and reapply the changes.
+    /// A `409 Conflict` status is not reported as such: the VSD describes it in the body, so it
+    /// is an `Error::Api`.
+    Conflict,
+    MissingCount,
+    /// The operation was cancelled with a `CancellationToken`.
+    Cancelled,
+    /// The operation was not finished after the given time, such as a job followed with
+    /// `Session::wait_job`.
+    Timeout(Duration),
+    /// An option of the session builder is not supported by the kind of session being built.
+    Unsupported(&'static str),
+    /// A job run by `Session::run_job` or followed with `Session::wait_job` failed.
+    JobFailed {
+        /// The operation run by the job, e.g. `"EXPORT"`.
+        command: String,
+        /// The reason why the job failed, as given by the server.
+        result: Option<serde_json::Value>,
+    },
+    /// The server rejected the request, and described why in the body of the response.
+    Api {
+        status: StatusCode,
+        internal_error_code: Option<i64>,
+        errors: Vec<ApiError>,
+        /// The raw body of the response.
+        body: Vec<u8>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -16,11 +67,56 @@ impl fmt::Display for Error {
         match *self {
             Error::InvalidUrl(ref e) => fmt::Display::fmt(e, f),
             Error::Reqwest(ref e) => fmt::Display::fmt(e, f),
+            Error::Json(ref e) => fmt::Display::fmt(e, f),
+            Error::Deserialize { ref source, ref body_snippet, ref url } => {
+                write!(f,
+                       "Failed to deserialize the response from {}: {}. Body: {}",
+                       url,
+                       source,
+                       body_snippet)
+            }
+            Error::Io(ref e) => fmt::Display::fmt(e, f),
             Error::MissingId => f.write_str("The entity does not have an ID"),
             Error::NoEntity => f.write_str("No entity in response body"),
             Error::NoSession => {
                 f.write_str("Entities must hold a reference to a session to perform ReST requests")
             }
+            Error::ResponseTooLarge(limit) => {
+                write!(f, "The response body exceeds the maximum size of {} bytes", limit)
+            }
+            Error::Codec(ref msg) => write!(f, "Failed to encode or decode a body: {}", msg),
+            Error::Keyring(ref msg) => write!(f, "Keyring error: {}", msg),
+            Error::UnexpectedStatus(status) => write!(f, "Unexpected response status: {}", status),
+            Error::Negotiate(ref msg) => write!(f, "SPNEGO authentication failed: {}", msg),
+            Error::MissingKey => f.write_str("The entity does not have an alternate key"),
+            Error::DuplicateKey(n) => write!(f, "{} entities have the same key", n),
+            Error::Conflict => f.write_str("The entity changed on the server since it was fetched"),
+            Error::MissingCount => {
+                f.write_str("The response does not have an X-Nuage-Count header")
+            }
+            Error::Cancelled => f.write_str("The operation was cancelled"),
+            Error::Timeout(timeout) => {
+                write!(f, "The operation was not finished after {} seconds", timeout.as_secs())
+            }
+            Error::Unsupported(option) => {
+                write!(f, "The `{}` option is not supported by this kind of session", option)
+            }
+            Error::JobFailed { ref command, ref result } => {
+                write!(f, "The {} job failed", command)?;
+                match *result {
+                    Some(ref result) => write!(f, ": {}", result),
+                    None => Ok(()),
+                }
+            }
+            Error::Api { status, ref errors, .. } => {
+                write!(f, "The server rejected the request: {}", status)?;
+                for error in errors {
+                    for description in &error.descriptions {
+                        write!(f, "; {}", description.description)?;
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -30,9 +126,26 @@ impl error::Error for Error {
         match *self {
             Error::InvalidUrl(ref e) => e.description(),
             Error::Reqwest(ref e) => e.description(),
+            Error::Json(ref e) => e.description(),
+            Error::Deserialize { .. } => "Failed to deserialize the response",
+            Error::Io(ref e) => e.description(),
             Error::MissingId => "The entity does not have an ID",
             Error::NoEntity => "No entity in response body",
             Error::NoSession => "Entities must hold a reference to a session to perform ReST requests",
+            Error::ResponseTooLarge(_) => "The response body exceeds the maximum size",
+            Error::Codec(_) => "Failed to encode or decode a body",
+            Error::Keyring(_) => "Keyring error",
+            Error::UnexpectedStatus(_) => "Unexpected response status",
+            Error::Negotiate(_) => "SPNEGO authentication failed",
+            Error::MissingKey => "The entity does not have an alternate key",
+            Error::DuplicateKey(_) => "Several entities have the same key",
+            Error::Conflict => "The entity changed on the server since it was fetched",
+            Error::MissingCount => "The response does not have an X-Nuage-Count header",
+            Error::Cancelled => "The operation was cancelled",
+            Error::Timeout(_) => "The operation was not finished in time",
+            Error::Unsupported(_) => "An option is not supported by this kind of session",
+            Error::JobFailed { .. } => "The job failed",
+            Error::Api { .. } => "The server rejected the request",
         }
     }
 
@@ -40,11 +153,69 @@ impl error::Error for Error {
         match *self {
             Error::InvalidUrl(ref e) => Some(e),
             Error::Reqwest(ref e) => Some(e),
+            Error::Json(ref e) => Some(e),
+            Error::Deserialize { ref source, .. } => Some(source),
+            Error::Io(ref e) => Some(e),
             _ => None,
         }
     }
 }
 
+impl Error {
+    /// Return the error matching a response with a non-success `status`, from the error
+    /// description in its JSON body. If the body cannot be parsed, the error has no description.
+    /// A `412 Precondition Failed` status is reported as `Error::Conflict`.
+    pub fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        Error::from_response_with(status, body, &Json)
+    }
+
+    /// Return the error matching a response with a non-success `status`, like `from_response`,
+    /// for a body encoded with `codec`.
+    pub fn from_response_with(status: StatusCode, body: &[u8], codec: &Codec) -> Self {
+        if status == StatusCode::PreconditionFailed {
+            return Error::Conflict;
+        }
+        let parsed: ErrorBody = codec
+            .decode(body)
+            .ok()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Error::Api {
+            status: status,
+            internal_error_code: parsed.internal_error_code,
+            errors: parsed.errors,
+            body: body.to_vec(),
+        }
+    }
+}
+
+/// An error described by the server, about one of the attributes of an entity or about the
+/// request as a whole.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ApiError {
+    /// The attribute the error is about, if any.
+    #[serde(default)]
+    pub property: Option<String>,
+    #[serde(default)]
+    pub descriptions: Vec<ErrorDescription>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ErrorDescription {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Default, Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    errors: Vec<ApiError>,
+    #[serde(rename = "internalErrorCode", default)]
+    internal_error_code: Option<i64>,
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Error::Reqwest(err)
@@ -56,3 +227,15 @@ impl From<hyper::error::ParseError> for Error {
         Error::InvalidUrl(err)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,149 @@
+use reqwest::Url;
+use serde_json::Value;
+
+use Error;
+use Filter;
+use RequestOptions;
+use Session;
+use XNuageFilter;
+
+/// An entry of the server's event log, recording a change made to one or more entities.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct EventLogEntry {
+    #[serde(rename = "ID")]
+    pub id: String,
+    /// The ID of the request that caused the change.
+    #[serde(rename = "requestID", default)]
+    pub request_id: Option<String>,
+    /// The kind of change, e.g. `"CREATE"`, `"UPDATE"` or `"DELETE"`.
+    #[serde(rename = "type", default)]
+    pub event_type: Option<String>,
+    /// The type of the entity that changed, e.g. `"enterprise"`.
+    #[serde(rename = "entityType", default)]
+    pub entity_type: Option<String>,
+    #[serde(rename = "entityID", default)]
+    pub entity_id: Option<String>,
+    #[serde(rename = "entityParentType", default)]
+    pub entity_parent_type: Option<String>,
+    #[serde(rename = "entityParentID", default)]
+    pub entity_parent_id: Option<String>,
+    /// The user who made the change.
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub enterprise: Option<String>,
+    /// The time the change was recorded, in milliseconds since the Unix epoch.
+    #[serde(rename = "eventReceivedTime", default)]
+    pub event_received_time: i64,
+    /// The entities as they were after the change.
+    #[serde(default)]
+    pub entities: Vec<Value>,
+}
+
+/// Fetches the entries of the event log, optionally filtered by time and entity type. It is
+/// created by `Session::events` or `Session::events_of`.
+///
+/// ```rust,ignore
+/// let entries = session.events()?
+///     .since(1500000000000)
+///     .entity_type("enterprise")
+///     .fetch()?;
+/// ```
+#[derive(Debug)]
+pub struct EventLog {
+    session: Session,
+    url: Url,
+    since: Option<i64>,
+    entity_types: Vec<String>,
+}
+
+impl EventLog {
+    pub fn new(session: Session, url: Url) -> Self {
+        EventLog {
+            session: session,
+            url: url,
+            since: None,
+            entity_types: Vec::new(),
+        }
+    }
+
+    /// Only fetch the entries recorded after `timestamp`, in milliseconds since the Unix epoch.
+    pub fn since(mut self, timestamp: i64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    /// Only fetch the entries about entities of the given type, e.g. `"enterprise"`. When called
+    /// several times, the entries about any of the types are fetched.
+    pub fn entity_type(mut self, entity_type: &str) -> Self {
+        self.entity_types.push(entity_type.to_owned());
+        self
+    }
+
+    /// Fetch the matching entries.
+    pub fn fetch(&self) -> Result<Vec<EventLogEntry>, Error> {
+        self.session.fetch_event_log_at(self.url.clone(), &self.options())
+    }
+
+    /// Return the `X-Nuage-Filter` expression matching the query, if any.
+    fn filter(&self) -> Option<Filter> {
+        let types = self.entity_types
+            .iter()
+            .map(|t| Filter::field("entityType").eq(t.as_str()))
+            .fold(None, |types: Option<Filter>, t| match types {
+                Some(types) => Some(types.or(t)),
+                None => Some(t),
+            });
+        let since = self.since
+            .map(|since| Filter::field("eventReceivedTime").gt(since));
+        match (types, since) {
+            (Some(types), Some(since)) => Some(types.and(since)),
+            (Some(types), None) => Some(types),
+            (None, since) => since,
+        }
+    }
+
+    fn options(&self) -> RequestOptions {
+        match self.filter() {
+            Some(filter) => RequestOptions::new().header(XNuageFilter(filter.to_string())),
+            None => RequestOptions::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use SessionBuilder;
+    use super::*;
+
+    fn event_log() -> EventLog {
+        let url = "https://vsd:8443/nuage/api/v5_0/";
+        let session = SessionBuilder::plain(url, "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap();
+        EventLog::new(session,
+                      Url::parse("https://vsd:8443/nuage/api/v5_0/eventlogs").unwrap())
+    }
+
+    #[test]
+    fn filter() {
+        let filter = |log: EventLog| log.filter().map(|filter| filter.to_string());
+        assert_eq!(filter(event_log()), None);
+        assert_eq!(filter(event_log().since(1000)),
+                   Some("eventReceivedTime > 1000".to_string()));
+        assert_eq!(filter(event_log().entity_type("enterprise")),
+                   Some(r#"entityType == "enterprise""#.to_string()));
+        let expected = concat!(r#"(entityType == "enterprise" or entityType == "domain") "#,
+                               "and eventReceivedTime > 1000");
+        assert_eq!(filter(event_log()
+                              .entity_type("enterprise")
+                              .entity_type("domain")
+                              .since(1000)),
+                   Some(expected.to_string()));
+        assert_eq!(filter(event_log().entity_type(r#"a" or entityType != "b"#)),
+                   Some(r#"entityType == "a\" or entityType != \"b""#.to_string()));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use user::CurrentUser;
+
+/// An event in the life of a session.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The session connected to the API, as the given user. It is emitted once the root entity
+    /// has its session: if the root entity does not describe a user, `Session::connect` returns
+    /// the error instead.
+    Connected(CurrentUser),
+    /// The session obtained a new API key, either when connecting or through
+    /// `Session::set_api_key`.
+    ApiKeyRefreshed,
+    /// The event channel of a `PushCenter` failed, for the given reason. It is opened again
+    /// after a delay.
+    PushChannelLost(String),
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+type Listener = Arc<Fn(&Event) + Send + Sync>;
+
+/// The listeners registered on a session.
+#[derive(Default)]
+pub struct Listeners {
+    inner: RwLock<Vec<Listener>>,
+}
+
+impl Listeners {
+    pub fn add(&self, listener: Listener) {
+        self.inner.write().unwrap().push(listener);
+    }
+
+    /// Call the listeners with `event`. They are called without holding the lock, so that they
+    /// can register other listeners, and so that a listener that panics does not poison it.
+    pub fn emit(&self, event: Event) {
+        let listeners = self.inner.read().unwrap().clone();
+        for listener in &listeners {
+            listener(&event);
+        }
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Listeners({})", self.inner.read().unwrap().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn emit() {
+        let listeners = Listeners::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = events.clone();
+        listeners.add(Arc::new(move |event: &Event| {
+                                   received.lock().unwrap().push(format!("{:?}", event))
+                               }));
+        listeners.emit(Event::ApiKeyRefreshed);
+        listeners.emit(Event::PushChannelLost("timeout".to_string()));
+        assert_eq!(*events.lock().unwrap(),
+                   vec!["ApiKeyRefreshed", "PushChannelLost(\"timeout\")"]);
+    }
+
+    #[test]
+    fn listener_adds_listener() {
+        let listeners = Arc::new(Listeners::default());
+        let inner = listeners.clone();
+        listeners.add(Arc::new(move |_: &Event| inner.add(Arc::new(|_: &Event| {}))));
+        listeners.emit(Event::ApiKeyRefreshed);
+        assert_eq!(listeners.inner.read().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn listener_panics() {
+        let listeners = Listeners::default();
+        listeners.add(Arc::new(|_: &Event| panic!("listener failed")));
+        let emit = AssertUnwindSafe(|| listeners.emit(Event::ApiKeyRefreshed));
+        assert!(panic::catch_unwind(emit).is_err());
+        assert!(!listeners.inner.is_poisoned());
+        listeners.add(Arc::new(|_: &Event| {}));
+    }
+}
@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use Error;
+
+/// Write `entities` to `writer` as CSV. `columns` are the serialized names of the attributes to
+/// export (for instance `"ID"` or `"externalID"`), and are used as the header row. Attributes
+/// that are missing or `null` produce empty cells, and nested values are written as JSON.
+pub fn to_csv<E, W>(entities: &[E], columns: &[&str], writer: &mut W) -> Result<(), Error>
+    where E: Serialize,
+          W: Write
+{
+    write_row(writer, columns.iter().map(|column| column.to_string()))?;
+
+    for entity in entities {
+        let value = serde_json::to_value(entity)?;
+        write_row(writer,
+                  columns
+                      .iter()
+                      .map(|column| cell(value.get(column).unwrap_or(&Value::Null))))?;
+    }
+    Ok(())
+}
+
+fn cell(value: &Value) -> String {
+    match *value {
+        Value::Null => String::new(),
+        Value::String(ref s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(ref n) => n.to_string(),
+        Value::Array(_) | Value::Object(_) => value.to_string(),
+    }
+}
+
+fn write_row<W, I>(writer: &mut W, cells: I) -> Result<(), Error>
+    where W: Write,
+          I: Iterator<Item = String>
+{
+    let row = cells.map(|cell| escape(&cell)).collect::<Vec<_>>().join(",");
+    writer.write_all(row.as_bytes())?;
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
+
+fn escape(cell: &str) -> String {
+    if cell.contains(|c| c == ',' || c == '"' || c == '\r' || c == '\n') {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Enterprise {
+        #[serde(rename = "ID")]
+        id: String,
+        name: String,
+        description: Option<String>,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn escaped_cells() {
+        assert_eq!(escape("acme"), "acme");
+        assert_eq!(escape("acme, inc"), "\"acme, inc\"");
+        assert_eq!(escape("the \"acme\""), "\"the \"\"acme\"\"\"");
+        assert_eq!(escape("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(escape(""), "");
+    }
+
+    #[test]
+    fn csv() {
+        let entities = vec![Enterprise {
+                                id: "1".to_string(),
+                                name: "acme, inc".to_string(),
+                                description: None,
+                                tags: vec!["a".to_string()],
+                            }];
+        let mut csv = Vec::new();
+        to_csv(&entities, &["ID", "name", "description", "tags", "missing"], &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(),
+                   "ID,name,description,tags,missing\r\n\
+                    1,\"acme, inc\",,\"[\"\"a\"\"]\",\r\n");
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use reqwest::Url;
+
+use {CancellationToken, ChildStream, Error, Page, RequestOptions, RestEntity, Session};
+use {XNuageAttributes, XNuageFilter, XNuageOrderBy, XNuagePage, XNuagePageSize};
+
+/// A request for the children of an entity, created by `Session::fetcher`. The children can be
+/// filtered, ordered and paginated by the server before being fetched:
+///
+/// ```rust,ignore
+/// let domains = session.fetcher::<_, Domain>(&enterprise)?
+///     .filter(Filter::field("name").begins_with("prod"))
+///     .order_by("name ASC")
+///     .page_size(50)
+///     .fetch()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Fetcher<C> {
+    session: Session,
+    url: Url,
+    options: RequestOptions,
+    marker: PhantomData<C>,
+}
+
+impl<C> Fetcher<C>
+    where C: RestEntity
+{
+    pub fn new(session: Session, url: Url) -> Self {
+        Fetcher {
+            session: session,
+            url: url,
+            options: RequestOptions::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Only fetch the children matching `filter`, either a `Filter` or an expression such as
+    /// `name == "x"`. It is sent as the `X-Nuage-Filter` header.
+    pub fn filter<F>(mut self, filter: F) -> Self
+        where F: ToString
+    {
+        self.options = self.options.header(XNuageFilter(filter.to_string()));
+        self
+    }
+
+    /// Sort the children, e.g. `name ASC`. It is sent as the `X-Nuage-OrderBy` header.
+    pub fn order_by(mut self, order_by: &str) -> Self {
+        self.options = self.options.header(XNuageOrderBy(order_by.to_owned()));
+        self
+    }
+
+    /// Only fetch the given page of children, starting at 0. It is sent as the `X-Nuage-Page`
+    /// header.
+    pub fn page(mut self, page: u32) -> Self {
+        self.options = self.options.header(XNuagePage(page));
+        self
+    }
+
+    /// Set the number of children per page. It is sent as the `X-Nuage-PageSize` header.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.options = self.options.header(XNuagePageSize(page_size));
+        self
+    }
+
+    /// Only fetch the given attributes of the children, by their serialized names, e.g.
+    /// `&["ID", "name"]`. It is sent as the `X-Nuage-Attributes` header. The other attributes
+    /// are missing from the response, so they must have a default value when deserializing
+    /// `C`. Saving such a partial entity with `save_full` would overwrite the missing
+    /// attributes on the server.
+    pub fn attributes(mut self, attributes: &[&str]) -> Self {
+        let attributes = attributes.iter().map(|a| a.to_string()).collect();
+        self.options = self.options.header(XNuageAttributes(attributes));
+        self
+    }
+
+    /// Set the timeout of the request, instead of the session's.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options = self.options.timeout(timeout);
+        self
+    }
+
+    /// Set other options for the request, such as a timeout. They replace the options set so
+    /// far, including the filter, order and pagination.
+    pub fn options(mut self, options: RequestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Abort the fetch when `token` is cancelled. With `iter` and `stream`, the children already
+    /// received are still returned, followed by `Error::Cancelled`.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.options = self.options.cancellation(token);
+        self
+    }
+
+    /// Fetch the children, with the pagination headers of the response.
+    pub fn fetch(&self) -> Result<Page<C>, Error> {
+        self.session.fetch_page_at(self.url.clone(), &self.options)
+    }
+
+    /// Fetch the children, and return an iterator that deserializes them one by one as the
+    /// response body is read. See `Session::stream_children`.
+    pub fn stream(&self) -> Result<ChildStream<C>, Error>
+        where C: Send + 'static
+    {
+        self.session.stream_children_at(self.url.clone(), &self.options)
+    }
+
+    /// Return the number of children matching the filter, without fetching them.
+    pub fn count(&self) -> Result<u64, Error> {
+        self.session.count_at(self.url.clone(), &self.options)
+    }
+
+    /// Return an iterator over the children, which fetches them page by page, starting at the
+    /// page set with `page` if any. Pages have `DEFAULT_PAGE_SIZE` children, unless another size
+    /// was set with `page_size`.
+    pub fn iter(self) -> Children<C> {
+        let page = self.options
+            .headers()
+            .get::<XNuagePage>()
+            .map_or(0, |page| page.0);
+        let page_size = self.options
+            .headers()
+            .get::<XNuagePageSize>()
+            .map_or(DEFAULT_PAGE_SIZE, |size| size.0);
+        Children {
+            fetcher: self.page_size(page_size),
+            buffer: VecDeque::new(),
+            page: page,
+            page_size: page_size,
+            done: false,
+        }
+    }
+}
+
+/// The number of children fetched at once by `Children`, unless specified otherwise.
+pub const DEFAULT_PAGE_SIZE: u32 = 500;
+
+/// An iterator over the children of an entity, created by `Session::iter_children` or
+/// `Fetcher::iter`. The children are fetched page by page, until the number of children
+/// announced by the server in the `X-Nuage-Count` header is reached.
+#[derive(Debug)]
+pub struct Children<C> {
+    fetcher: Fetcher<C>,
+    buffer: VecDeque<C>,
+    page: u32,
+    page_size: u32,
+    done: bool,
+}
+
+impl<C> Children<C>
+    where C: RestEntity
+{
+    fn fetch_next_page(&mut self) -> Result<(), Error> {
+        let options = self.fetcher.options.clone().header(XNuagePage(self.page));
+        let page = self.fetcher
+            .session
+            .fetch_page_at::<C>(self.fetcher.url.clone(), &options)?;
+        self.page += 1;
+        let fetched = u64::from(self.page) * u64::from(self.page_size);
+        self.done = match page.count {
+            Some(count) => page.is_empty() || fetched >= count,
+            // Without a count, a short page can only be the last one.
+            None => page.len() < self.page_size as usize,
+        };
+        self.buffer.extend(page);
+        Ok(())
+    }
+}
+
+impl<C> Iterator for Children<C>
+    where C: RestEntity
+{
+    type Item = Result<C, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() && !self.done {
+            if let Err(e) = self.fetch_next_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
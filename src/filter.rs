@@ -0,0 +1,283 @@
+use std::fmt;
+
+/// A filter expression in the predicate syntax of the `X-Nuage-Filter` header, built without
+/// having to quote and escape the values by hand:
+///
+/// ```rust,ignore
+/// let filter = Filter::field("name").eq("prod")
+///     .and(Filter::field("externalID").is_null());
+/// let domains = session.fetcher::<_, Domain>(&enterprise)?
+///     .filter(filter)
+///     .fetch()?;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    expression: String,
+    // The operator combining the clauses of the expression, if there are several.
+    operator: Option<Logical>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Logical {
+    And,
+    Or,
+}
+
+impl Filter {
+    /// Start a condition on an attribute, by its serialized name.
+    pub fn field(name: &str) -> Field {
+        Field { name: name.to_owned() }
+    }
+
+    /// Match the entities that match both this filter and `other`.
+    pub fn and(self, other: Filter) -> Filter {
+        self.combine(Logical::And, other)
+    }
+
+    /// Match the entities that match this filter or `other`.
+    pub fn or(self, other: Filter) -> Filter {
+        self.combine(Logical::Or, other)
+    }
+
+    fn combine(self, operator: Logical, other: Filter) -> Filter {
+        let keyword = match operator {
+            Logical::And => "and",
+            Logical::Or => "or",
+        };
+        Filter {
+            expression: format!("{} {} {}",
+                                self.operand(operator),
+                                keyword,
+                                other.operand(operator)),
+            operator: Some(operator),
+        }
+    }
+
+    /// Return the expression, in parentheses if it combines clauses with another operator.
+    fn operand(&self, operator: Logical) -> String {
+        match self.operator {
+            Some(op) if op != operator => format!("({})", self.expression),
+            _ => self.expression.clone(),
+        }
+    }
+
+    fn condition(field: Field, operator: &str, value: Value) -> Filter {
+        Filter {
+            expression: format!("{} {} {}", field.name, operator, value),
+            operator: None,
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// An attribute to put a condition on, created by `Filter::field`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    name: String,
+}
+
+impl Field {
+    pub fn eq<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, "==", value.into())
+    }
+
+    pub fn ne<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, "!=", value.into())
+    }
+
+    pub fn lt<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, "<", value.into())
+    }
+
+    pub fn le<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, "<=", value.into())
+    }
+
+    pub fn gt<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, ">", value.into())
+    }
+
+    pub fn ge<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::condition(self, ">=", value.into())
+    }
+
+    /// Match the entities whose attribute contains `value`.
+    pub fn contains(self, value: &str) -> Filter {
+        Filter::condition(self, "CONTAINS", value.into())
+    }
+
+    /// Match the entities whose attribute starts with `value`.
+    pub fn begins_with(self, value: &str) -> Filter {
+        Filter::condition(self, "BEGINSWITH", value.into())
+    }
+
+    /// Match the entities whose attribute ends with `value`.
+    pub fn ends_with(self, value: &str) -> Filter {
+        Filter::condition(self, "ENDSWITH", value.into())
+    }
+
+    /// Match the entities whose attribute is not set.
+    pub fn is_null(self) -> Filter {
+        Filter::condition(self, "==", Value::Null)
+    }
+
+    /// Match the entities whose attribute is set.
+    pub fn is_not_null(self) -> Filter {
+        Filter::condition(self, "!=", Value::Null)
+    }
+}
+
+/// A value compared to an attribute. Strings are quoted and escaped when the filter is
+/// serialized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::String(ref s) => {
+                write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(i64::from(value))
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Integer(i64::from(value))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(value: &'a str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions() {
+        assert_eq!(Filter::field("name").eq("prod").to_string(), r#"name == "prod""#);
+        assert_eq!(Filter::field("name").ne("prod").to_string(), r#"name != "prod""#);
+        assert_eq!(Filter::field("priority").lt(10).to_string(), "priority < 10");
+        assert_eq!(Filter::field("priority").le(10u32).to_string(), "priority <= 10");
+        assert_eq!(Filter::field("creationDate").gt(1500000000000i64).to_string(),
+                   "creationDate > 1500000000000");
+        assert_eq!(Filter::field("ratio").ge(0.5).to_string(), "ratio >= 0.5");
+        assert_eq!(Filter::field("dhcpEnabled").eq(true).to_string(), "dhcpEnabled == true");
+        assert_eq!(Filter::field("name").contains("prod").to_string(),
+                   r#"name CONTAINS "prod""#);
+        assert_eq!(Filter::field("name").begins_with("prod").to_string(),
+                   r#"name BEGINSWITH "prod""#);
+        assert_eq!(Filter::field("name").ends_with("prod").to_string(),
+                   r#"name ENDSWITH "prod""#);
+        assert_eq!(Filter::field("externalID").is_null().to_string(), "externalID == null");
+        assert_eq!(Filter::field("externalID").is_not_null().to_string(),
+                   "externalID != null");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(Filter::field("name").eq(r#"say "hi""#).to_string(),
+                   r#"name == "say \"hi\"""#);
+        assert_eq!(Filter::field("name").eq(r"C:\temp").to_string(),
+                   r#"name == "C:\\temp""#);
+        // The backslashes are escaped first, so that an escaped quote stays escaped.
+        assert_eq!(Filter::field("name").eq(r#"a\"b"#).to_string(),
+                   r#"name == "a\\\"b""#);
+        // The operators and keywords in a value are only text.
+        assert_eq!(Filter::field("name").contains(r#"" or name != ""#).to_string(),
+                   r#"name CONTAINS "\" or name != \"""#);
+        assert_eq!(Filter::field("name").eq(String::from("")).to_string(), r#"name == """#);
+    }
+
+    #[test]
+    fn same_operator() {
+        let filter = Filter::field("a")
+            .eq(1)
+            .and(Filter::field("b").eq(2))
+            .and(Filter::field("c").eq(3));
+        assert_eq!(filter.to_string(), "a == 1 and b == 2 and c == 3");
+
+        let filter = Filter::field("a")
+            .eq(1)
+            .or(Filter::field("b").eq(2).or(Filter::field("c").eq(3)));
+        assert_eq!(filter.to_string(), "a == 1 or b == 2 or c == 3");
+    }
+
+    #[test]
+    fn nested_operators() {
+        let filter = Filter::field("a")
+            .eq(1)
+            .or(Filter::field("b").eq(2))
+            .and(Filter::field("c").eq(3));
+        assert_eq!(filter.to_string(), "(a == 1 or b == 2) and c == 3");
+
+        let filter = Filter::field("a")
+            .eq(1)
+            .and(Filter::field("b").eq(2).or(Filter::field("c").eq(3)));
+        assert_eq!(filter.to_string(), "a == 1 and (b == 2 or c == 3)");
+
+        let filter = Filter::field("a")
+            .eq(1)
+            .and(Filter::field("b").eq(2))
+            .or(Filter::field("c").eq(3).and(Filter::field("d").eq(4)));
+        assert_eq!(filter.to_string(), "(a == 1 and b == 2) or (c == 3 and d == 4)");
+
+        let filter = Filter::field("a")
+            .eq(1)
+            .or(Filter::field("b").eq(2))
+            .and(Filter::field("c").eq(3).or(Filter::field("d").eq(4)))
+            .or(Filter::field("e").eq(5));
+        assert_eq!(filter.to_string(),
+                   "((a == 1 or b == 2) and (c == 3 or d == 4)) or e == 5");
+    }
+}
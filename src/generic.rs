@@ -0,0 +1,292 @@
+use reqwest::Url;
+use serde_json::{Map, Value};
+
+use path;
+use Error;
+use Method;
+use Session;
+
+/// An entity of any type, whose attributes are kept as JSON values. It is meant for the types
+/// that have no `RestEntity` implementation, for instance to inspect a resource the bindings do
+/// not cover yet.
+///
+/// Since `RestEntity::path` and `RestEntity::group_path` do not depend on the entity, the paths
+/// of a generic entity cannot be given to them: `GenericEntity` does not implement `RestEntity`,
+/// so it cannot be given to the functions that only know the type of an entity, such as
+/// `Session::get` or `Session::fetch_children`. It keeps its paths instead, and has its own
+/// methods to fetch, save and delete it, and to fetch and create its children.
+///
+/// ```rust,ignore
+/// let mut gateway = session.fetch_generic("gateway", "gateways", "42")?;
+/// gateway.set("description", Value::String("edge gateway".to_owned()));
+/// gateway.save()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct GenericEntity {
+    session: Option<Session>,
+    path: String,
+    group_path: String,
+    attributes: Map<String, Value>,
+}
+
+impl GenericEntity {
+    /// Create an entity with no attributes, with the rest path of the entity and of its group,
+    /// such as `"enterprise"` and `"enterprises"`.
+    pub fn new(path: &str, group_path: &str) -> Self {
+        GenericEntity {
+            session: None,
+            path: path.to_owned(),
+            group_path: group_path.to_owned(),
+            attributes: Map::new(),
+        }
+    }
+
+    /// Create an entity from attributes returned by the server, with a reference to `session`.
+    pub fn from_attributes(session: &Session,
+                           path: &str,
+                           group_path: &str,
+                           attributes: Map<String, Value>)
+                           -> Self {
+        GenericEntity {
+            session: Some(session.clone()),
+            path: path.to_owned(),
+            group_path: group_path.to_owned(),
+            attributes: attributes,
+        }
+    }
+
+    /// Give a session to the entity.
+    pub fn set_session(&mut self, session: Session) {
+        self.session = Some(session);
+    }
+
+    pub fn get_session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Return the rest path of the entity, without its ID.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return the rest path of the entity's group.
+    pub fn group_path(&self) -> &str {
+        &self.group_path
+    }
+
+    /// Return the ID of the entity, from its `ID` attribute.
+    pub fn id(&self) -> Option<&str> {
+        self.attributes.get("ID").and_then(|id| id.as_str())
+    }
+
+    /// Set the ID of the entity.
+    pub fn set_id(&mut self, id: &str) {
+        self.set("ID", Value::String(id.to_owned()));
+    }
+
+    /// Return the value of an attribute, by its serialized name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.attributes.get(name)
+    }
+
+    /// Set the value of an attribute, by its serialized name.
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.attributes.insert(name.to_owned(), value);
+    }
+
+    /// Remove an attribute, and return its value.
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.attributes.remove(name)
+    }
+
+    /// Return all the attributes of the entity.
+    pub fn attributes(&self) -> &Map<String, Value> {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Map<String, Value> {
+        &mut self.attributes
+    }
+
+    /// Return a short, human readable description of the entity, made of its path and its ID,
+    /// e.g. `gateway 42`.
+    pub fn pretty(&self) -> String {
+        format!("{} {}", self.path, self.id().unwrap_or("<no ID>"))
+    }
+
+    /// Fetch the entity and replace its attributes.
+    pub fn fetch(&mut self) -> Result<(), Error> {
+        let session = self.session()?;
+        let attributes = session
+            .send_generic(Method::Get, self.url()?, None)?
+            .and_then(|mut entities| entities.pop())
+            .ok_or(Error::NoEntity)?;
+        self.attributes = attributes;
+        Ok(())
+    }
+
+    /// Save the entity, and replace its attributes with the ones returned by the server, if
+    /// any.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let session = self.session()?;
+        let entities = session
+            .send_generic(Method::Put, self.url()?, Some(&self.attributes))?;
+        if let Some(attributes) = entities.and_then(|mut entities| entities.pop()) {
+            self.attributes = attributes;
+        }
+        Ok(())
+    }
+
+    /// Delete the entity from the server.
+    pub fn delete(self) -> Result<(), Error> {
+        self.session()?
+            .send_generic(Method::Delete, self.url()?, None)?;
+        Ok(())
+    }
+
+    /// Fetch the children of the entity whose rest paths are `path` and `group_path`.
+    pub fn fetch_generic_children(&self,
+                                  path: &str,
+                                  group_path: &str)
+                                  -> Result<Vec<GenericEntity>, Error> {
+        self.session()?
+            .fetch_generic_at(self.group_url(group_path)?, path, group_path)
+    }
+
+    /// Create a child under the entity, and return it as populated by the server.
+    pub fn create_child(&self, child: GenericEntity) -> Result<GenericEntity, Error> {
+        let url = self.group_url(&child.group_path)?;
+        self.session()?.create_generic_at(url, child)
+    }
+
+    fn session(&self) -> Result<&Session, Error> {
+        self.session.as_ref().ok_or(Error::NoSession)
+    }
+
+    /// Return the URL of the entity.
+    fn url(&self) -> Result<Url, Error> {
+        let id = self.id().ok_or(Error::MissingId)?;
+        path::entity(self.session()?.url(), &self.path, id)
+    }
+
+    /// Return the URL of a group of children of the entity.
+    fn group_url(&self, group_path: &str) -> Result<Url, Error> {
+        let id = self.id().ok_or(Error::MissingId)?;
+        path::group(self.session()?.url(), Some((&self.path, id)), group_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::Headers;
+    use serde_json::Value;
+
+    use transport::{Request, Response, Transport};
+    use {Error, Method, SessionBuilder};
+    use super::*;
+
+    /// A transport answering every request with the same body, and keeping the method and the
+    /// URL of the requests.
+    #[derive(Debug)]
+    struct Canned {
+        body: &'static str,
+        requests: Arc<Mutex<Vec<(Method, Url)>>>,
+    }
+
+    impl Transport for Canned {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((request.method, request.url.clone()));
+            Ok(Response::new(StatusCode::Ok,
+                             Headers::new(),
+                             request.url,
+                             Box::new(Cursor::new(self.body.as_bytes().to_vec()))))
+        }
+    }
+
+    fn session(body: &'static str) -> (Session, Arc<Mutex<Vec<(Method, Url)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let transport = Canned {
+            body: body,
+            requests: requests.clone(),
+        };
+        let url = "https://vsd:8443/nuage/api/v5_0/";
+        let session = SessionBuilder::plain(url, "csproot", "csproot")
+            .unwrap()
+            .transport(transport)
+            .build()
+            .unwrap();
+        (session, requests)
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("https://vsd:8443/nuage/api/v5_0/{}", path)).unwrap()
+    }
+
+    #[test]
+    fn attributes() {
+        let mut gateway = GenericEntity::new("gateway", "gateways");
+        assert_eq!(gateway.id(), None);
+        gateway.set_id("42");
+        gateway.set("name", Value::String("edge".to_owned()));
+        assert_eq!(gateway.id(), Some("42"));
+        assert_eq!(gateway.get("name"), Some(&Value::String("edge".to_owned())));
+        assert_eq!(gateway.pretty(), "gateway 42");
+        assert_eq!(gateway.remove("name"), Some(Value::String("edge".to_owned())));
+        assert_eq!(gateway.attributes().len(), 1);
+    }
+
+    #[test]
+    fn no_session() {
+        let mut gateway = GenericEntity::new("gateway", "gateways");
+        gateway.set_id("42");
+        match gateway.fetch() {
+            Err(Error::NoSession) => {}
+            other => panic!("expected Error::NoSession, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fetch_and_save() {
+        let (session, requests) = session(r#"[{"ID": "42", "name": "edge"}]"#);
+        let mut gateway = session.fetch_generic("gateway", "gateways", "42").unwrap();
+        assert_eq!(gateway.get("name"), Some(&Value::String("edge".to_owned())));
+        gateway.save().unwrap();
+        gateway.delete().unwrap();
+        assert_eq!(*requests.lock().unwrap(),
+                   vec![(Method::Get, url("gateway/42")),
+                        (Method::Put, url("gateway/42")),
+                        (Method::Delete, url("gateway/42"))]);
+    }
+
+    #[test]
+    fn children() {
+        let (session, requests) = session(r#"[{"ID": "1"}, {"ID": "2"}]"#);
+        let mut gateway =
+            GenericEntity::from_attributes(&session, "gateway", "gateways", Map::new());
+        gateway.set_id("42");
+        let ports = gateway.fetch_generic_children("port", "ports").unwrap();
+        assert_eq!(ports.iter().map(|port| port.id()).collect::<Vec<_>>(),
+                   vec![Some("1"), Some("2")]);
+        assert_eq!(ports[0].path(), "port");
+        assert_eq!(ports[0].group_path(), "ports");
+
+        let created = gateway
+            .create_child(GenericEntity::new("port", "ports"))
+            .unwrap();
+        assert_eq!(created.id(), Some("2"));
+        session
+            .create_generic(GenericEntity::new("gateway", "gateways"))
+            .unwrap();
+        assert_eq!(*requests.lock().unwrap(),
+                   vec![(Method::Get, url("gateway/42/ports")),
+                        (Method::Post, url("gateway/42/ports")),
+                        (Method::Post, url("gateways"))]);
+    }
+}
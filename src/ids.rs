@@ -0,0 +1,11 @@
+/// A slim representation of an entity, holding only its ID and name. Deserializing a collection
+/// into such entries is much cheaper than deserializing the full entities, which makes
+/// reconciliation passes over large collections cheap.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct IdEntry {
+    #[serde(rename = "ID")]
+    pub id: String,
+    /// The name of the entity, for the entities that have one.
+    #[serde(default)]
+    pub name: Option<String>,
+}
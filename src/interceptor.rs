@@ -0,0 +1,25 @@
+use std::fmt;
+
+use reqwest::Url;
+use reqwest::header::Headers;
+
+use auth::RequestParts;
+use Error;
+use Method;
+use Response;
+
+/// A hook called around every request sent by a session, for instance to add custom headers,
+/// record metrics or keep an audit log. Interceptors are registered with
+/// `SessionBuilder::interceptor`, and called in the order they were registered.
+pub trait Interceptor: fmt::Debug + Send + Sync {
+    /// Called before a request is sent, once all its headers but the signature (see
+    /// `AuthProvider::sign`) have been set. An error aborts the request. Does nothing by
+    /// default.
+    fn before(&self, _request: &RequestParts, _headers: &mut Headers) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when the response to a request has been received, before its body is read. Does
+    /// nothing by default.
+    fn after(&self, _method: &Method, _url: &Url, _response: &Response) {}
+}
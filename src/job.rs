@@ -0,0 +1,133 @@
+use serde_json::Value;
+
+/// An asynchronous job run by the server, such as an export, an import or a certificate
+/// operation. Jobs are created by `Session::create_job` and followed with `Session::wait_job`,
+/// or both at once with `Session::run_job`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The operation run by the job, e.g. `"EXPORT"`.
+    pub command: String,
+    #[serde(default)]
+    pub parameters: Value,
+    /// The status of the job, e.g. `"RUNNING"`, `"SUCCESS"` or `"FAILED"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// The progress of the job, between 0 and 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f64>,
+    /// The result of the job once it is finished, or the reason why it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+}
+
+impl Job {
+    pub fn new(command: &str, parameters: Value) -> Self {
+        Job {
+            id: None,
+            command: command.to_owned(),
+            parameters: parameters,
+            status: None,
+            progress: None,
+            result: None,
+        }
+    }
+
+    /// Return true if the job succeeded.
+    pub fn succeeded(&self) -> bool {
+        self.status.as_ref().map_or(false, |s| s == "SUCCESS")
+    }
+
+    /// Return true if the job failed.
+    pub fn failed(&self) -> bool {
+        self.status.as_ref().map_or(false, |s| s == "FAILED")
+    }
+
+    /// Return true if the job is finished, whether it succeeded or not.
+    pub fn is_finished(&self) -> bool {
+        self.succeeded() || self.failed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::{self, Value};
+
+    use {Error, Session, SessionBuilder};
+    use super::*;
+
+    fn job(status: &str) -> Job {
+        let json = format!(r#"{{"ID": "1", "command": "EXPORT", "status": "{}",
+                                "progress": 0.5, "result": "done"}}"#,
+                           status);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn session() -> Session {
+        SessionBuilder::plain("https://vsd:8443/nuage/api/v5_0/", "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn status() {
+        let running = job("RUNNING");
+        assert!(!running.succeeded() && !running.failed() && !running.is_finished());
+        let succeeded = job("SUCCESS");
+        assert!(succeeded.succeeded() && !succeeded.failed() && succeeded.is_finished());
+        let failed = job("FAILED");
+        assert!(!failed.succeeded() && failed.failed() && failed.is_finished());
+        assert!(!Job::new("EXPORT", Value::Null).is_finished());
+    }
+
+    #[test]
+    fn deserialize() {
+        let running = job("RUNNING");
+        assert_eq!(running.id, Some("1".to_string()));
+        assert_eq!(running.command, "EXPORT");
+        assert_eq!(running.parameters, Value::Null);
+        assert_eq!(running.progress, Some(0.5));
+        assert_eq!(running.result, Some(Value::String("done".to_string())));
+    }
+
+    #[test]
+    fn serialize() {
+        let value = serde_json::to_value(&Job::new("EXPORT", Value::Bool(true))).unwrap();
+        let expected: Value = serde_json::from_str(r#"{"command": "EXPORT", "parameters": true}"#)
+            .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn wait_finished() {
+        let session = session();
+        let succeeded = session.wait_job(job("SUCCESS"), Duration::from_secs(0)).unwrap();
+        assert!(succeeded.succeeded());
+        match session.wait_job(job("FAILED"), Duration::from_secs(0)) {
+            Err(Error::JobFailed { command, result }) => {
+                assert_eq!(command, "EXPORT");
+                assert_eq!(result, Some(Value::String("done".to_string())));
+            }
+            other => panic!("expected Error::JobFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let session = session();
+        match session.wait_job(job("RUNNING"), Duration::from_secs(0)) {
+            Err(Error::Timeout(timeout)) => assert_eq!(timeout, Duration::from_secs(0)),
+            other => panic!("expected Error::Timeout, got {:?}", other),
+        }
+        let mut created = job("RUNNING");
+        created.id = None;
+        match session.wait_job(created, Duration::from_secs(0)) {
+            Err(Error::MissingId) => {}
+            other => panic!("expected Error::MissingId, got {:?}", other),
+        }
+    }
+}
@@ -1,24 +1,151 @@
 #[macro_use]
 extern crate hyper;
 extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
 extern crate reqwest;
+#[macro_use]
+extern crate log;
+#[cfg(feature = "msgpack")]
+extern crate rmp_serde;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+#[cfg(feature = "keyring")]
+extern crate keyring;
+#[cfg(feature = "signing")]
+extern crate ring;
+#[cfg(any(feature = "signing", feature = "kerberos"))]
+extern crate base64;
+#[cfg(feature = "kerberos")]
+extern crate libgssapi;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "simd-json")]
+extern crate simd_json;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate tokio_core;
 
+pub mod alarm;
+#[cfg(feature = "async")]
+pub mod async_session;
+pub mod auth;
+pub mod builder;
+pub mod cancel;
+pub mod codec;
+#[cfg(feature = "keyring")]
+pub mod credentials;
+pub mod diff;
 pub mod error;
+pub mod eventlog;
+pub mod events;
+pub mod export;
+pub mod fetcher;
+pub mod filter;
+pub mod generic;
+pub mod ids;
+pub mod interceptor;
+pub mod job;
+pub mod manager;
+pub mod metadata;
+#[cfg(feature = "kerberos")]
+pub mod negotiate;
+pub mod options;
+pub mod outcome;
+pub mod page;
+pub mod patch;
+pub mod push;
+pub mod reference;
+pub mod request;
+pub mod response;
+pub mod retry;
+pub mod secret;
+pub mod server;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod state;
+pub mod statistics;
+pub mod transport;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod body;
+mod bulk;
+mod logging;
+mod path;
+mod proxy;
+mod queue;
+mod ratelimit;
+mod stream;
+pub mod upload;
+pub mod user;
+pub mod validation;
 
-use reqwest::{Client, ClientBuilder, Response, Url};
-use reqwest::header::{Headers, Authorization, Basic, ContentType};
-use hyper::mime::{Mime, TopLevel, SubLevel, Attr, Value};
-use serde::Serialize;
+use reqwest::{Client, ClientBuilder, Identity, StatusCode, Url};
+use reqwest::header::{Headers, ContentType, Accept, ETag, IfMatch, qitem};
+use hyper::mime::Mime;
+use serde::{Serialize, Deserializer};
 
-pub use error::Error;
+use std::any::Any;
+use std::cmp;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub use alarm::{Alarm, AlarmQuery};
+#[cfg(feature = "async")]
+pub use async_session::{AsyncRestEntity, AsyncRestRootEntity, AsyncSession};
+pub use auth::AuthProvider;
+pub use body::Buffer;
+pub use cancel::CancellationToken;
+pub use codec::Codec;
+pub use diff::FieldChange;
+pub use error::{ApiError, Error};
+pub use eventlog::{EventLog, EventLogEntry};
+pub use events::Event;
+pub use fetcher::{Children, Fetcher};
+pub use filter::Filter;
+pub use generic::GenericEntity;
+pub use ids::IdEntry;
+pub use interceptor::Interceptor;
+pub use job::Job;
+pub use manager::{SessionKey, SessionManager};
+pub use metadata::{GlobalMetadata, Metadata, MetadataTag};
+pub use options::RequestOptions;
+pub use outcome::DeleteOutcome;
+pub use page::Page;
+pub use patch::Patch;
+pub use push::{PushCenter, PushEvent};
+pub use reference::Ref;
+pub use request::RequestBuilder;
+pub use response::ResponseInfo;
+pub use retry::RetryPolicy;
+pub use secret::Secret;
+pub use server::{ApiVersion, ServerInfo};
+pub use state::SessionState;
+pub use statistics::{Statistics, TimeSeries};
+pub use stream::ChildStream;
+pub use transport::{Response, Transport};
+pub use upload::{Form, Upload};
+pub use user::CurrentUser;
+pub use validation::AttributeSpec;
 pub use reqwest::Certificate;
+pub use reqwest::header::EntityTag;
+pub use hyper::method::Method;
 
-pub trait RestEntity<'a>: Serialize + for<'de> serde::Deserialize<'de> {
-    /// Give a reference to an existing session to the entity. Without a session, an entity is
-    /// pretty much useless, since it cannot be fetched, updated, deleted or used to create
-    /// children entities.
-    fn set_session(&mut self, session: &'a Session);
+/// An entity of the API. Besides the entity's description (its paths and ID), implementations
+/// only have to keep the session the entity was fetched with: the requests are implemented by
+/// default on top of it. The whole implementation can also be generated with
+/// `#[derive(RestEntity)]`, from the `bambou-derive` crate.
+pub trait RestEntity: Serialize + for<'de> serde::Deserialize<'de> {
+    /// Give a session to the entity. Without a session, an entity is pretty much useless, since it
+    /// cannot be fetched, updated, deleted or used to create children entities.
+    fn set_session(&mut self, session: Session);
 
     /// Return a reference to a session. This session is used to make server requests.
     fn get_session(&self) -> Option<&Session>;
@@ -29,239 +156,3263 @@ pub trait RestEntity<'a>: Serialize + for<'de> serde::Deserialize<'de> {
     /// Return the rest path of the entity's group. This is usually the same than de entity's path.
     fn group_path() -> &'static str;
 
+    /// Return the specification of the entity's attributes. When the `validation` feature is
+    /// enabled, responses are checked against it. By default, the specification is empty and
+    /// nothing is checked.
+    fn attributes() -> &'static [AttributeSpec] {
+        &[]
+    }
+
+    /// Return the serialized name of an attribute that uniquely identifies the entity under its
+    /// parent besides its ID, such as `"externalID"` or `"name"`. It is used by
+    /// `Session::fetch_by_key`, `Session::key_url` and `Session::upsert_child`. By default,
+    /// entities do not have an alternate key.
+    fn alternate_key() -> Option<&'static str> {
+        None
+    }
+
     /// Return the ID of the entity. It may not exist (if the entity does not exist on the server
     /// for instance), or just not be known on the client side.
     fn id(&self) -> Option<&str>;
 
-    /// Must return true if the entity is a root of the API and false otherwise.
+    /// Must return true if the entity is a root of the API and false otherwise. A root entity,
+    /// such as `me` on the VSD, is a singleton: its URL is its path alone, without an ID, and
+    /// its children are directly under the base URL.
     fn is_root(&self) -> bool;
 
     /// Fetch the entity from the server and populate its attributes from the response.
-    fn fetch(&mut self) -> Result<Response, Error>;
+    fn fetch(&mut self) -> Result<ResponseInfo, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.fetch_entity(self)
+    }
+
+    /// Fetch the entity from the server, and return the attributes that differ between the
+    /// server and the local copy. See `Session::refresh`.
+    fn refresh(&mut self) -> Result<Vec<FieldChange>, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.refresh(self)
+    }
+
+    /// Update the entity on the server from its attributes. If the entity tracks its changes,
+    /// only the modified attributes are sent.
+    fn save(&mut self) -> Result<ResponseInfo, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.save(self)
+    }
+
+    /// Update the given attributes of the entity, by their serialized names, with a `PATCH`
+    /// request. See `Session::patch`.
+    fn patch(&mut self,
+             attributes: serde_json::Map<String, serde_json::Value>)
+             -> Result<ResponseInfo, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.patch(self, &Patch::Partial(attributes))
+    }
+
+    /// Update the modified attributes of the entity with a `PATCH` request. See
+    /// `Session::patch_changes`.
+    fn patch_changes(&mut self) -> Result<ResponseInfo, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.patch_changes(self)
+    }
 
-    /// Update the entity on the server from its attributes.
-    fn save(&mut self) -> Result<Response, Error>;
+    /// Update the entity on the server from all its attributes, whether they were modified or
+    /// not.
+    fn save_full(&mut self) -> Result<ResponseInfo, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.save_full(self)
+    }
 
     /// Delete the entity from the server.
-    fn delete(self) -> Result<Response, Error>;
+    fn delete(self) -> Result<DeleteOutcome, Error> {
+        let session = self.get_session().cloned().ok_or(Error::NoSession)?;
+        session.delete(self)
+    }
 
     /// Fetch children entities from the server.
-    // fn fetch_children<C>(&self, children: &mut Vec<C>) -> Fetcher<C>
-    fn fetch_children<C>(&self, children: &mut Vec<C>) -> Result<Response, Error>
-        where C: RestEntity<'a>;
+    fn fetch_children<C>(&self) -> Result<Vec<C>, Error>
+        where C: RestEntity
+    {
+        self.get_session()
+            .ok_or(Error::NoSession)?
+            .fetch_children(self)
+    }
+
+    /// Create a child entity, and return it as populated by the server.
+    fn create_child<C>(&self, child: C) -> Result<C, Error>
+        where C: RestEntity
+    {
+        self.get_session()
+            .ok_or(Error::NoSession)?
+            .create_child(self, child)
+    }
+
+    /// Set the children of type `C` assigned to the entity. See `Session::assign`.
+    fn assign_children<C>(&self, children: &[&C]) -> Result<(), Error>
+        where C: RestEntity
+    {
+        self.get_session()
+            .ok_or(Error::NoSession)?
+            .assign(self, children)
+    }
+
+    /// Fetch the metadata attached to the entity. They are removed with `Metadata::delete`.
+    fn metadata(&self) -> Result<Vec<Metadata>, Error> {
+        self.fetch_children()
+    }
+
+    /// Attach metadata to the entity, and return them as created by the server.
+    fn attach_metadata(&self, metadata: Metadata) -> Result<Metadata, Error> {
+        self.create_child(metadata)
+    }
+
+    /// Fetch the global metadata attached to the entity. They are removed with
+    /// `GlobalMetadata::delete`.
+    fn global_metadata(&self) -> Result<Vec<GlobalMetadata>, Error> {
+        self.fetch_children()
+    }
 
-    /// Create a child entity.
-    fn create_child<C>(&self, child: &mut C) -> Result<Response, Error> where C: RestEntity<'a>;
+    /// Attach global metadata to the entity, and return them as created by the server.
+    fn attach_global_metadata(&self, metadata: GlobalMetadata) -> Result<GlobalMetadata, Error> {
+        self.create_child(metadata)
+    }
+
+    /// Return the `ETag` of the entity, as returned by the server the last time it was fetched or
+    /// saved. When it is known, it is sent back as is in an `If-Match` header, so that saves and
+    /// deletions are only performed if the entity did not change on the server in the meantime,
+    /// and fail with `Error::Conflict` otherwise. By default, entities do not keep their `ETag`.
+    fn etag(&self) -> Option<&EntityTag> {
+        None
+    }
+
+    /// Called with the `ETag` of the response the entity was populated from, if any. Entities
+    /// that do not keep it can ignore it, which is the default.
+    fn set_etag(&mut self, _etag: Option<EntityTag>) {}
+
+    /// Called every time the entity's attributes are populated from a server response, for
+    /// instance to normalize attributes or compute derived fields. Does nothing by default.
+    fn on_fetched(&mut self) {}
+
+    /// Called before the entity is sent to the server to be saved or created. Does nothing by
+    /// default.
+    fn on_saving(&mut self) {}
+
+    /// Called after the entity has been created on the server, and populated from the response.
+    /// Does nothing by default.
+    fn on_created(&mut self) {}
+
+    /// Return a custom body and content type to send instead of the serialized entity when it is
+    /// saved or created. This is only needed for the few endpoints that expect a non-standard
+    /// payload. By default, the entity is serialized with the session's codec.
+    fn raw_body(&self) -> Option<(Mime, Vec<u8>)> {
+        None
+    }
+
+    /// Return the attributes that best identify the entity for a human reader, as (name, value)
+    /// pairs. They are used by `pretty()`. By default, no attribute is shown.
+    fn key_attributes(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Return a short, human readable description of the entity, made of its path, its ID and
+    /// its key attributes, e.g. `enterprises 5d3f... (name: acme)`.
+    fn pretty(&self) -> String {
+        let mut s = format!("{} {}", Self::path(), self.id().unwrap_or("<no ID>"));
+        let attributes = self.key_attributes();
+        if !attributes.is_empty() {
+            let attributes = attributes
+                .iter()
+                .map(|&(name, ref value)| format!("{}: {}", name, value))
+                .collect::<Vec<_>>();
+            s.push_str(&format!(" ({})", attributes.join(", ")));
+        }
+        s
+    }
+
+    /// Return true if the entity keeps its attributes as they were last fetched or saved, to
+    /// track the attributes modified since. `Session::save` then only sends the modified
+    /// attributes. By default, changes are not tracked.
+    fn tracks_changes() -> bool {
+        false
+    }
+
+    /// Return the serialized attributes of the entity as they were last fetched or saved, if
+    /// the entity tracks its changes.
+    fn original(&self) -> Option<&serde_json::Value> {
+        None
+    }
+
+    /// Called with the serialized attributes of the entity every time it is populated from a
+    /// server response, if the entity tracks its changes.
+    fn set_original(&mut self, _original: serde_json::Value) {}
+
+    /// Return the attributes modified since the entity was last fetched or saved. It is empty if
+    /// the entity does not track its changes.
+    fn changes(&self) -> Result<Vec<FieldChange>, Error> {
+        match self.original() {
+            Some(original) => Ok(diff::diff_values(original, &serde_json::to_value(self)?)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Return the attributes that differ between this entity and `other`, for instance to log
+    /// what a `save` is about to change.
+    fn diff(&self, other: &Self) -> Result<Vec<FieldChange>, Error> {
+        diff::diff(self, other)
+    }
 }
-pub trait RestRootEntity<'a>: RestEntity<'a> {
+pub trait RestRootEntity: RestEntity {
     /// Return the API key for the current session. After the first password authentication, the
     /// root entity should hold an API key. This API key is then used by the session for all
     /// subsequent requests.
     fn get_api_key(&self) -> Option<&str>;
 }
 
+/// A builder for sessions. All the configuration methods consume the builder and return it, so
+/// that they can be chained:
+///
+/// ```rust,ignore
+/// let session = SessionBuilder::new(url, "csproot", "csproot", "csp")?
+///     .root_certificate(cert)
+///     .max_response_size(100 * 1024 * 1024)
+///     .build()?;
+/// ```
 pub struct SessionBuilder {
     client_builder: ClientBuilder,
-    pub url: Url,
-    pub username: String,
-    pub password: String,
-    pub api_key: Option<String>,
-    pub organization: String,
+    // The options set on `client_builder` so far, which `build_async` cannot apply.
+    client_options: Vec<&'static str>,
+    client: Option<Client>,
+    root_certificates: Vec<Certificate>,
+    url: Url,
+    username: String,
+    password: Secret,
+    api_key: Option<Secret>,
+    auth: Option<Arc<AuthProvider>>,
+    organization: Option<String>,
+    max_response_size: Option<u64>,
+    codec: Arc<Codec>,
+    query: Vec<(String, String)>,
+    max_concurrent_gets: Option<usize>,
+    bulk_parallelism: usize,
+    max_reauth: u32,
+    retry_policy: Option<RetryPolicy>,
+    client_certificate: bool,
+    proxy: proxy::ProxyConfig,
+    interceptors: Vec<Box<Interceptor>>,
+    log_bodies: bool,
+    transport: Option<Arc<Transport>>,
+    api_version: Option<String>,
+    current_user: Option<serde_json::Value>,
+    proxy_user: Option<String>,
+    rate_limit: Option<(f64, u32)>,
+    max_throttled_retries: u32,
+    otp_provider: Option<OtpProvider>,
+    #[cfg(feature = "gzip")]
+    compression_threshold: Option<usize>,
 }
 
 impl SessionBuilder {
     /// Create a new session builder
     pub fn new(url: &str, login: &str, password: &str, organization: &str) -> Result<Self, Error> {
+        let mut builder = SessionBuilder::plain(url, login, password)?;
+        builder.organization = Some(organization.to_owned());
+        Ok(builder)
+    }
+
+    /// Create a new session builder for a plain REST API, that does not follow the Nuage
+    /// conventions: no `X-Nuage-Organization` header is sent. The entity and session model can
+    /// then be used with any API that answers with arrays of entities.
+    pub fn plain(url: &str, login: &str, password: &str) -> Result<Self, Error> {
         let session = SessionBuilder {
             client_builder: ClientBuilder::new()?,
+            client_options: Vec::new(),
+            client: None,
+            root_certificates: Vec::new(),
             url: Url::parse(url)?,
             username: login.to_owned(),
-            password: password.to_owned(),
-            organization: organization.to_owned(),
+            password: Secret::from(password),
+            organization: None,
             api_key: None,
+            auth: None,
+            max_response_size: None,
+            codec: Arc::new(codec::Json),
+            query: Vec::new(),
+            max_concurrent_gets: None,
+            bulk_parallelism: 1,
+            max_reauth: DEFAULT_MAX_REAUTH,
+            retry_policy: None,
+            client_certificate: false,
+            proxy: proxy::ProxyConfig::default(),
+            interceptors: Vec::new(),
+            log_bodies: false,
+            transport: None,
+            api_version: None,
+            current_user: None,
+            proxy_user: None,
+            rate_limit: None,
+            max_throttled_retries: DEFAULT_MAX_THROTTLED_RETRIES,
+            otp_provider: None,
+            #[cfg(feature = "gzip")]
+            compression_threshold: None,
         };
         Ok(session)
     }
 
-    pub fn add_root_certificate(&mut self, cert: Certificate) -> Result<(), Error> {
-        self.client_builder.add_root_certificate(cert)?;
-        Ok(())
+    /// Create a session builder that resumes a session from its state, as returned by
+    /// `Session::serialize_state`. If the state has an API key, the built session authenticates
+    /// with it and never sends a password, like a session built with `from_api_key`. The client
+    /// options, such as the trusted certificates, are not part of the state and must be set
+    /// again.
+    pub fn from_state(state: SessionState) -> Result<Self, Error> {
+        let mut builder = SessionBuilder::plain(&state.url, &state.username, "")?;
+        builder.organization = state.organization;
+        if let Some(api_key) = state.api_key {
+            builder = builder.auth(auth::ApiKeyAuth::new(&state.username, api_key));
+        }
+        builder.current_user = match state.current_user {
+            Some(user) => Some(serde_json::to_value(user)?),
+            None => None,
+        };
+        builder.proxy_user = state.proxy_user;
+        Ok(builder)
+    }
+
+    /// Create a session builder for a session that is already authenticated with an API key, for
+    /// instance a key handed out by a central authenticator. The built session can be used
+    /// directly, without calling `Session::connect`, and never sends a password.
+    pub fn from_api_key(url: &str,
+                        login: &str,
+                        api_key: &str,
+                        organization: &str)
+                        -> Result<Self, Error> {
+        let builder = SessionBuilder::new(url, login, "", organization)?;
+        Ok(builder.auth(auth::ApiKeyAuth::new(login, Secret::from(api_key))))
+    }
+
+    /// Set the API key to start with. With the default authentication provider, it is then used
+    /// instead of the password.
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(Secret::from(api_key));
+        self
+    }
+
+    /// Trust an additional root certificate.
+    pub fn root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// Trust the root certificates of a PEM bundle, such as the CA file of a lab or of a company
+    /// PKI. The file may hold several certificates, and anything outside the certificates, such
+    /// as comments, is ignored. It fails if the file holds no certificate, or an invalid or
+    /// truncated one.
+    pub fn ca_bundle_path<P: AsRef<Path>>(mut self, path: P) -> Result<Self, Error> {
+        let mut bundle = String::new();
+        File::open(path)?.read_to_string(&mut bundle)?;
+        let certificates = pem_certificates(&bundle)?;
+        if certificates.is_empty() {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData,
+                                                "no certificate in the CA bundle")));
+        }
+        for pem in certificates {
+            self.root_certificates.push(Certificate::from_pem(pem.as_bytes())?);
+        }
+        Ok(self)
+    }
+
+    /// Accept any certificate from the server, even self-signed or expired ones. This makes the
+    /// connection vulnerable to man-in-the-middle attacks, so it must only be used in lab
+    /// environments.
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.client_builder.danger_accept_invalid_certs(true);
+        self.client_options.push("danger_accept_invalid_certs");
+        self
     }
 
     /// Disable hostname verification
-    pub fn danger_disable_hostname_verification(&mut self) {
+    pub fn danger_disable_hostname_verification(mut self) -> Self {
         self.client_builder
             .danger_disable_hostname_verification();
+        self.client_options.push("danger_disable_hostname_verification");
+        self
     }
 
     /// Enable hostname verification
-    pub fn enable_hostname_verification(&mut self) {
+    pub fn enable_hostname_verification(mut self) -> Self {
         self.client_builder.enable_hostname_verification();
+        self
+    }
+
+    /// Read the password from the platform keyring, where it is stored under `service` for the
+    /// builder's user name.
+    #[cfg(feature = "keyring")]
+    pub fn password_from_keyring(mut self, service: &str) -> Result<Self, Error> {
+        self.password = credentials::get_password(service, &self.username)?;
+        Ok(self)
+    }
+
+    /// Read an API key from the platform keyring, where it was stored under `service` for the
+    /// builder's user name by `Session::store_api_key_in_keyring`.
+    #[cfg(feature = "keyring")]
+    pub fn api_key_from_keyring(mut self, service: &str) -> Result<Self, Error> {
+        self.api_key = Some(credentials::get_api_key(service, &self.username)?);
+        Ok(self)
+    }
+
+    /// Use a custom authentication scheme. By default, the session uses HTTP basic
+    /// authentication with the password and then the API key (see `auth::BasicAuth`). The
+    /// password and API key given to this builder are then ignored.
+    pub fn auth<A>(mut self, auth: A) -> Self
+        where A: AuthProvider + 'static
+    {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Authenticate with a TLS client certificate and its private key, given as a DER-encoded
+    /// PKCS #12 archive protected by `password`. The password of the user is then never sent,
+    /// unless another authentication provider is set with `auth`.
+    pub fn identity(mut self, pkcs12: &[u8], password: &str) -> Result<Self, Error> {
+        let identity = Identity::from_pkcs12_der(pkcs12, password)?;
+        self.client_builder.identity(identity);
+        self.client_options.push("identity");
+        self.client_certificate = true;
+        Ok(self)
+    }
+
+    /// Authenticate with a TLS client certificate and its private key, both PEM-encoded. See
+    /// `identity`.
+    pub fn client_cert_pem(mut self, cert: &[u8], key: &[u8]) -> Result<Self, Error> {
+        let mut pem = key.to_vec();
+        pem.push(b'\n');
+        pem.extend_from_slice(cert);
+        let identity = Identity::from_pem(&pem)?;
+        self.client_builder.identity(identity);
+        self.client_options.push("client_cert_pem");
+        self.client_certificate = true;
+        Ok(self)
+    }
+
+    /// Send the requests through the proxy at `url`, e.g. `http://proxy.corp:3128`, except for
+    /// the hosts given to `no_proxy`.
+    pub fn proxy(mut self, url: &str) -> Result<Self, Error> {
+        self.proxy.url = Some(Url::parse(url)?);
+        Ok(self)
+    }
+
+    /// Reach the given hosts, and their subdomains, without going through the proxy. `*` matches
+    /// all the hosts.
+    pub fn no_proxy(mut self, hosts: &[&str]) -> Self {
+        self.proxy
+            .no_proxy
+            .extend(hosts.iter().map(|host| host.to_string()));
+        self
+    }
+
+    /// Configure the proxy from the `HTTPS_PROXY` (or `HTTP_PROXY`) and `NO_PROXY` environment
+    /// variables. This replaces the proxy configured so far.
+    pub fn proxy_from_env(mut self) -> Result<Self, Error> {
+        self.proxy = proxy::ProxyConfig::from_env()?;
+        Ok(self)
+    }
+
+    /// Set the timeout of the requests, from the time they are sent to the time the response
+    /// body has been read. It can be overridden for a single request with
+    /// `RequestOptions::timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder.timeout(timeout);
+        self.client_options.push("timeout");
+        self
+    }
+
+    /// Set the timeout for opening a connection to the server, so that a dead server fails fast
+    /// even when the request timeout is long.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder.connect_timeout(timeout);
+        self.client_options.push("connect_timeout");
+        self
+    }
+
+    /// Set the maximum number of idle connections kept open to the server. Tools sending many
+    /// requests in parallel should raise it to at least the number of concurrent requests, so
+    /// that connections are reused instead of being opened and closed for every request.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.client_builder.pool_max_idle_per_host(max);
+        self.client_options.push("pool_max_idle_per_host");
+        self
+    }
+
+    /// Set how long an idle connection is kept open before being closed, or keep idle
+    /// connections open until the server closes them with `None`. It should be lower than the
+    /// idle timeout of the server and of the load balancers in front of it, to avoid sending a
+    /// request on a connection they are closing.
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.client_builder.pool_idle_timeout(timeout);
+        self.client_options.push("pool_idle_timeout");
+        self
+    }
+
+    /// Enable TCP keep-alive on the connections to the server, probing idle connections every
+    /// `interval` so that they are not silently dropped by firewalls.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.client_builder.tcp_keepalive(Some(interval));
+        self.client_options.push("tcp_keepalive");
+        self
+    }
+
+    /// Register an interceptor, called around every request sent by the session.
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+        where I: Interceptor + 'static
+    {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    /// Send the requests with `transport` instead of the default `reqwest` client. The client
+    /// options set on this builder are then ignored.
+    pub fn transport<T>(mut self, transport: T) -> Self
+        where T: Transport + 'static
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Use the given version of the Nuage API, e.g. `"v6"` or `"v5.0"`. The URL given to the
+    /// builder is then only the address of the server, such as `https://vsd:8443`, and the
+    /// session URL is built from it as `/nuage/api/<version>/`, with the dots of the version
+    /// replaced by underscores. The versions supported by a server are returned by
+    /// `Session::supported_versions`.
+    pub fn api_version(mut self, version: &str) -> Self {
+        self.api_version = Some(version.to_owned());
+        self
+    }
+
+    /// Log the bodies of the requests and responses, besides their method, URL, status and
+    /// latency which are always logged at the debug level. The values of the attributes that
+    /// look like credentials are redacted, but the bodies may still hold sensitive data, so this
+    /// is disabled by default.
+    pub fn log_bodies(mut self, enabled: bool) -> Self {
+        self.log_bodies = enabled;
+        self
+    }
+
+    /// Configure the underlying client builder, to set the options that the session builder does
+    /// not wrap.
+    pub fn configure_client<F>(mut self, f: F) -> Self
+        where F: FnOnce(&mut ClientBuilder)
+    {
+        f(&mut self.client_builder);
+        self.client_options.push("configure_client");
+        self
+    }
+
+    /// Use an existing client instead of building a new one, for instance to share a connection
+    /// pool between several sessions. The client options set on this builder are then ignored.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Limit the size of the response bodies the session accepts. Larger responses are rejected
+    /// with `Error::ResponseTooLarge` instead of being buffered in memory. The bodies that are
+    /// read as they are received, by `fetch_children_each`, `stream_children` and `download`,
+    /// are not limited, since they are never buffered.
+    pub fn max_response_size(mut self, limit: u64) -> Self {
+        self.max_response_size = Some(limit);
+        self
+    }
+
+    /// Set the wire format used for request and response bodies. JSON is used by default.
+    pub fn codec<C>(mut self, codec: C) -> Self
+        where C: Codec + 'static
+    {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Add a query parameter to every request, for deployments that require a tenant or a
+    /// locale parameter on all calls. It can be overridden for a single request with
+    /// `RequestOptions::query`.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Bound the number of `GET` requests the session sends at the same time. The requests
+    /// beyond that number wait, in the order they were sent, until the body of a previous
+    /// response has been read. This smooths out the bursts of reads sent by many threads sharing
+    /// a session, which otherwise all open their own connection. The long polls of a
+    /// `PushCenter` and the streamed children of `stream_children` are not bounded, see
+    /// `RequestOptions::unqueued`. A bound of 0 means no bound, which is the default.
+    pub fn max_concurrent_gets(mut self, n: usize) -> Self {
+        self.max_concurrent_gets = if n > 0 { Some(n) } else { None };
+        self
+    }
+
+    /// Set how many entities `Session::save_all` and `Session::delete_all` process at the same
+    /// time, each on its own thread. It defaults to 1, which processes them one after the other
+    /// on the calling thread.
+    pub fn bulk_parallelism(mut self, n: usize) -> Self {
+        self.bulk_parallelism = n;
+        self
+    }
+
+    /// Send at most `requests_per_second` requests per second on average, with bursts of up to
+    /// `burst` requests. The requests beyond the limit wait for their turn, whatever thread
+    /// sends them. When the server answers with `429 Too Many Requests` anyway, all the requests
+    /// are held for the delay given by its `Retry-After` header. By default, the rate is not
+    /// limited.
+    ///
+    /// It fails with an `InvalidInput` I/O error if `requests_per_second` is not a positive
+    /// number, or if `burst` is 0.
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> Result<Self, Error> {
+        ratelimit::check(requests_per_second, burst)?;
+        self.rate_limit = Some((requests_per_second, burst));
+        Ok(self)
+    }
+
+    /// Set how many times a request is sent again after a `429 Too Many Requests` response,
+    /// once the delay given by its `Retry-After` header has passed. It defaults to 3, and 0
+    /// returns the response right away.
+    pub fn max_throttled_retries(mut self, n: u32) -> Self {
+        self.max_throttled_retries = n;
+        self
+    }
+
+    /// Set a function returning the current one-time password of the user, for the servers that
+    /// require two-factor authentication. It is called every time the session logs in with the
+    /// password, when connecting and when authenticating again after the API key expired, and
+    /// the one-time password is sent in the `X-Nuage-OTP` header. It is called again for every
+    /// retry, since a one-time password can only be used once. Requests authenticated with the
+    /// API key do not need it.
+    pub fn otp_provider<F>(mut self, provider: F) -> Self
+        where F: Fn() -> String + Send + Sync + 'static
+    {
+        self.otp_provider = Some(OtpProvider(Box::new(provider)));
+        self
+    }
+
+    /// Set how many times a request is retried after authenticating again, when it is rejected
+    /// with `401 Unauthorized` because the API key expired. Once connected, the session
+    /// authenticates again with the password and fetches a new API key from the root entity. It
+    /// defaults to 1, and 0 disables re-authentication.
+    pub fn max_reauth(mut self, n: u32) -> Self {
+        self.max_reauth = n;
+        self
+    }
+
+    /// Retry the idempotent requests that fail with a connection error or a transient status,
+    /// as described by `policy`. By default, requests are not retried.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Compress the bodies of `POST` and `PUT` requests larger than `threshold` bytes with gzip.
+    /// The server must support `Content-Encoding: gzip` requests, which is not the case of all
+    /// API versions. This mostly helps bulk creations over slow links.
+    #[cfg(feature = "gzip")]
+    pub fn compress_bodies_over(mut self, threshold: usize) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
     }
 
     pub fn build(mut self) -> Result<Session, Error> {
-        Ok(Session {
-               client: self.client_builder.build()?,
-               url: self.url,
-               username: self.username,
-               password: self.password,
-               api_key: self.api_key,
-               organization: self.organization,
-           })
+        let auth = match self.auth {
+            Some(auth) => auth,
+            None if self.client_certificate => {
+                let auth = auth::CertificateAuth::new(&self.username);
+                auth.set_api_key(self.api_key);
+                Arc::new(auth) as Arc<AuthProvider>
+            }
+            None => default_auth(&self.username, self.password, self.api_key),
+        };
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                let client = match self.client {
+                    Some(client) => client,
+                    None => {
+                        for cert in self.root_certificates {
+                            self.client_builder.add_root_certificate(cert)?;
+                        }
+                        if let Some(proxy) = self.proxy.proxy() {
+                            self.client_builder.proxy(proxy);
+                        }
+                        self.client_builder.build()?
+                    }
+                };
+                Arc::new(transport::ReqwestTransport::new(client)) as Arc<Transport>
+            }
+        };
+        let headers = common_headers(self.organization.as_ref().map(|s| s.as_str()),
+                                     &*self.codec);
+        let url = session_url(self.url, self.api_version)?;
+        let inner = Inner {
+            url: url,
+            username: self.username,
+            auth: auth,
+            organization: self.organization,
+            max_response_size: self.max_response_size,
+            codec: self.codec,
+            query: self.query,
+            headers: headers,
+            current_user: RwLock::new(self.current_user),
+            listeners: events::Listeners::default(),
+            get_queue: self.max_concurrent_gets
+                .map(|n| queue::Queue::new(n, transport.clone())),
+            transport: transport,
+            bulk_parallelism: self.bulk_parallelism,
+            rate_limiter: self.rate_limit
+                .map(|(rate, burst)| ratelimit::RateLimiter::new(rate, burst)),
+            max_throttled_retries: self.max_throttled_retries,
+            max_reauth: self.max_reauth,
+            retry_policy: self.retry_policy,
+            interceptors: self.interceptors,
+            log_bodies: self.log_bodies,
+            otp_provider: self.otp_provider,
+            proxy_user: RwLock::new(self.proxy_user),
+            root: RwLock::new(None),
+            reauth: Mutex::new(()),
+            root_entity: RwLock::new(None),
+            #[cfg(feature = "gzip")]
+            compression_threshold: self.compression_threshold,
+            #[cfg(feature = "validation")]
+            mismatches: Mutex::new(Vec::new()),
+        };
+        Ok(Session { inner: Arc::new(inner) })
+    }
+
+    /// Build an asynchronous session. It uses its own client, built with the root certificates
+    /// of this builder. The options that asynchronous sessions do not support, such as the
+    /// client options, the proxy, the transport, the interceptors or the retry policy, are not
+    /// silently ignored: building fails with `Error::Unsupported` if any of them was set.
+    #[cfg(feature = "async")]
+    pub fn build_async(self,
+                       handle: &tokio_core::reactor::Handle)
+                       -> Result<AsyncSession, Error> {
+        use reqwest::unstable::async::ClientBuilder;
+
+        if let Some(option) = self.unsupported_async_option() {
+            return Err(Error::Unsupported(option));
+        }
+        let mut client_builder = ClientBuilder::new();
+        for cert in self.root_certificates {
+            client_builder.add_root_certificate(cert)?;
+        }
+        let auth = match self.auth {
+            Some(auth) => auth,
+            None => default_auth(&self.username, self.password, self.api_key),
+        };
+        let headers = common_headers(self.organization.as_ref().map(|s| s.as_str()),
+                                     &*self.codec);
+        Ok(AsyncSession::new(client_builder.build(handle)?,
+                             session_url(self.url, self.api_version)?,
+                             auth,
+                             self.codec,
+                             self.max_response_size,
+                             self.query,
+                             headers))
+    }
+
+    /// Return the name of the first option set on this builder that asynchronous sessions do
+    /// not support, if any. Since asynchronous sessions neither authenticate again nor retry
+    /// throttled requests, `max_reauth` and `max_throttled_retries` are reported when they are
+    /// changed from their defaults.
+    #[cfg(feature = "async")]
+    fn unsupported_async_option(&self) -> Option<&'static str> {
+        #[cfg(feature = "gzip")]
+        {
+            if self.compression_threshold.is_some() {
+                return Some("compress_bodies_over");
+            }
+        }
+        let unsupported = [(self.client.is_some(), "client"),
+                           (self.proxy.url.is_some(), "proxy"),
+                           (self.transport.is_some(), "transport"),
+                           (!self.interceptors.is_empty(), "interceptor"),
+                           (self.retry_policy.is_some(), "retry_policy"),
+                           (self.rate_limit.is_some(), "rate_limit"),
+                           (self.max_concurrent_gets.is_some(), "max_concurrent_gets"),
+                           (self.max_reauth != DEFAULT_MAX_REAUTH, "max_reauth"),
+                           (self.max_throttled_retries != DEFAULT_MAX_THROTTLED_RETRIES,
+                            "max_throttled_retries"),
+                           (self.otp_provider.is_some(), "otp_provider"),
+                           (self.proxy_user.is_some(), "proxy_user"),
+                           (self.log_bodies, "log_bodies")];
+        self.client_options
+            .first()
+            .cloned()
+            .or_else(|| {
+                         unsupported
+                             .iter()
+                             .find(|&&(set, _)| set)
+                             .map(|&(_, option)| option)
+                     })
+    }
+}
+
+/// Return the URL of the API, given the URL passed to the builder and the API version, if any.
+fn session_url(url: Url, api_version: Option<String>) -> Result<Url, Error> {
+    match api_version {
+        Some(version) => Ok(url.join(&format!("/nuage/api/{}/", version.replace('.', "_")))?),
+        None => Ok(url),
+    }
+}
+
+/// The authentication provider used when none is given to the builder: basic authentication with
+/// the password, and then with the API key.
+fn default_auth(username: &str, password: Secret, api_key: Option<Secret>) -> Arc<AuthProvider> {
+    let auth = auth::BasicAuth::new(username, password);
+    auth.set_api_key(api_key);
+    Arc::new(auth)
+}
+
+/// Return the headers common to all the requests, except for the authentication headers.
+fn common_headers(organization: Option<&str>, codec: &Codec) -> Headers {
+    let mut headers = Headers::new();
+
+    // X-Nuage-Organization: organization
+    if let Some(organization) = organization {
+        headers.set(XNuageOrganization(organization.to_owned()));
     }
+
+    // content-type: application/json
+    // accept: application/json
+    // (or whatever content type the codec uses)
+    headers.set(ContentType(codec.content_type()));
+    headers.set(Accept(vec![qitem(codec.content_type())]));
+
+    headers
 }
 
 header! { (XNuageOrganization, "X-Nuage-Organization") => [String] }
+header! { (XNuageFilter, "X-Nuage-Filter") => [String] }
+header! { (XNuageOrderBy, "X-Nuage-OrderBy") => [String] }
+header! { (XNuagePage, "X-Nuage-Page") => [u32] }
+header! { (XNuagePageSize, "X-Nuage-PageSize") => [u32] }
+header! { (XNuageCount, "X-Nuage-Count") => [u64] }
+header! { (XNuageProxyUser, "X-Nuage-ProxyUser") => [String] }
+header! { (XNuageAttributes, "X-Nuage-Attributes") => (String)+ }
+header! { (XNuagePatchType, "X-Nuage-PatchType") => [String] }
+header! { (XNuageOtp, "X-Nuage-OTP") => [String] }
 
+/// A session is a cheap handle to a shared connection to the API: cloning it only clones a
+/// reference, and all the clones share the same client, configuration and state. Entities hold a
+/// clone of the session they were fetched with, so they do not borrow anything and can be stored
+/// or moved to other threads freely.
+///
+/// A session is `Send` and `Sync`: the state that changes during its lifetime, such as the API
+/// key, is kept behind locks, so a single authenticated session can be shared between threads.
 #[derive(Clone, Debug)]
 pub struct Session {
-    client: Client,
-    pub url: Url,
-    pub username: String,
-    pub password: String,
-    pub api_key: Option<String>,
-    pub organization: String,
+    inner: Arc<Inner>,
 }
 
-impl<'a> Session {
-    /// Delete an entity. This consumes the entity.
-    pub fn delete<E>(&self, entity: E) -> Result<Response, Error>
-        where E: RestEntity<'a>
-    {
-        let url = self.entity_url(&entity)?;
-        let headers = self.headers();
-        let resp = self.client.delete(url)?.headers(headers).send()?;
-        Ok(resp)
+#[derive(Debug)]
+struct Inner {
+    transport: Arc<Transport>,
+    url: Url,
+    username: String,
+    auth: Arc<AuthProvider>,
+    organization: Option<String>,
+    max_response_size: Option<u64>,
+    codec: Arc<Codec>,
+    query: Vec<(String, String)>,
+    // The headers common to all the requests, except for the authentication headers. They only
+    // depend on the organization and the codec, so they are computed once.
+    headers: Headers,
+    // The attributes of the root entity the session connected with, decoded by
+    // `Session::current_user`.
+    current_user: RwLock<Option<serde_json::Value>>,
+    listeners: events::Listeners,
+    get_queue: Option<queue::Queue>,
+    bulk_parallelism: usize,
+    rate_limiter: Option<ratelimit::RateLimiter>,
+    max_throttled_retries: u32,
+    max_reauth: u32,
+    retry_policy: Option<RetryPolicy>,
+    interceptors: Vec<Box<Interceptor>>,
+    log_bodies: bool,
+    otp_provider: Option<OtpProvider>,
+    // The user the session acts on behalf of, as `user@enterprise`.
+    proxy_user: RwLock<Option<String>>,
+    // The URL of the root entity, and the function that fetches it again to obtain a new API
+    // key. They are set when connecting.
+    root: RwLock<Option<(Url, Reauthenticate)>>,
+    // Held while authenticating again, so that only one thread does it at a time.
+    reauth: Mutex<()>,
+    // The root entity returned by `Session::root`, as long as it is in use. It is not owned by
+    // the session, since it holds the session itself.
+    root_entity: RwLock<Option<CachedRoot>>,
+    #[cfg(feature = "gzip")]
+    compression_threshold: Option<usize>,
+    #[cfg(feature = "validation")]
+    mismatches: Mutex<Vec<validation::Mismatch>>,
+}
+
+/// The function returning the one-time password of the user, set with
+/// `SessionBuilder::otp_provider`.
+struct OtpProvider(Box<Fn() -> String + Send + Sync>);
+
+impl fmt::Debug for OtpProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("OtpProvider")
     }
+}
 
-    /// Save an entity.
-    pub fn save<E>(&'a self, entity: &mut E) -> Result<Response, Error>
-        where E: RestEntity<'a>
-    {
-        let headers = self.headers();
-        let url = self.entity_url(entity)?;
+/// A root entity cached by `Session::root`, as a `Weak<R>`.
+struct CachedRoot(Box<Any + Send + Sync>);
 
-        let mut resp = self.client
-            .put(url)?
-            .headers(headers)
-            .json(entity)?
-            .send()?;
+impl fmt::Debug for CachedRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CachedRoot")
+    }
+}
 
-        let mut entities: Vec<E> = resp.json()?;
-        *entity = entities.pop().ok_or(Error::NoEntity)?;
-        entity.set_session(self);
-        Ok(resp)
+/// The number of times a request is sent again after a `429 Too Many Requests` response, unless
+/// set with `SessionBuilder::max_throttled_retries`.
+const DEFAULT_MAX_THROTTLED_RETRIES: u32 = 3;
+
+/// The number of times a request is retried after authenticating again, unless set with
+/// `SessionBuilder::max_reauth`.
+const DEFAULT_MAX_REAUTH: u32 = 1;
+
+/// The maximum length of the body kept in `Error::Deserialize`.
+const MAX_BODY_SNIPPET: usize = 1024;
+
+/// The delay before polling a job for the first time, in milliseconds. It doubles after each
+/// poll, up to `MAX_JOB_POLL_DELAY_MS`.
+const FIRST_JOB_POLL_DELAY_MS: u64 = 500;
+
+/// The maximum delay between two polls of a job, in milliseconds.
+const MAX_JOB_POLL_DELAY_MS: u64 = 10_000;
+
+type Reauthenticate = fn(Session, Url) -> Result<Option<String>, Error>;
+
+/// Fetch the root entity at `url` with the password, update the current user, and return the
+/// new API key.
+fn reauthenticate<R>(session: Session, url: Url) -> Result<Option<String>, Error>
+    where R: RestRootEntity
+{
+    session.inner.auth.set_api_key(None);
+    let mut resp = session
+        .send_once(Method::Get, url, session.headers(), None, &RequestOptions::default())?;
+    let mut entities: Vec<R> = session.decode(&mut resp)?;
+    let root = entities.pop().ok_or(Error::NoEntity)?;
+    *session.inner.current_user.write().unwrap() = Some(serde_json::to_value(&root)?);
+    Ok(root.get_api_key().map(|s| s.to_string()))
+}
+
+/// Return the PEM blocks of the certificates in `bundle`. It fails if a certificate is not
+/// terminated.
+fn pem_certificates(bundle: &str) -> Result<Vec<String>, Error> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+    let mut certificates = Vec::new();
+    let mut rest = bundle;
+    while let Some(start) = rest.find(BEGIN) {
+        match rest[start..].find(END) {
+            Some(end) => {
+                let end = start + end + END.len();
+                certificates.push(rest[start..end].to_string());
+                rest = &rest[end..];
+            }
+            None => {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData,
+                                                    "truncated certificate in the CA bundle")))
+            }
+        }
     }
+    Ok(certificates)
+}
 
-    /// Create a child under the parent, and give the child a reference to the current session.
-    pub fn create_child<P, C>(&'a self, parent: &P, child: &mut C) -> Result<Response, Error>
-        where P: RestEntity<'a>,
-              C: RestEntity<'a>
-    {
-        let url = if parent.is_root() {
-            self.url.join(C::group_path())?
-        } else {
-            self.entity_url(parent)?.join(C::group_path())?
-        };
-        let headers = self.headers();
+/// Deserialize a `T` from the decoded body of the response from `url`. On failure, the start of
+/// the body is kept in the error, with the credentials redacted, to show what the server sent.
+fn from_value<T>(value: serde_json::Value, body: &[u8], url: &Url) -> Result<T, Error>
+    where T: for<'de> serde::Deserialize<'de>
+{
+    serde_json::from_value(value).map_err(|e| {
+        let mut snippet = logging::redacted(body);
+        if snippet.len() > MAX_BODY_SNIPPET {
+            let mut end = MAX_BODY_SNIPPET;
+            while !snippet.is_char_boundary(end) {
+                end -= 1;
+            }
+            snippet.truncate(end);
+            snippet.push_str("...");
+        }
+        Error::Deserialize {
+            source: e,
+            body_snippet: snippet,
+            url: url.clone(),
+        }
+    })
+}
+
+/// Wait for `delay` before retrying a request, or until the request is cancelled.
+fn wait(options: &RequestOptions, delay: Duration) {
+    match options.get_cancellation() {
+        Some(token) => {
+            token.wait(delay);
+        }
+        None => thread::sleep(delay),
+    }
+}
 
-        let mut resp = self.client
-            .post(url)?
-            .headers(headers)
-            .json(child)?
-            .send()?;
+#[allow(dead_code)]
+fn assert_session_is_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<Session>();
+}
 
-        let mut entities: Vec<C> = resp.json()?;
-        *child = entities.pop().ok_or(Error::NoEntity)?;
-        child.set_session(self);
-        Ok(resp)
+impl Session {
+    /// Return the base URL of the API.
+    pub fn url(&self) -> &Url {
+        &self.inner.url
     }
 
-    /// Fetch the children of a parent entity, and give the children a reference to the current
-    /// session.
-    pub fn fetch_children<P, C>(&'a self,
-                                parent: &P,
-                                children: &mut Vec<C>)
-                                -> Result<Response, Error>
-        where P: RestEntity<'a>,
-              C: RestEntity<'a>
-    {
-        let url = if parent.is_root() {
-            self.url.join(C::group_path())?
-        } else {
-            self.entity_url(parent)?.join(C::group_path())?
-        };
-        let headers = self.headers();
-        let mut resp = self.client.get(url)?.headers(headers).send()?;
+    /// Return the maximum size of the response bodies, if any.
+    pub fn max_response_size(&self) -> Option<u64> {
+        self.inner.max_response_size
+    }
+
+    /// Return the name of the user the session is authenticated as.
+    pub fn username(&self) -> &str {
+        &self.inner.username
+    }
+
+    /// Return the organization the session is bound to. Sessions built with
+    /// `SessionBuilder::plain` do not have any.
+    pub fn organization(&self) -> Option<&str> {
+        self.inner.organization.as_ref().map(|s| s.as_str())
+    }
 
-        // XXX: No idea why I can't just write `children = resp.json()?;`
-        let children_: Vec<C> = resp.json()?;
-        *children = children_;
+    /// Return the API key of the session, if any. The API key is obtained when connecting.
+    pub fn api_key(&self) -> Option<Secret> {
+        self.inner.auth.api_key()
+    }
 
-        for mut child in children {
-            child.set_session(self);
+    /// Return the user the session is authenticated as. It is only known after connecting, or
+    /// when the session was resumed from a state that has it. It fails if the root entity does
+    /// not describe a user.
+    pub fn current_user(&self) -> Result<Option<CurrentUser>, Error> {
+        match *self.inner.current_user.read().unwrap() {
+            Some(ref root) => Ok(Some(serde_json::from_value(root.clone())?)),
+            None => Ok(None),
         }
-        Ok(resp)
     }
 
-    /// Start a new session. The root object is populated with a reference to the session.
-    pub fn connect<R>(&'a mut self, root: &mut R) -> Result<Response, Error>
-        where R: RestRootEntity<'a>
+    /// Set the API key used to authenticate the subsequent requests. Without an API key, the
+    /// default authentication provider uses the password.
+    pub fn set_api_key(&self, api_key: Option<String>) {
+        let refreshed = api_key.is_some();
+        self.inner.auth.set_api_key(api_key.map(Secret::from));
+        if refreshed {
+            self.inner.listeners.emit(Event::ApiKeyRefreshed);
+        }
+    }
+
+    /// Act on behalf of `user` of `enterprise` in the subsequent requests, by sending the
+    /// `X-Nuage-ProxyUser` header. This requires the session's user to be allowed to impersonate
+    /// other users, as the CSP administrators are. Since clones of a session share their state,
+    /// they all impersonate the user.
+    pub fn impersonate(&self, user: &str, enterprise: &str) {
+        *self.inner.proxy_user.write().unwrap() = Some(format!("{}@{}", user, enterprise));
+    }
+
+    /// Stop impersonating a user, see `impersonate`.
+    pub fn stop_impersonation(&self) {
+        *self.inner.proxy_user.write().unwrap() = None;
+    }
+
+    /// Return the user the session is impersonating, as `user@enterprise`, if any.
+    pub fn impersonation(&self) -> Option<String> {
+        self.inner.proxy_user.read().unwrap().clone()
+    }
+
+    /// Return the state of the session, to resume it later with `Session::from_state` without
+    /// authenticating with the password again. It fails if the current user cannot be decoded,
+    /// see `current_user`.
+    pub fn serialize_state(&self) -> Result<SessionState, Error> {
+        Ok(SessionState {
+               url: self.inner.url.to_string(),
+               username: self.inner.username.clone(),
+               organization: self.inner.organization.clone(),
+               api_key: self.api_key(),
+               current_user: self.current_user()?,
+               proxy_user: self.impersonation(),
+           })
+    }
+
+    /// Resume a session from its state, with the default options. Use
+    /// `SessionBuilder::from_state` to set other options.
+    pub fn from_state(state: SessionState) -> Result<Session, Error> {
+        SessionBuilder::from_state(state)?.build()
+    }
+
+    /// Register a function called on every event in the life of the session, for instance to
+    /// update the health status of an application. Listeners are shared by all the clones of
+    /// the session, and are called from the thread that triggered the event.
+    pub fn on_event<F>(&self, listener: F)
+        where F: Fn(&Event) + Send + Sync + 'static
     {
-        let url = self.entity_url(root)?;
-        let headers = self.headers();
-        let client = self.client.clone();
-        let mut resp = client.get(url)?.headers(headers).send()?;
-        let mut entities: Vec<R> = resp.json()?;
-        *root = entities.pop().ok_or(Error::NoEntity)?;
-        self.api_key = root.get_api_key().map(|s| s.to_string());
-        root.set_session(self);
-        Ok(resp)
+        self.inner.listeners.add(Arc::new(listener));
     }
 
-    /// Fetch an entity and populate its attributes, and set its session.
-    pub fn fetch_entity<E>(&'a self, entity: &mut E) -> Result<Response, Error>
-        where E: RestEntity<'a>
+    /// Store the session's API key in the platform keyring, under `service` for the session's user
+    /// name, so that it can be reused by `SessionBuilder::api_key_from_keyring`. Does nothing if
+    /// the session does not have an API key.
+    #[cfg(feature = "keyring")]
+    pub fn store_api_key_in_keyring(&self, service: &str) -> Result<(), Error> {
+        match self.api_key() {
+            Some(api_key) => {
+                credentials::set_api_key(service, &self.inner.username, api_key.expose())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Delete an entity. This consumes the entity.
+    pub fn delete<E>(&self, entity: E) -> Result<DeleteOutcome, Error>
+        where E: RestEntity
     {
-        let url = self.entity_url(entity)?;
-        let headers = self.headers();
-        let mut resp = self.client.get(url)?.headers(headers).send()?;
-        let mut entities: Vec<E> = resp.json()?;
-        *entity = entities.pop().unwrap();
-        entity.set_session(self);
-        Ok(resp)
+        self.delete_with(entity, &RequestOptions::default())
     }
 
-    fn headers(&self) -> Headers {
-        let mut headers = Headers::new();
-
-        // X-Nuage-Organization: organization
-        headers.set(XNuageOrganization(self.organization.clone()));
-
-        // content-type: application/json
-        headers.set(ContentType(Mime(TopLevel::Application,
-                                     SubLevel::Json,
-                                     vec![(Attr::Charset, Value::Utf8)])));
-
-        // Authorization: base64("login:password")
-        // or if we have an API Key already:
-        // Authorization: base64("login:api_key")
-        headers.set(Authorization(Basic {
-                                      username: self.username.clone(),
-                                      password: self.api_key
-                                          .clone()
-                                          .or_else(|| Some(self.password.clone())),
-                                  }));
+    /// Delete an entity, with options specific to this request. If the `ETag` of the entity is
+    /// known, or with `RequestOptions::if_match`, the deletion fails with `Error::Conflict` if
+    /// the entity changed in the meantime.
+    pub fn delete_with<E>(&self,
+                          entity: E,
+                          options: &RequestOptions)
+                          -> Result<DeleteOutcome, Error>
+        where E: RestEntity
+    {
+        let url = self.entity_url(&entity)?;
+        let headers = self.conditional_headers(&entity);
+        let mut resp = self.send_with(Method::Delete, url, headers, None, options)?;
+        match resp.status() {
+            status if status.is_success() => Ok(DeleteOutcome::Deleted),
+            StatusCode::NotFound => Ok(DeleteOutcome::AlreadyGone),
+            StatusCode::MultipleChoices => {
+                let body = self.read_body(&mut resp)?;
+                let choices = outcome::Choices::decode(&body, self.response_codec(&resp))?;
+                Ok(DeleteOutcome::ConfirmationRequired { choices: choices })
+            }
+            _ => Err(self.error_from(&mut resp)),
+        }
+    }
 
-        headers
+    /// Save an entity. If the entity tracks its changes (see `RestEntity::tracks_changes`), only
+    /// the attributes modified since it was last fetched or saved are sent, so that the other
+    /// attributes are not overwritten on the server. If none was modified, no request is sent,
+    /// and the `ResponseInfo` returned is `ResponseInfo::unchanged`.
+    pub fn save<E>(&self, entity: &mut E) -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        self.save_with(entity, &RequestOptions::default())
     }
 
-    fn entity_url<E>(&self, entity: &E) -> Result<Url, Error>
-        where E: RestEntity<'a>
+    /// Save an entity, with options specific to this request. If the `ETag` of the entity is
+    /// known, the save fails with `Error::Conflict` if the entity changed in the meantime.
+    pub fn save_with<E>(&self,
+                        entity: &mut E,
+                        options: &RequestOptions)
+                        -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        self.save_entity(entity, options, true)
+    }
+
+    /// Save all the attributes of an entity, whether it tracks its changes or not.
+    pub fn save_full<E>(&self, entity: &mut E) -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        self.save_entity(entity, &RequestOptions::default(), false)
+    }
+
+    fn save_entity<E>(&self,
+                      entity: &mut E,
+                      options: &RequestOptions,
+                      partial: bool)
+                      -> Result<ResponseInfo, Error>
+        where E: RestEntity
     {
-        let url = self.url
-            .join(E::path())?
-            .join(entity.id().ok_or(Error::MissingId)?)?;
-        Ok(url)
+        entity.on_saving();
+        let mut headers = self.conditional_headers(entity);
+        let url = self.entity_url(entity)?;
+        let body = if partial && entity.original().is_some() && entity.raw_body().is_none() {
+            let changes = entity
+                .changes()?
+                .into_iter()
+                .map(|change| (change.field, change.new))
+                .collect::<serde_json::Map<_, _>>();
+            if changes.is_empty() {
+                return Ok(ResponseInfo::unchanged(url));
+            }
+            self.encode(&changes)?
+        } else {
+            self.entity_body(entity, &mut headers)?
+        };
+        let mut resp = self.send_with(Method::Put, url, headers, Some(body), options)?;
+
+        let mut entities: Vec<E> = self.decode(&mut resp)?;
+        *entity = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(entity);
+        self.track_etag(entity, &resp);
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Delete several entities, concurrently if `SessionBuilder::bulk_parallelism` allows it, and
+    /// return the outcome of each deletion, in the order of the entities. A failure does not stop
+    /// the other deletions.
+    pub fn delete_all<E>(&self, entities: Vec<E>) -> Vec<Result<DeleteOutcome, Error>>
+        where E: RestEntity + Send + 'static
+    {
+        bulk::run(self,
+                  entities,
+                  self.inner.bulk_parallelism,
+                  |session, entity| session.delete(entity))
+    }
+
+    /// Save several entities, concurrently if `SessionBuilder::bulk_parallelism` allows it, and
+    /// return the result of each save, in the order of the entities. The entities that were saved
+    /// are populated from the response, and the others are left untouched. A failure does not
+    /// stop the other saves.
+    pub fn save_all<E>(&self, entities: &mut [E]) -> Vec<Result<ResponseInfo, Error>>
+        where E: RestEntity + Clone + Send + 'static
+    {
+        let results = bulk::run(self,
+                                entities.to_vec(),
+                                self.inner.bulk_parallelism,
+                                |session, mut entity| -> Result<(E, ResponseInfo), Error> {
+                                    let resp = session.save(&mut entity)?;
+                                    Ok((entity, resp))
+                                });
+        entities
+            .iter_mut()
+            .zip(results)
+            .map(|(entity, result)| {
+                     result.map(|(saved, resp)| {
+                                    *entity = saved;
+                                    resp
+                                })
+                 })
+            .collect()
+    }
+
+    /// Partially update an entity with a JSON Patch, a merge patch or a set of attributes, for
+    /// servers that support the `PATCH` method. If the server answers with the updated entity,
+    /// its attributes are populated from the response. Otherwise, the attributes of a
+    /// `Patch::Partial` are applied to the entity.
+    pub fn patch<E>(&self, entity: &mut E, patch: &Patch) -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        let mut headers = self.conditional_headers(entity);
+        headers.set(ContentType(patch.content_type()));
+        let url = self.entity_url(entity)?;
+        let mut resp = self.send(Method::Patch, url, headers, Some(patch.body()?))?;
+        self.check_status(&mut resp)?;
+
+        let body = self.read_body(&mut resp)?;
+        if !body.is_empty() {
+            let mut entities: Vec<E> = self.decode_body(&body, resp.url())?;
+            *entity = entities.pop().ok_or(Error::NoEntity)?;
+            self.bind(entity);
+            self.track_etag(entity, &resp);
+        } else if let Patch::Partial(ref attributes) = *patch {
+            let mut value = serde_json::to_value(&*entity)?;
+            if let Some(object) = value.as_object_mut() {
+                for (name, attribute) in attributes {
+                    object.insert(name.clone(), attribute.clone());
+                }
+            }
+            *entity = serde_json::from_value(value)?;
+            self.bind(entity);
+            self.track_etag(entity, &resp);
+        }
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Update the attributes of an entity modified since it was last fetched or saved, with a
+    /// `PATCH` request instead of a `PUT`. If the entity does not track its changes, or was never
+    /// fetched, all its attributes are sent. If none was modified, no request is sent, and the
+    /// `ResponseInfo` returned is `ResponseInfo::unchanged`.
+    pub fn patch_changes<E>(&self, entity: &mut E) -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        let attributes = if entity.original().is_some() {
+            let changes = entity
+                .changes()?
+                .into_iter()
+                .map(|change| (change.field, change.new))
+                .collect::<serde_json::Map<_, _>>();
+            if changes.is_empty() {
+                return Ok(ResponseInfo::unchanged(self.entity_url(entity)?));
+            }
+            changes
+        } else {
+            match serde_json::to_value(&*entity)? {
+                serde_json::Value::Object(attributes) => attributes,
+                _ => serde_json::Map::new(),
+            }
+        };
+        self.patch(entity, &Patch::Partial(attributes))
+    }
+
+    /// Create a child under the parent, and return it as populated by the server, with a
+    /// reference to the current session.
+    pub fn create_child<P, C>(&self, parent: &P, child: C) -> Result<C, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        self.create_child_with(parent, child, &RequestOptions::default())
+    }
+
+    /// Create a child under the parent, with options specific to this request.
+    pub fn create_child_with<P, C>(&self,
+                                   parent: &P,
+                                   mut child: C,
+                                   options: &RequestOptions)
+                                   -> Result<C, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        child.on_saving();
+        let mut headers = self.headers();
+        let body = self.entity_body(&child, &mut headers)?;
+        let mut resp = self.send_with(Method::Post, url, headers, Some(body), options)?;
+
+        let mut entities: Vec<C> = self.decode(&mut resp)?;
+        let mut child = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(&mut child);
+        self.track_etag(&mut child, &resp);
+        child.on_created();
+        Ok(child)
+    }
+
+    /// Create several children under the parent with a single request, and replace them with the
+    /// entities populated by the server, in the same order. The entities' `raw_body` is not used:
+    /// they are always sent as an array serialized with the session's codec.
+    pub fn create_children<P, C>(&self,
+                                 parent: &P,
+                                 children: &mut Vec<C>)
+                                 -> Result<ResponseInfo, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        for child in children.iter_mut() {
+            child.on_saving();
+        }
+        let body = self.encode(children)?;
+        let mut resp = self.send(Method::Post, url, self.headers(), Some(body))?;
+
+        let created: Vec<C> = self.decode(&mut resp)?;
+        if created.len() != children.len() {
+            return Err(Error::NoEntity);
+        }
+        *children = created;
+        for child in children.iter_mut() {
+            self.bind(child);
+            child.on_created();
+        }
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Fetch the children of a parent entity, and give the children a reference to the current
+    /// session.
+    pub fn fetch_children<P, C>(&self, parent: &P) -> Result<Vec<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        self.fetch_children_with(parent, &RequestOptions::default())
+    }
+
+    /// Fetch the children of a parent entity, with options specific to this request.
+    pub fn fetch_children_with<P, C>(&self,
+                                     parent: &P,
+                                     options: &RequestOptions)
+                                     -> Result<Vec<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        self.fetch_children_at(url, options)
+    }
+
+    /// Return a fetcher for the children of a parent entity, to filter, order or paginate them.
+    pub fn fetcher<P, C>(&self, parent: &P) -> Result<Fetcher<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        Ok(Fetcher::new(self.clone(), url))
+    }
+
+    /// Return an iterator over the children of a parent entity. The children are fetched page
+    /// by page as the iterator advances, until all of them have been returned.
+    pub fn iter_children<P, C>(&self, parent: &P) -> Result<Children<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        Ok(self.fetcher::<P, C>(parent)?.iter())
+    }
+
+    /// Return the number of children of a parent entity, as announced by the server in the
+    /// `X-Nuage-Count` header of a `HEAD` request, without fetching them.
+    pub fn count_children<P, C>(&self, parent: &P) -> Result<u64, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        self.count_at(url, &RequestOptions::default())
+    }
+
+    fn count_at(&self, url: Url, options: &RequestOptions) -> Result<u64, Error> {
+        let resp = self.send_with(Method::Head, url, self.headers(), None, options)?;
+        if !resp.status().is_success() {
+            // A response to a `HEAD` request has no body, hence no error description.
+            return Err(Error::from_response(resp.status(), &[]));
+        }
+        resp.headers()
+            .get::<XNuageCount>()
+            .map(|count| count.0)
+            .ok_or(Error::MissingCount)
+    }
+
+    fn fetch_children_at<C>(&self, url: Url, options: &RequestOptions) -> Result<Vec<C>, Error>
+        where C: RestEntity
+    {
+        Ok(self.fetch_page_at(url, options)?.into_vec())
+    }
+
+    /// Fetch a page of children, with the pagination headers of the response.
+    fn fetch_page_at<C>(&self, url: Url, options: &RequestOptions) -> Result<Page<C>, Error>
+        where C: RestEntity
+    {
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, options)?;
+        let mut children: Vec<C> = self.decode(&mut resp)?;
+        for child in &mut children {
+            self.bind(child);
+        }
+        Ok(Page::new(children, &resp))
+    }
+
+    /// Fetch the IDs (and names, if any) of the children of a parent entity, without
+    /// deserializing the full entities. Only these attributes are requested, with the
+    /// `X-Nuage-Attributes` header, so the server does not send the others.
+    pub fn fetch_children_ids<P, C>(&self, parent: &P) -> Result<Vec<IdEntry>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        let mut headers = self.headers();
+        headers.set(XNuageAttributes(vec!["ID".to_string(), "name".to_string()]));
+        let mut resp = self.send(Method::Get, url, headers, None)?;
+        self.decode_list(&mut resp)
+    }
+
+    /// Assign existing entities to a parent entity, for the relationships where the children are
+    /// not owned by the parent, such as the users of a group. The server replaces the assigned
+    /// children with `children`, so an empty slice unassigns all of them.
+    pub fn assign<P, C>(&self, parent: &P, children: &[&C]) -> Result<(), Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let ids = children
+            .iter()
+            .map(|child| child.id().ok_or(Error::MissingId))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_ids::<P, C>(parent, &ids, None)
+    }
+
+    /// Assign existing entities to a parent entity, in addition to the children already
+    /// assigned. This is a single request, with the `X-Nuage-PatchType: ADD` header.
+    pub fn assign_add<P, C>(&self, parent: &P, children: &[&C]) -> Result<(), Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let ids = children
+            .iter()
+            .map(|child| child.id().ok_or(Error::MissingId))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_ids::<P, C>(parent, &ids, Some("ADD"))
+    }
+
+    /// Unassign entities from a parent entity, and leave the other assigned children as they
+    /// are. This is a single request, with the `X-Nuage-PatchType: REMOVE` header.
+    pub fn assign_remove<P, C>(&self, parent: &P, children: &[&C]) -> Result<(), Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let ids = children
+            .iter()
+            .map(|child| child.id().ok_or(Error::MissingId))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_ids::<P, C>(parent, &ids, Some("REMOVE"))
+    }
+
+    /// Unassign entities from a parent entity, and leave the other assigned children as they
+    /// are. The assigned children are fetched first and the rest of them assigned again, so
+    /// this is not atomic, but it works with the servers that do not support
+    /// `X-Nuage-PatchType`. Otherwise, prefer `assign_remove`.
+    pub fn unassign<P, C>(&self, parent: &P, children: &[&C]) -> Result<(), Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let removed = children
+            .iter()
+            .map(|child| child.id().ok_or(Error::MissingId))
+            .collect::<Result<Vec<_>, _>>()?;
+        let assigned = self.fetch_children_ids::<P, C>(parent)?;
+        let ids = assigned
+            .iter()
+            .map(|entry| entry.id.as_str())
+            .filter(|id| !removed.contains(id))
+            .collect::<Vec<_>>();
+        self.assign_ids::<P, C>(parent, &ids, None)
+    }
+
+    /// Assign the children with the given IDs. Without a patch type, they replace the assigned
+    /// children; with `ADD` or `REMOVE`, they are added to or removed from them.
+    fn assign_ids<P, C>(&self,
+                        parent: &P,
+                        ids: &[&str],
+                        patch_type: Option<&str>)
+                        -> Result<(), Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        let body = self.encode(&ids)?;
+        let mut headers = self.headers();
+        if let Some(patch_type) = patch_type {
+            headers.set(XNuagePatchType(patch_type.to_owned()));
+        }
+        let mut resp = self.send(Method::Put, url, headers, Some(body))?;
+        self.check_status(&mut resp)
+    }
+
+    /// Fetch the child of a parent entity whose alternate key (see `RestEntity::alternate_key`)
+    /// has the given value. Return `Ok(None)` if there is no such child, and an error if several
+    /// children match.
+    pub fn fetch_by_key<P, C>(&self, parent: &P, value: &str) -> Result<Option<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let key = C::alternate_key().ok_or(Error::MissingKey)?;
+        self.fetch_unique_by(parent, key, value)
+    }
+
+    /// Return the URL of the child of a parent entity whose alternate key (see
+    /// `RestEntity::alternate_key`) has the given value, for the requests that are not modeled
+    /// by the entity, such as `request_raw`. The API only addresses entities by ID, so the child
+    /// is looked up first: `Error::NoEntity` is returned if there is no such child.
+    pub fn key_url<P, C>(&self, parent: &P, value: &str) -> Result<Url, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let child = self.fetch_by_key::<P, C>(parent, value)?
+            .ok_or(Error::NoEntity)?;
+        self.entity_url(&child)
+    }
+
+    /// Fetch the child of a parent entity whose `externalID` is `external_id`, which is the usual
+    /// way to find the entities created by a cloud management system. Return `Ok(None)` if there
+    /// is no such child, and an error if several children match.
+    pub fn fetch_by_external_id<P, C>(&self,
+                                      parent: &P,
+                                      external_id: &str)
+                                      -> Result<Option<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        self.fetch_unique_by(parent, "externalID", external_id)
+    }
+
+    /// Create a child under the parent, or update it if a child with the same alternate key (see
+    /// `RestEntity::alternate_key`) already exists. Return the child as populated by the server.
+    pub fn upsert_child<P, C>(&self, parent: &P, child: C) -> Result<C, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let key = C::alternate_key().ok_or(Error::MissingKey)?;
+        let mut value = serde_json::to_value(&child)?;
+        let key_value = match value.get(key) {
+            Some(&serde_json::Value::String(ref s)) => s.clone(),
+            Some(&serde_json::Value::Number(ref n)) => n.to_string(),
+            _ => return Err(Error::MissingKey),
+        };
+
+        match self.fetch_unique_by::<P, C>(parent, key, &key_value)? {
+            None => self.create_child(parent, child),
+            Some(existing) => {
+                // Give the child the ID of the existing entity, so that it can be saved.
+                let id = existing.id().ok_or(Error::MissingId)?;
+                match value.as_object_mut() {
+                    Some(object) => {
+                        object.insert("ID".to_owned(), serde_json::Value::String(id.to_owned()))
+                    }
+                    None => {
+                        return Err(Error::Codec("the entity is not serialized as an object"
+                                                    .to_string()))
+                    }
+                };
+                let mut child: C = serde_json::from_value(value)?;
+                // The entity may not read its ID from the `ID` attribute.
+                if child.id() != Some(id) {
+                    return Err(Error::MissingId);
+                }
+                self.save(&mut child)?;
+                Ok(child)
+            }
+        }
+    }
+
+    /// Fetch the only child whose `attribute` is equal to `value`.
+    fn fetch_unique_by<P, C>(&self,
+                             parent: &P,
+                             attribute: &str,
+                             value: &str)
+                             -> Result<Option<C>, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let filter = Filter::field(attribute).eq(value);
+        let options = RequestOptions::new().header(XNuageFilter(filter.to_string()));
+        let mut children: Vec<C> = self.fetch_children_with(parent, &options)?;
+        match children.len() {
+            0 | 1 => Ok(children.pop()),
+            n => Err(Error::DuplicateKey(n)),
+        }
+    }
+
+    /// Fetch the children of a parent entity, deserializing them one by one as the response body
+    /// is read. Each child is given a reference to the current session and passed to `callback`.
+    /// Unlike `fetch_children`, the children are never collected, so memory usage stays flat even
+    /// for very large collections. The body is requested and parsed as JSON, whatever codec the
+    /// session uses.
+    pub fn fetch_children_each<P, C, F>(&self,
+                                        parent: &P,
+                                        callback: F)
+                                        -> Result<ResponseInfo, Error>
+        where P: RestEntity,
+              C: RestEntity,
+              F: FnMut(C)
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        let mut resp = self.send(Method::Get, url, self.json_headers(), None)?;
+        self.check_status(&mut resp)?;
+
+        {
+            let mut de = serde_json::Deserializer::from_reader(BufReader::new(&mut resp));
+            de.deserialize_seq(stream::EachChild::new(self, callback))
+                .map_err(body::from_json)?;
+            de.end().map_err(body::from_json)?;
+        }
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Fetch the children of a parent entity, and return an iterator that deserializes them one
+    /// by one as the response body is read. This is `fetch_children_each` without the callback:
+    /// only the child being deserialized is held in memory, so memory usage stays flat even for
+    /// very large collections. The body is requested and parsed as JSON, whatever codec the
+    /// session uses.
+    ///
+    /// ```rust,ignore
+    /// for vport in session.stream_children::<_, VPort>(&enterprise)? {
+    ///     let vport = vport?;
+    ///     println!("{:?}", vport.name);
+    /// }
+    /// ```
+    pub fn stream_children<P, C>(&self, parent: &P) -> Result<ChildStream<C>, Error>
+        where P: RestEntity,
+              C: RestEntity + Send + 'static
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        self.stream_children_at(url, &RequestOptions::new())
+    }
+
+    fn stream_children_at<C>(&self,
+                             url: Url,
+                             options: &RequestOptions)
+                             -> Result<ChildStream<C>, Error>
+        where C: RestEntity + Send + 'static
+    {
+        // The body is read as the stream is consumed, which may take long.
+        let options = options.clone().unqueued();
+        let mut resp = self.send_with(Method::Get, url, self.json_headers(), None, &options)?;
+        self.check_status(&mut resp)?;
+        let cancellation = options.get_cancellation().cloned();
+        Ok(ChildStream::new(self.clone(), resp, cancellation))
+    }
+
+    /// Fetch the children of a parent entity, and return the raw response body instead of
+    /// deserializing it. This lets the caller deserialize the children into types that borrow from
+    /// the body, for instance slim structs with `&str` fields, without allocating every string.
+    /// The body is requested as JSON, whatever codec the session uses.
+    pub fn fetch_children_buffer<P, C>(&self, parent: &P) -> Result<Buffer, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        let url = self.children_url::<P, C>(parent)?;
+        let mut resp = self.send(Method::Get, url, self.json_headers(), None)?;
+        self.check_status(&mut resp)?;
+        let bytes = self.read_body(&mut resp)?;
+        Ok(Buffer::new(resp, bytes))
+    }
+
+    /// Download the resource at `path` (relative to the session URL) and copy the response body to
+    /// `writer` as it is received. This is meant for endpoints serving non-JSON content, such as
+    /// certificates or archives. Since the body is never buffered, the maximum response size
+    /// does not apply.
+    pub fn download<W>(&self, path: &str, writer: &mut W) -> Result<ResponseInfo, Error>
+        where W: io::Write
+    {
+        let url = self.inner.url.join(path)?;
+        let mut resp = self.send(Method::Get, url, self.headers(), None)?;
+        self.check_status(&mut resp)?;
+        io::copy(&mut resp, writer)?;
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Upload a file or a raw body to the resource at `path` (relative to the session URL). This
+    /// is meant for endpoints that do not accept JSON, such as certificate or image uploads.
+    pub fn upload(&self, path: &str, upload: Upload) -> Result<ResponseInfo, Error> {
+        let url = self.inner.url.join(path)?;
+        let mut headers = self.headers();
+        let (content_type, body) = upload.into_body();
+        headers.set(ContentType(content_type));
+        let mut resp = self.send(Method::Post, url, headers, Some(body))?;
+        self.check_status(&mut resp)?;
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Open up to `n` connections to the server ahead of time, by sending `n` concurrent `HEAD`
+    /// requests to the session URL. The connections, including their TLS handshake, are then kept
+    /// in the client's pool and reused by the subsequent requests. The requests are sent through
+    /// the transport and the interceptors like any other. The status of the responses is
+    /// ignored: only connection errors are reported.
+    pub fn warm_up(&self, n: usize) -> Result<(), Error> {
+        let threads = (0..n)
+            .map(|_| {
+                let session = self.clone();
+                thread::spawn(move || -> Result<(), Error> {
+                    let url = session.inner.url.clone();
+                    session
+                        .send_once(Method::Head,
+                                   url,
+                                   session.headers(),
+                                   None,
+                                   &RequestOptions::default())?;
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread
+                .join()
+                .map_err(|_| {
+                             Error::Io(io::Error::new(io::ErrorKind::Other,
+                                                      "a warm up thread panicked"))
+                         })??;
+        }
+        Ok(())
+    }
+
+    /// Send a request to `path` (relative to the session URL) with the session's authentication
+    /// and organization headers. This is an escape hatch for the endpoints that are not modeled
+    /// by any entity. The response is returned as is.
+    pub fn request_raw(&self,
+                       method: Method,
+                       path: &str,
+                       body: Option<Vec<u8>>)
+                       -> Result<Response, Error> {
+        let url = self.inner.url.join(path)?;
+        self.send(method, url, self.headers(), body)
+    }
+
+    /// Send a `GET` request to `path` (relative to the session URL) and decode the response into
+    /// a `T`. This is meant for one-off endpoints, such as system configurations or licenses,
+    /// that do not deserve a full `RestEntity` implementation.
+    pub fn get_as<T>(&self, path: &str) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>
+    {
+        self.request_as::<T, ()>(Method::Get, path, None)
+    }
+
+    /// Send a `POST` request with `body` to `path` and decode the response into a `T`.
+    pub fn post_as<T, B>(&self, path: &str, body: &B) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>,
+              B: Serialize
+    {
+        self.request_as(Method::Post, path, Some(body))
+    }
+
+    /// Send a `PUT` request with `body` to `path` and decode the response into a `T`.
+    pub fn put_as<T, B>(&self, path: &str, body: &B) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>,
+              B: Serialize
+    {
+        self.request_as(Method::Put, path, Some(body))
+    }
+
+    /// Send a `PATCH` request with `body` to `path` and decode the response into a `T`.
+    pub fn patch_as<T, B>(&self, path: &str, body: &B) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>,
+              B: Serialize
+    {
+        self.request_as(Method::Patch, path, Some(body))
+    }
+
+    /// Send an `OPTIONS` request to `path` and decode the response into a `T`.
+    pub fn options_as<T>(&self, path: &str) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>
+    {
+        self.request_as::<T, ()>(Method::Options, path, None)
+    }
+
+    /// Send a `HEAD` request to `path`, and return the status and headers of the response.
+    pub fn head(&self, path: &str) -> Result<ResponseInfo, Error> {
+        Ok(ResponseInfo::new(&self.request_raw(Method::Head, path, None)?))
+    }
+
+    /// Send a request with an optional body to `path` and decode the response into a `T`. An
+    /// empty response is decoded as `null`, so that `T` can be `()` or an `Option`.
+    pub fn request_as<T, B>(&self,
+                            method: Method,
+                            path: &str,
+                            body: Option<&B>)
+                            -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>,
+              B: Serialize
+    {
+        let body = match body {
+            Some(body) => Some(self.encode(body)?),
+            None => None,
+        };
+        let mut resp = self.request_raw(method, path, body)?;
+        self.decode_value(&mut resp)
+    }
+
+    /// Prepare a request to `path` (relative to the session URL), for the endpoints that are not
+    /// modeled by any entity. The path is resolved like a link, as with `request_raw`: an
+    /// absolute path such as `/nuage/v5_0/...` replaces the path of the session URL, and a query
+    /// may follow the path. Unlike `request_raw`, headers, query parameters and a timeout can be
+    /// set before sending it.
+    pub fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, Error> {
+        let url = self.inner.url.join(path)?;
+        Ok(RequestBuilder::new(self.clone(), method, url))
+    }
+
+    /// Fetch the entity with the given ID and rest paths as a `GenericEntity`, for the types that
+    /// have no `RestEntity` implementation.
+    pub fn fetch_generic(&self,
+                         path: &str,
+                         group_path: &str,
+                         id: &str)
+                         -> Result<GenericEntity, Error> {
+        let mut entity = GenericEntity::new(path, group_path);
+        entity.set_id(id);
+        entity.set_session(self.clone());
+        entity.fetch()?;
+        Ok(entity)
+    }
+
+    /// Fetch the children of a parent entity whose rest paths are `path` and `group_path`, as
+    /// `GenericEntity`s.
+    pub fn fetch_generic_children<P>(&self,
+                                     parent: &P,
+                                     path: &str,
+                                     group_path: &str)
+                                     -> Result<Vec<GenericEntity>, Error>
+        where P: RestEntity
+    {
+        self.fetch_generic_at(self.group_url(parent, group_path)?, path, group_path)
+    }
+
+    /// Create an entity of any type at the root of the API, and return it as populated by the
+    /// server. Entities are created under a generic parent with `GenericEntity::create_child`.
+    pub fn create_generic(&self, entity: GenericEntity) -> Result<GenericEntity, Error> {
+        let url = path::group(&self.inner.url, None, entity.group_path())?;
+        self.create_generic_at(url, entity)
+    }
+
+    /// Fetch the entities at `url`, whose rest paths are `path` and `group_path`, as
+    /// `GenericEntity`s.
+    fn fetch_generic_at(&self,
+                        url: Url,
+                        path: &str,
+                        group_path: &str)
+                        -> Result<Vec<GenericEntity>, Error> {
+        Ok(self.send_generic(Method::Get, url, None)?
+               .unwrap_or_default()
+               .into_iter()
+               .map(|attributes| GenericEntity::from_attributes(self, path, group_path, attributes))
+               .collect())
+    }
+
+    /// Create a `GenericEntity` with a `POST` request to `url`, and return it as populated by
+    /// the server.
+    fn create_generic_at(&self, url: Url, entity: GenericEntity) -> Result<GenericEntity, Error> {
+        let attributes = self.send_generic(Method::Post, url, Some(entity.attributes()))?
+            .and_then(|mut entities| entities.pop())
+            .ok_or(Error::NoEntity)?;
+        Ok(GenericEntity::from_attributes(self, entity.path(), entity.group_path(), attributes))
+    }
+
+    /// Send a request for a `GenericEntity`, with the given attributes as body, and return the
+    /// entities in the response body, if any.
+    fn send_generic(&self,
+                    method: Method,
+                    url: Url,
+                    attributes: Option<&serde_json::Map<String, serde_json::Value>>)
+                    -> Result<Option<Vec<serde_json::Map<String, serde_json::Value>>>, Error> {
+        let body = match attributes {
+            Some(attributes) => Some(self.encode(attributes)?),
+            None => None,
+        };
+        let mut resp = self.send(method, url, self.headers(), body)?;
+        self.decode_value(&mut resp)
+    }
+
+    /// Query the server for the API versions it supports and its system configuration. The
+    /// versions are read from `/nuage` on the server, and the system configuration from the
+    /// `systemconfigs` resource of the API. Since only some users may read the system
+    /// configuration, it is left empty if it cannot be fetched.
+    pub fn server_info(&self) -> Result<ServerInfo, Error> {
+        let versions = self.supported_versions()?;
+        let system_config = self.get_as::<Vec<serde_json::Value>>("systemconfigs")
+            .ok()
+            .and_then(|mut configs| configs.pop());
+        Ok(ServerInfo {
+               versions: versions,
+               system_config: system_config,
+           })
+    }
+
+    /// Return the API versions supported by the server, as listed by `/nuage`. This does not
+    /// require the session to be connected.
+    pub fn supported_versions(&self) -> Result<Vec<ApiVersion>, Error> {
+        let versions: server::Versions = self.get_as("/nuage")?;
+        Ok(versions.versions)
+    }
+
+    /// Fetch the alarms of an entity matching `query`.
+    pub fn fetch_alarms<P>(&self, parent: &P, query: &AlarmQuery) -> Result<Vec<Alarm>, Error>
+        where P: RestEntity
+    {
+        self.fetch_alarms_at(self.group_url(parent, "alarms")?, &query.options())
+    }
+
+    /// Watch the alarms of an entity matching `query`, by polling them every `interval`. The
+    /// returned watcher yields the alarms raised since the previous poll. To receive the alarms
+    /// from the event channel instead, see `PushCenter::subscribe_alarms`.
+    pub fn watch_alarms<P>(&self,
+                           parent: &P,
+                           query: AlarmQuery,
+                           interval: Duration)
+                           -> Result<alarm::AlarmWatcher, Error>
+        where P: RestEntity
+    {
+        let url = self.group_url(parent, "alarms")?;
+        Ok(alarm::AlarmWatcher::new(self.clone(), url, query, interval))
+    }
+
+    fn fetch_alarms_at(&self, url: Url, options: &RequestOptions) -> Result<Vec<Alarm>, Error> {
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, options)?;
+        self.decode_list(&mut resp)
+    }
+
+    /// Return a fetcher for the entries of the server's event log.
+    pub fn events(&self) -> Result<EventLog, Error> {
+        Ok(EventLog::new(self.clone(), path::append(&self.inner.url, &["eventlogs"])?))
+    }
+
+    /// Return a fetcher for the entries of the event log of an entity.
+    pub fn events_of<P>(&self, parent: &P) -> Result<EventLog, Error>
+        where P: RestEntity
+    {
+        Ok(EventLog::new(self.clone(), self.group_url(parent, "eventlogs")?))
+    }
+
+    fn fetch_event_log_at(&self,
+                          url: Url,
+                          options: &RequestOptions)
+                          -> Result<Vec<EventLogEntry>, Error> {
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, options)?;
+        self.decode_list(&mut resp)
+    }
+
+    /// Run a job under a parent entity, for instance an `"EXPORT"` of an enterprise, and wait
+    /// until it is finished, for at most `timeout`. The result of the job is returned if it
+    /// succeeded, and `Error::JobFailed` otherwise. See `wait_job`.
+    ///
+    /// ```rust,ignore
+    /// let export = session.run_job(&enterprise, "EXPORT", json!({}), Duration::from_secs(600))?;
+    /// ```
+    pub fn run_job<P>(&self,
+                      parent: &P,
+                      command: &str,
+                      parameters: serde_json::Value,
+                      timeout: Duration)
+                      -> Result<serde_json::Value, Error>
+        where P: RestEntity
+    {
+        let job = self.create_job(parent, command, parameters)?;
+        let job = self.wait_job(job, timeout)?;
+        Ok(job.result.unwrap_or(serde_json::Value::Null))
+    }
+
+    /// Create a job under a parent entity, without waiting for it to finish.
+    pub fn create_job<P>(&self,
+                         parent: &P,
+                         command: &str,
+                         parameters: serde_json::Value)
+                         -> Result<Job, Error>
+        where P: RestEntity
+    {
+        let url = self.group_url(parent, "jobs")?;
+        let body = self.encode(&Job::new(command, parameters))?;
+        let mut resp = self.send(Method::Post, url, self.headers(), Some(body))?;
+        self.decode_list(&mut resp)?.pop().ok_or(Error::NoEntity)
+    }
+
+    /// Fetch the current state of a job.
+    pub fn fetch_job(&self, id: &str) -> Result<Job, Error> {
+        self.fetch_job_with(id, &RequestOptions::default())
+    }
+
+    fn fetch_job_with(&self, id: &str, options: &RequestOptions) -> Result<Job, Error> {
+        let url = path::entity(&self.inner.url, "jobs", id)?;
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, options)?;
+        self.decode_list(&mut resp)?.pop().ok_or(Error::NoEntity)
+    }
+
+    /// Poll a job until it is finished, waiting longer and longer between the polls, and return
+    /// it if it succeeded or `Error::JobFailed` if it failed. If the job is not finished after
+    /// `timeout`, `Error::Timeout` is returned, and the job keeps running on the server.
+    pub fn wait_job(&self, job: Job, timeout: Duration) -> Result<Job, Error> {
+        self.wait_job_with(job, timeout, &RequestOptions::default())
+    }
+
+    /// Poll a job like `wait_job`, with options applying to every poll. If the options have a
+    /// cancellation token, waiting stops with `Error::Cancelled` as soon as it is cancelled.
+    pub fn wait_job_with(&self,
+                         mut job: Job,
+                         timeout: Duration,
+                         options: &RequestOptions)
+                         -> Result<Job, Error> {
+        let id = job.id.clone().ok_or(Error::MissingId)?;
+        let start = Instant::now();
+        let mut delay = Duration::from_millis(FIRST_JOB_POLL_DELAY_MS);
+        while !job.is_finished() {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(Error::Timeout(timeout));
+            }
+            wait(options, cmp::min(delay, timeout - elapsed));
+            if let Some(token) = options.get_cancellation() {
+                token.check()?;
+            }
+            delay = cmp::min(delay * 2, Duration::from_millis(MAX_JOB_POLL_DELAY_MS));
+            job = self.fetch_job_with(&id, options)?;
+        }
+        if job.failed() {
+            return Err(Error::JobFailed {
+                           command: job.command,
+                           result: job.result,
+                       });
+        }
+        Ok(job)
+    }
+
+    /// Fetch the statistics of an entity, from its `statistics` endpoint, for the given metrics
+    /// (e.g. `"BYTES_IN"` and `"PACKETS_IN"`) between `start` and `end`, in seconds since the
+    /// Unix epoch, with one data point every `frequency`.
+    pub fn statistics<E>(&self,
+                         entity: &E,
+                         metrics: &[&str],
+                         start: i64,
+                         end: i64,
+                         frequency: Duration)
+                         -> Result<TimeSeries, Error>
+        where E: RestEntity
+    {
+        let url = self.group_url(entity, "statistics")?;
+        let points = (end - start) / cmp::max(frequency.as_secs() as i64, 1);
+        let options = RequestOptions::new()
+            .query("startTime", &start.to_string())
+            .query("endTime", &end.to_string())
+            .query("numberOfDataPoints", &points.to_string())
+            .query("metricTypes", &metrics.join(","));
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, &options)?;
+        let statistics: Statistics = self.decode_list(&mut resp)?
+            .pop()
+            .ok_or(Error::NoEntity)?;
+        Ok(statistics.into())
+    }
+
+    /// Start a new session. The root object is populated with a reference to the session.
+    ///
+    /// The API key and the current user are stored behind locks and shared by all the clones of
+    /// the session, so a session shared by several threads can connect again, for instance when
+    /// the API key expires, without being borrowed mutably.
+    pub fn connect<R>(&self, root: &mut R) -> Result<ResponseInfo, Error>
+        where R: RestRootEntity
+    {
+        let url = self.entity_url(root)?;
+        let mut resp = self.send(Method::Get, url.clone(), self.headers(), None)?;
+        let mut entities: Vec<R> = self.decode(&mut resp)?;
+        *root = entities.pop().ok_or(Error::NoEntity)?;
+        self.set_api_key(root.get_api_key().map(|s| s.to_string()));
+        *self.inner.root.write().unwrap() = Some((url, reauthenticate::<R>));
+        *self.inner.current_user.write().unwrap() = Some(serde_json::to_value(&*root)?);
+        self.bind(root);
+        if let Some(current_user) = self.current_user()? {
+            self.inner.listeners.emit(Event::Connected(current_user));
+        }
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Return the root entity of the API, connecting with `R::default()` if it is not cached.
+    /// The root entity is shared by all the clones of the session, so it does not have to be
+    /// passed around, but it is only cached as long as it is in use: since the root entity holds
+    /// the session, the session does not keep it alive. Calling `root` with another type
+    /// connects again, and replaces the cached entity.
+    ///
+    /// The session is not locked while connecting, so the listeners of `Event::Connected` may
+    /// call `root` too. Threads calling `root` at the same time may then each connect.
+    pub fn root<R>(&self) -> Result<Arc<R>, Error>
+        where R: RestRootEntity + Default + Send + Sync + 'static
+    {
+        if let Some(root) = self.cached_root::<R>() {
+            return Ok(root);
+        }
+        let mut root = R::default();
+        self.connect(&mut root)?;
+        let root = Arc::new(root);
+        let cached = CachedRoot(Box::new(Arc::downgrade(&root)));
+        *self.inner.root_entity.write().unwrap() = Some(cached);
+        Ok(root)
+    }
+
+    /// Authenticate again with the password and get a new API key, as when a request is
+    /// rejected because the API key expired. This does nothing if the session never connected.
+    pub fn reauthenticate(&self) -> Result<(), Error> {
+        if !self.inner.auth.can_reauthenticate() {
+            return Ok(());
+        }
+        let _reauth = self.inner.reauth.lock().unwrap();
+        let root = self.inner.root.read().unwrap().clone();
+        if let Some((root_url, reauthenticate)) = root {
+            let api_key = reauthenticate(self.clone(), root_url)?;
+            self.set_api_key(api_key);
+        }
+        Ok(())
+    }
+
+    /// Drop the root entity cached by `root`. The next call to `root` connects again.
+    pub fn clear_root(&self) {
+        *self.inner.root_entity.write().unwrap() = None;
+    }
+
+    fn cached_root<R>(&self) -> Option<Arc<R>>
+        where R: RestRootEntity + Send + Sync + 'static
+    {
+        self.inner
+            .root_entity
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|cached| cached.0.downcast_ref::<Weak<R>>())
+            .and_then(|root| root.upgrade())
+    }
+
+    /// Return the differences found so far between the responses and the entities'
+    /// specifications, and clear them.
+    #[cfg(feature = "validation")]
+    pub fn take_schema_mismatches(&self) -> Vec<validation::Mismatch> {
+        ::std::mem::replace(&mut *self.inner.mismatches.lock().unwrap(), Vec::new())
+    }
+
+    /// Fetch an entity and populate its attributes, and set its session.
+    pub fn fetch_entity<E>(&self, entity: &mut E) -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        self.fetch_entity_with(entity, &RequestOptions::default())
+    }
+
+    /// Fetch an entity and populate its attributes, and return the attributes that differ
+    /// between the server and the local copy as it was before, local modifications included.
+    /// In a reconciliation loop, this tells what changed on the server since the last pass.
+    pub fn refresh<E>(&self, entity: &mut E) -> Result<Vec<FieldChange>, Error>
+        where E: RestEntity
+    {
+        let local = serde_json::to_value(&*entity)?;
+        self.fetch_entity(entity)?;
+        Ok(diff::diff_values(&local, &serde_json::to_value(&*entity)?))
+    }
+
+    /// Fetch an entity, with options specific to this request.
+    pub fn fetch_entity_with<E>(&self,
+                                entity: &mut E,
+                                options: &RequestOptions)
+                                -> Result<ResponseInfo, Error>
+        where E: RestEntity
+    {
+        let url = self.entity_url(entity)?;
+        let mut resp = self.send_with(Method::Get, url, self.headers(), None, options)?;
+        let mut entities: Vec<E> = self.decode(&mut resp)?;
+        *entity = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(entity);
+        self.track_etag(entity, &resp);
+        Ok(ResponseInfo::new(&resp))
+    }
+
+    /// Fetch an entity like `fetch_entity`, but return `Ok(None)` instead of an error if the
+    /// entity does not exist on the server. In that case, the entity is left untouched.
+    pub fn try_fetch_entity<E>(&self, entity: &mut E) -> Result<Option<ResponseInfo>, Error>
+        where E: RestEntity
+    {
+        let url = self.entity_url(entity)?;
+        let mut resp = self.send(Method::Get, url, self.headers(), None)?;
+        if resp.status() == StatusCode::NotFound {
+            return Ok(None);
+        }
+        let mut entities: Vec<E> = self.decode(&mut resp)?;
+        *entity = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(entity);
+        self.track_etag(entity, &resp);
+        Ok(Some(ResponseInfo::new(&resp)))
+    }
+
+    /// Fetch the entity with the given ID, and give it a reference to the current session. The
+    /// entity does not need to be built first:
+    ///
+    /// ```rust,ignore
+    /// let enterprise = session.get::<Enterprise>("42")?;
+    /// ```
+    pub fn get<E>(&self, id: &str) -> Result<E, Error>
+        where E: RestEntity
+    {
+        let url = self.id_url::<E>(id)?;
+        let mut resp = self.send(Method::Get, url, self.headers(), None)?;
+        let mut entities: Vec<E> = self.decode(&mut resp)?;
+        let mut entity = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(&mut entity);
+        self.track_etag(&mut entity, &resp);
+        Ok(entity)
+    }
+
+    /// Fetch the entity with the given ID. This is the same as `get`.
+    pub fn fetch_by_id<E>(&self, id: &str) -> Result<E, Error>
+        where E: RestEntity
+    {
+        self.get(id)
+    }
+
+    /// Fetch the entity with the given ID like `get`, but return `Ok(None)` instead of an
+    /// error if it does not exist on the server.
+    pub fn try_fetch_by_id<E>(&self, id: &str) -> Result<Option<E>, Error>
+        where E: RestEntity
+    {
+        let url = self.id_url::<E>(id)?;
+        let mut resp = self.send(Method::Get, url, self.headers(), None)?;
+        if resp.status() == StatusCode::NotFound {
+            return Ok(None);
+        }
+        let mut entities: Vec<E> = self.decode(&mut resp)?;
+        let mut entity = entities.pop().ok_or(Error::NoEntity)?;
+        self.bind(&mut entity);
+        self.track_etag(&mut entity, &resp);
+        Ok(Some(entity))
+    }
+
+    /// Send a request with the default options.
+    fn send(&self,
+            method: Method,
+            url: Url,
+            headers: Headers,
+            body: Option<Vec<u8>>)
+            -> Result<Response, Error> {
+        self.send_with(method, url, headers, body, &RequestOptions::default())
+    }
+
+    /// Send a request. This is the code path shared by all the operations of the session. If the
+    /// request is rejected because the API key expired, the session authenticates again and
+    /// retries it. Idempotent requests are also retried according to the retry policy, if any.
+    fn send_with(&self,
+                 method: Method,
+                 url: Url,
+                 headers: Headers,
+                 body: Option<Vec<u8>>,
+                 options: &RequestOptions)
+                 -> Result<Response, Error> {
+        let idempotent = match method {
+            Method::Get | Method::Put | Method::Delete | Method::Head | Method::Options => true,
+            _ => false,
+        };
+        let mut attempts = 0;
+        let mut reauths = 0;
+        let mut throttled = 0;
+        loop {
+            if let Some(token) = options.get_cancellation() {
+                token.check()?;
+            }
+            // The API key this attempt is authenticated with, to tell whether another thread
+            // authenticated again in the meantime if it is rejected.
+            let api_key = self.inner.auth.api_key();
+            let result = self.send_once(method.clone(),
+                                        url.clone(),
+                                        headers.clone(),
+                                        body.as_ref().map(|b| b.as_slice()),
+                                        options);
+            attempts += 1;
+            if let Some(ref policy) = self.inner.retry_policy {
+                let transient = match result {
+                    Ok(ref resp) => policy.retries_status(resp.status()),
+                    Err(ref e) => policy.retries_error(e),
+                };
+                if idempotent && transient && policy.allows(attempts) {
+                    wait(options, policy.delay(attempts));
+                    continue;
+                }
+            }
+            let resp = result?;
+            // The server did not process a throttled request, so it can always be sent again.
+            if resp.status() == StatusCode::TooManyRequests &&
+               throttled < self.inner.max_throttled_retries {
+                let delay = ratelimit::retry_after(resp.headers())
+                    .unwrap_or_else(|| Duration::from_secs(1));
+                match self.inner.rate_limiter {
+                    Some(ref limiter) => limiter.pause(delay),
+                    None => wait(options, delay),
+                }
+                throttled += 1;
+                continue;
+            }
+            if resp.status() != StatusCode::Unauthorized || reauths >= self.inner.max_reauth ||
+               !self.inner.auth.can_reauthenticate() {
+                return Ok(resp);
+            }
+            {
+                // Only one thread authenticates again: the others wait for it, and then retry
+                // with the new API key.
+                let _reauth = self.inner.reauth.lock().unwrap();
+                if self.inner.auth.api_key() == api_key {
+                    let root = self.inner.root.read().unwrap().clone();
+                    match root {
+                        Some((root_url, reauthenticate)) => {
+                            let api_key = reauthenticate(self.clone(), root_url)?;
+                            self.set_api_key(api_key);
+                        }
+                        // The session never connected, so there is no API key to refresh.
+                        None => return Ok(resp),
+                    }
+                }
+            }
+            reauths += 1;
+        }
+    }
+
+    /// Send a request once. The body is only copied when the request is handed to the transport.
+    fn send_once(&self,
+                 method: Method,
+                 mut url: Url,
+                 mut headers: Headers,
+                 body: Option<&[u8]>,
+                 options: &RequestOptions)
+                 -> Result<Response, Error> {
+        if let Some(ref limiter) = self.inner.rate_limiter {
+            limiter.acquire();
+        }
+        // The authentication headers are set for this attempt only: they are dropped with the
+        // request once it is sent.
+        self.inner.auth.apply(&mut headers);
+        // A request sent with the password rather than an API key logs in, and a one-time
+        // password can only be used once, so a new one is fetched for every attempt.
+        if let Some(ref provider) = self.inner.otp_provider {
+            if self.inner.auth.api_key().is_none() {
+                headers.set(XNuageOtp((provider.0)()));
+            }
+        }
+        headers.extend(options.headers().iter());
+        #[cfg(feature = "gzip")]
+        let body = self.compress(&method, body, &mut headers)?;
+        #[cfg(not(feature = "gzip"))]
+        let body = body.map(|b| b.to_vec());
+        {
+            let query = options.merged_query(&self.inner.query);
+            if !query.is_empty() {
+                let mut pairs = url.query_pairs_mut();
+                for &&(ref name, ref value) in &query {
+                    pairs.append_pair(name, value);
+                }
+            }
+        }
+        {
+            let parts = auth::RequestParts {
+                method: &method,
+                url: &url,
+                body: body.as_ref().map(|b| b.as_slice()).unwrap_or(&[]),
+            };
+            for interceptor in &self.inner.interceptors {
+                interceptor.before(&parts, &mut headers)?;
+            }
+            self.inner.auth.sign(&parts, &mut headers)?;
+        }
+        if self.inner.log_bodies {
+            if let Some(ref body) = body {
+                debug!("{} {} request body: {}", method, url, logging::redacted(body));
+            }
+        }
+        let is_get = method == Method::Get;
+        let start = Instant::now();
+        let request = transport::Request {
+            method: method.clone(),
+            url: url.clone(),
+            headers: headers,
+            body: body,
+            timeout: options.get_timeout(),
+        };
+        let resp = match self.inner.get_queue {
+            Some(ref queue) if is_get && !options.is_unqueued() => queue.execute(request),
+            _ => self.inner.transport.execute(request),
+        };
+        let elapsed = start.elapsed();
+        let millis = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_nanos() / 1_000_000);
+        let resp = match resp {
+            Ok(resp) => {
+                debug!("{} {} -> {} ({} ms)", method, url, resp.status(), millis);
+                resp
+            }
+            Err(e) => {
+                debug!("{} {} failed after {} ms: {}", method, url, millis, e);
+                return Err(e);
+            }
+        };
+        for interceptor in &self.inner.interceptors {
+            interceptor.after(&method, &url, &resp);
+        }
+        Ok(resp)
+    }
+
+    /// Compress a `POST` or `PUT` body if it is larger than the configured threshold. The body
+    /// is compressed before the request is signed, so that the signature covers the bytes that
+    /// are sent.
+    #[cfg(feature = "gzip")]
+    fn compress(&self,
+                method: &Method,
+                body: Option<&[u8]>,
+                headers: &mut Headers)
+                -> Result<Option<Vec<u8>>, Error> {
+        use std::io::Write;
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use hyper::header::{ContentEncoding, Encoding};
+
+        let threshold = match self.inner.compression_threshold {
+            Some(threshold) => threshold,
+            None => return Ok(body.map(|b| b.to_vec())),
+        };
+        let body = match body {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        if body.len() <= threshold || (*method != Method::Post && *method != Method::Put) {
+            return Ok(Some(body.to_vec()));
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body)?;
+        headers.set(ContentEncoding(vec![Encoding::Gzip]));
+        Ok(Some(encoder.finish()?))
+    }
+
+    /// Give the session to an entity that has just been populated from a response.
+    fn bind<E>(&self, entity: &mut E)
+        where E: RestEntity
+    {
+        entity.set_session(self.clone());
+        entity.on_fetched();
+        if E::tracks_changes() {
+            if let Ok(original) = serde_json::to_value(&*entity) {
+                entity.set_original(original);
+            }
+        }
+    }
+
+    /// Give an entity the `ETag` of the response it was populated from.
+    fn track_etag<E>(&self, entity: &mut E, resp: &Response)
+        where E: RestEntity
+    {
+        let etag = resp.headers().get::<ETag>().map(|etag| etag.0.clone());
+        entity.set_etag(etag);
+    }
+
+    /// Return the headers of a request that modifies an entity, with an `If-Match` header if the
+    /// `ETag` of the entity is known.
+    fn conditional_headers<E>(&self, entity: &E) -> Headers
+        where E: RestEntity
+    {
+        let mut headers = self.headers();
+        if let Some(etag) = entity.etag() {
+            headers.set(IfMatch::Items(vec![etag.clone()]));
+        }
+        headers
+    }
+
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+        where T: Serialize
+    {
+        self.inner.codec.encode(&serde_json::to_value(value)?)
+    }
+
+    /// Fail with the error described by the response if its status is not a success.
+    fn check_status(&self, resp: &mut Response) -> Result<(), Error> {
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(self.error_from(resp))
+        }
+    }
+
+    /// Read the whole body of a response, within the maximum response size.
+    fn read_body(&self, resp: &mut Response) -> Result<Vec<u8>, Error> {
+        let body = body::read(resp, self.inner.max_response_size)?;
+        if self.inner.log_bodies {
+            debug!("{} response body: {}", resp.url(), logging::redacted(&body));
+        }
+        Ok(body)
+    }
+
+    /// Return the error matching a non-success response, from the error description in its body.
+    fn error_from(&self, resp: &mut Response) -> Error {
+        match self.read_body(resp) {
+            Ok(body) => Error::from_response_with(resp.status(), &body, self.response_codec(resp)),
+            Err(e) => e,
+        }
+    }
+
+    /// Return the codec to decode the body of a response with. See `codec::for_response`.
+    fn response_codec(&self, resp: &Response) -> &Codec {
+        codec::for_response(&*self.inner.codec, resp.headers())
+    }
+
+    /// Return the body to send for an entity, and set the matching content type.
+    fn entity_body<E>(&self, entity: &E, headers: &mut Headers) -> Result<Vec<u8>, Error>
+        where E: RestEntity
+    {
+        match entity.raw_body() {
+            Some((content_type, body)) => {
+                headers.set(ContentType(content_type));
+                Ok(body)
+            }
+            None => self.encode(entity),
+        }
+    }
+
+    /// Read a response body and decode the entities it contains.
+    fn decode<E>(&self, resp: &mut Response) -> Result<Vec<E>, Error>
+        where E: RestEntity
+    {
+        self.check_status(resp)?;
+        let body = self.read_body(resp)?;
+        self.decode_body(&body, resp.url())
+    }
+
+    fn decode_body<E>(&self, body: &[u8], url: &Url) -> Result<Vec<E>, Error>
+        where E: RestEntity
+    {
+        // The server answers with an empty body when there is no entity to return.
+        if body.is_empty() {
+            return Ok(Vec::new());
+        }
+        let value = self.inner.codec.decode(body)?;
+        #[cfg(feature = "validation")]
+        self.validate::<E>(&value);
+        from_value(value, body, url)
+    }
+
+    /// Check the status of a response and decode its body into a `T`. An empty body is decoded
+    /// as `null`.
+    fn decode_value<T>(&self, resp: &mut Response) -> Result<T, Error>
+        where T: for<'de> serde::Deserialize<'de>
+    {
+        self.check_status(resp)?;
+        let body = self.read_body(resp)?;
+        let value = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            self.inner.codec.decode(&body)?
+        };
+        from_value(value, &body, resp.url())
+    }
+
+    /// Read a response body and decode the list of values it contains. Unlike `decode`, this
+    /// works for any type, not just entities.
+    fn decode_list<T>(&self, resp: &mut Response) -> Result<Vec<T>, Error>
+        where T: for<'de> serde::Deserialize<'de>
+    {
+        self.check_status(resp)?;
+        let body = self.read_body(resp)?;
+        if body.is_empty() {
+            return Ok(Vec::new());
+        }
+        from_value(self.inner.codec.decode(&body)?, &body, resp.url())
+    }
+
+    #[cfg(feature = "validation")]
+    fn validate<E>(&self, value: &serde_json::Value)
+        where E: RestEntity
+    {
+        let mismatches = validation::validate(E::path(), E::attributes(), value);
+        if !mismatches.is_empty() {
+            self.inner.mismatches.lock().unwrap().extend(mismatches);
+        }
+    }
+
+    /// Return the headers common to all the requests of the session. The authentication headers
+    /// are not part of them: they are only set by `send_once`, right before a request is sent, so
+    /// that the credentials do not outlive the request.
+    fn headers(&self) -> Headers {
+        let mut headers = self.inner.headers.clone();
+        if let Some(ref user) = *self.inner.proxy_user.read().unwrap() {
+            headers.set(XNuageProxyUser(user.clone()));
+        }
+        headers
+    }
+
+    /// Return the headers of a request whose response body is parsed as JSON rather than with
+    /// the codec of the session.
+    fn json_headers(&self) -> Headers {
+        let mut headers = self.headers();
+        headers.set(Accept(vec![qitem(codec::Json.content_type())]));
+        headers
+    }
+
+    fn children_url<P, C>(&self, parent: &P) -> Result<Url, Error>
+        where P: RestEntity,
+              C: RestEntity
+    {
+        self.group_url(parent, C::group_path())
+    }
+
+    fn group_url<P>(&self, parent: &P, group_path: &str) -> Result<Url, Error>
+        where P: RestEntity
+    {
+        if parent.is_root() {
+            path::group(&self.inner.url, None, group_path)
+        } else {
+            let id = parent.id().ok_or(Error::MissingId)?;
+            path::group(&self.inner.url, Some((P::path(), id)), group_path)
+        }
+    }
+
+    /// Return the URL of an entity. Root entities have no ID: their URL is their path alone.
+    fn entity_url<E>(&self, entity: &E) -> Result<Url, Error>
+        where E: RestEntity
+    {
+        if entity.is_root() {
+            return path::root(&self.inner.url, E::path());
+        }
+        self.id_url::<E>(entity.id().ok_or(Error::MissingId)?)
+    }
+
+    fn id_url<E>(&self, id: &str) -> Result<Url, Error>
+        where E: RestEntity
+    {
+        path::entity(&self.inner.url, E::path(), id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::{Authorization, Basic, ContentType, Headers};
+    use serde_json;
+
+    use transport::{Request, Response, Transport};
+    use {CurrentUser, Error, Event, Form, IdEntry, Metadata, Method, RequestOptions, RestEntity,
+         RestRootEntity, Session, SessionBuilder, SessionState, Upload, XNuageAttributes,
+         XNuageOtp};
+    use super::pem_certificates;
+
+    const URL: &'static str = "https://vsd:8443/nuage/api/v5_0/";
+
+    /// A transport answering every request with the same body, and keeping the headers of the
+    /// requests.
+    #[derive(Debug)]
+    struct Canned {
+        body: &'static str,
+        headers: Arc<Mutex<Vec<Headers>>>,
+    }
+
+    impl Transport for Canned {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            self.headers.lock().unwrap().push(request.headers);
+            Ok(Response::new(StatusCode::Ok,
+                             Headers::new(),
+                             request.url,
+                             Box::new(Cursor::new(self.body.as_bytes().to_vec()))))
+        }
+    }
+
+    #[test]
+    fn children_ids() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let transport = Canned {
+            body: r#"[{"ID": "1", "name": "a"}, {"ID": "2"}]"#,
+            headers: headers.clone(),
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .api_key("key")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let mut parent = Metadata::default();
+        parent.id = Some("42".to_string());
+
+        let ids = session.fetch_children_ids::<_, Metadata>(&parent).unwrap();
+        assert_eq!(ids,
+                   vec![IdEntry {
+                            id: "1".to_string(),
+                            name: Some("a".to_string()),
+                        },
+                        IdEntry {
+                            id: "2".to_string(),
+                            name: None,
+                        }]);
+        let headers = headers.lock().unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].get::<XNuageAttributes>(),
+                   Some(&XNuageAttributes(vec!["ID".to_string(), "name".to_string()])));
+    }
+
+    #[test]
+    fn unqueued_requests() {
+        let transport = Canned {
+            body: "[]",
+            headers: Arc::new(Mutex::new(Vec::new())),
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .max_concurrent_gets(1)
+            .transport(transport)
+            .build()
+            .unwrap();
+        let url = Url::parse(URL).unwrap();
+        let options = RequestOptions::new().unqueued();
+        // Neither response is read, so a queued request would wait for a slot forever.
+        let _first = session
+            .send_with(Method::Get, url.clone(), session.headers(), None, &options)
+            .unwrap();
+        let _second = session
+            .send_with(Method::Get, url, session.headers(), None, &options)
+            .unwrap();
+    }
+
+    #[test]
+    fn multipart_upload() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let transport = Canned {
+            body: "",
+            headers: headers.clone(),
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .transport(transport)
+            .build()
+            .unwrap();
+        session
+            .upload("certificates", Upload::multipart(Form::new().text("name", "vsd")))
+            .unwrap();
+        let headers = headers.lock().unwrap();
+        assert_eq!(headers[0].get::<ContentType>().unwrap().to_string(),
+                   "multipart/form-data; boundary=bambou-boundary-0");
+        assert!(headers[0].has::<Authorization<Basic>>());
+    }
+
+    #[test]
+    fn warm_up() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let transport = Canned {
+            body: "",
+            headers: headers.clone(),
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .transport(transport)
+            .build()
+            .unwrap();
+        session.warm_up(3).unwrap();
+        assert_eq!(headers.lock().unwrap().len(), 3);
+    }
+
+    #[derive(Debug)]
+    struct Panicking;
+
+    impl Transport for Panicking {
+        fn execute(&self, _request: Request) -> Result<Response, Error> {
+            panic!("transport failed")
+        }
+    }
+
+    #[test]
+    fn warm_up_panic() {
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .transport(Panicking)
+            .build()
+            .unwrap();
+        match session.warm_up(1) {
+            Err(Error::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::Other),
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+    }
+
+    /// An entity that tracks its changes.
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Tracked {
+        #[serde(rename = "ID")]
+        id: String,
+        name: String,
+        #[serde(skip)]
+        original: Option<serde_json::Value>,
+    }
+
+    impl RestEntity for Tracked {
+        fn set_session(&mut self, _session: Session) {}
+
+        fn get_session(&self) -> Option<&Session> {
+            None
+        }
+
+        fn path() -> &'static str {
+            "tracked"
+        }
+
+        fn group_path() -> &'static str {
+            "tracked"
+        }
+
+        fn id(&self) -> Option<&str> {
+            Some(&self.id)
+        }
+
+        fn is_root(&self) -> bool {
+            false
+        }
+
+        fn tracks_changes() -> bool {
+            true
+        }
+
+        fn original(&self) -> Option<&serde_json::Value> {
+            self.original.as_ref()
+        }
+
+        fn set_original(&mut self, original: serde_json::Value) {
+            self.original = Some(original);
+        }
+    }
+
+    fn tracked_session(headers: Arc<Mutex<Vec<Headers>>>) -> (Session, Tracked) {
+        let transport = Canned {
+            body: r#"[{"ID": "42", "name": "a"}]"#,
+            headers: headers,
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .transport(transport)
+            .build()
+            .unwrap();
+        let entity = session.get::<Tracked>("42").unwrap();
+        (session, entity)
+    }
+
+    #[test]
+    fn save_unchanged() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let (session, mut entity) = tracked_session(headers.clone());
+        let info = session.save(&mut entity).unwrap();
+        assert_eq!(info.status, StatusCode::NotModified);
+        assert_eq!(info.url, Url::parse(URL).unwrap().join("tracked/42").unwrap());
+        assert_eq!(headers.lock().unwrap().len(), 1);
+
+        entity.name = "b".to_string();
+        session.save(&mut entity).unwrap();
+        assert_eq!(headers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn patch_unchanged() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let (session, mut entity) = tracked_session(headers.clone());
+        let info = session.patch_changes(&mut entity).unwrap();
+        assert_eq!(info.status, StatusCode::NotModified);
+        assert_eq!(headers.lock().unwrap().len(), 1);
+
+        entity.name = "b".to_string();
+        session.patch_changes(&mut entity).unwrap();
+        assert_eq!(headers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn refresh() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let (session, mut entity) = tracked_session(headers.clone());
+        assert!(session.refresh(&mut entity).unwrap().is_empty());
+
+        entity.name = "b".to_string();
+        let changes = session.refresh(&mut entity).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "name");
+        assert_eq!(changes[0].old, serde_json::Value::String("b".to_string()));
+        assert_eq!(changes[0].new, serde_json::Value::String("a".to_string()));
+        assert_eq!(entity.name, "a");
+        assert_eq!(headers.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn save_full_unchanged() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let (session, mut entity) = tracked_session(headers.clone());
+        session.save_full(&mut entity).unwrap();
+        assert_eq!(headers.lock().unwrap().len(), 2);
+    }
+
+    /// A root entity, whose user name may be of any type.
+    #[derive(Default, Serialize, Deserialize)]
+    struct Root {
+        #[serde(skip)]
+        session: Option<Session>,
+        #[serde(rename = "ID")]
+        id: Option<String>,
+        #[serde(rename = "APIKey")]
+        api_key: Option<String>,
+        #[serde(rename = "userName")]
+        user_name: serde_json::Value,
+    }
+
+    impl RestEntity for Root {
+        fn set_session(&mut self, session: Session) {
+            self.session = Some(session);
+        }
+
+        fn get_session(&self) -> Option<&Session> {
+            self.session.as_ref()
+        }
+
+        fn path() -> &'static str {
+            "me"
+        }
+
+        fn group_path() -> &'static str {
+            "me"
+        }
+
+        fn id(&self) -> Option<&str> {
+            self.id.as_ref().map(|id| id.as_str())
+        }
+
+        fn is_root(&self) -> bool {
+            true
+        }
+    }
+
+    impl RestRootEntity for Root {
+        fn get_api_key(&self) -> Option<&str> {
+            self.api_key.as_ref().map(|key| key.as_str())
+        }
+    }
+
+    fn connect(body: &'static str) -> (Result<Root, Error>, Vec<CurrentUser>) {
+        let transport = Canned {
+            body: body,
+            headers: Arc::new(Mutex::new(Vec::new())),
+        };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .transport(transport)
+            .build()
+            .unwrap();
+        let users = Arc::new(Mutex::new(Vec::new()));
+        let connected = users.clone();
+        session.on_event(move |event| if let Event::Connected(ref user) = *event {
+                             connected.lock().unwrap().push(user.clone());
+                         });
+        let mut root = Root::default();
+        let result = session.connect(&mut root).map(|_| root);
+        let users = users.lock().unwrap().clone();
+        (result, users)
+    }
+
+    #[test]
+    fn connected() {
+        let (root, users) = connect(r#"[{"ID": "1", "APIKey": "key", "userName": "csproot"}]"#);
+        assert!(root.unwrap().get_session().is_some());
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].user_name, Some("csproot".to_string()));
+    }
+
+    #[test]
+    fn connected_user_not_decoded() {
+        let (root, users) = connect(r#"[{"ID": "1", "APIKey": "key", "userName": 42}]"#);
+        match root {
+            Err(Error::Json(_)) => {}
+            Err(e) => panic!("expected a decoding error, got {:?}", e),
+            Ok(_) => panic!("expected a decoding error"),
+        }
+        assert!(users.is_empty());
+    }
+
+    /// A transport rejecting the requests authenticated with the API key `"expired"`, and
+    /// keeping the password or API key of every request.
+    #[derive(Debug)]
+    struct Expiring {
+        passwords: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Transport for Expiring {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            let password = request
+                .headers
+                .get::<Authorization<Basic>>()
+                .and_then(|auth| auth.password.clone())
+                .unwrap_or_default();
+            self.passwords.lock().unwrap().push(password.clone());
+            let (status, body) = if request.url.path().ends_with("/me") {
+                (StatusCode::Ok, r#"[{"ID": "1", "APIKey": "key", "userName": "csproot"}]"#)
+            } else if password == "expired" {
+                (StatusCode::Unauthorized, "")
+            } else {
+                (StatusCode::Ok, "[]")
+            };
+            Ok(Response::new(status,
+                             Headers::new(),
+                             request.url,
+                             Box::new(Cursor::new(body.as_bytes().to_vec()))))
+        }
+    }
+
+    fn expired_session(max_reauth: u32) -> (Session, Arc<Mutex<Vec<String>>>) {
+        let passwords = Arc::new(Mutex::new(Vec::new()));
+        let transport = Expiring { passwords: passwords.clone() };
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .max_reauth(max_reauth)
+            .transport(transport)
+            .build()
+            .unwrap();
+        session.connect(&mut Root::default()).unwrap();
+        session.set_api_key(Some("expired".to_string()));
+        passwords.lock().unwrap().clear();
+        (session, passwords)
+    }
+
+    #[test]
+    fn reauthenticate() {
+        let (session, passwords) = expired_session(1);
+        let url = Url::parse(URL).unwrap().join("enterprises").unwrap();
+        let resp = session
+            .send(Method::Get, url, session.headers(), None)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::Ok);
+        assert_eq!(*passwords.lock().unwrap(), vec!["expired", "csproot", "key"]);
+    }
+
+    #[test]
+    fn no_reauthentication() {
+        let (session, passwords) = expired_session(0);
+        let url = Url::parse(URL).unwrap().join("enterprises").unwrap();
+        let resp = session
+            .send(Method::Get, url, session.headers(), None)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::Unauthorized);
+        assert_eq!(*passwords.lock().unwrap(), vec!["expired"]);
+    }
+
+    #[test]
+    fn one_time_password() {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let transport = Canned {
+            body: r#"[{"ID": "1", "APIKey": "key", "userName": "csproot"}]"#,
+            headers: headers.clone(),
+        };
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .otp_provider(move || {
+                              let mut calls = counter.lock().unwrap();
+                              *calls += 1;
+                              format!("00000{}", calls)
+                          })
+            .transport(transport)
+            .build()
+            .unwrap();
+        session.connect(&mut Root::default()).unwrap();
+        let url = Url::parse(URL).unwrap().join("enterprises").unwrap();
+        session
+            .send(Method::Get, url, session.headers(), None)
+            .unwrap();
+
+        let headers = headers.lock().unwrap();
+        assert_eq!(headers[0].get::<XNuageOtp>(),
+                   Some(&XNuageOtp("000001".to_string())));
+        // The second request is authenticated with the API key.
+        assert_eq!(headers[1].get::<XNuageOtp>(), None);
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn unsupported_async_options() {
+        let builder = || SessionBuilder::plain(URL, "csproot", "csproot").unwrap();
+        assert_eq!(builder().unsupported_async_option(), None);
+        assert_eq!(builder().max_reauth(0).unsupported_async_option(),
+                   Some("max_reauth"));
+        assert_eq!(builder().max_throttled_retries(5).unsupported_async_option(),
+                   Some("max_throttled_retries"));
+    }
+
+    fn state(current_user: Option<CurrentUser>) -> SessionState {
+        SessionState {
+            url: URL.to_string(),
+            username: "csproot".to_string(),
+            organization: Some("csp".to_string()),
+            api_key: None,
+            current_user: current_user,
+            proxy_user: None,
+        }
+    }
+
+    #[test]
+    fn no_current_user() {
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(session.current_user().unwrap(), None);
+        assert_eq!(session.serialize_state().unwrap().current_user, None);
+    }
+
+    #[test]
+    fn current_user_from_state() {
+        let user = CurrentUser {
+            id: Some("1".to_string()),
+            user_name: Some("csproot".to_string()),
+            role: Some("CSPROOT".to_string()),
+            enterprise_id: Some("2".to_string()),
+            enterprise_name: Some("CSP".to_string()),
+            api_key_expiry: Some(1000),
+        };
+        let session = SessionBuilder::from_state(state(Some(user.clone())))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(session.current_user().unwrap(), Some(user.clone()));
+        assert_eq!(session.serialize_state().unwrap(), state(Some(user)));
+    }
+
+    #[test]
+    fn current_user_not_decoded() {
+        let session = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap();
+        let root = serde_json::from_str(r#"{"ID": "1", "userName": 42}"#).unwrap();
+        *session.inner.current_user.write().unwrap() = Some(root);
+        match session.current_user() {
+            Err(Error::Json(_)) => {}
+            other => panic!("expected a decoding error, got {:?}", other),
+        }
+        assert!(session.serialize_state().is_err());
+    }
+
+    fn bundle(name: &str) -> String {
+        format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn is_invalid_data(result: Result<SessionBuilder, Error>) -> bool {
+        match result {
+            Err(Error::Io(ref e)) => e.kind() == io::ErrorKind::InvalidData,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn pem_blocks() {
+        let bundle = include_str!("../tests/data/ca_bundle.pem");
+        let certificates = pem_certificates(bundle).unwrap();
+        assert_eq!(certificates.len(), 2);
+        for certificate in &certificates {
+            assert!(certificate.starts_with("-----BEGIN CERTIFICATE-----\n"));
+            assert!(certificate.ends_with("\n-----END CERTIFICATE-----"));
+        }
+        assert!(certificates[0] != certificates[1]);
+        // The comments around the certificates are ignored.
+        assert!(!certificates.iter().any(|certificate| certificate.contains('#')));
+    }
+
+    #[test]
+    fn no_pem_block() {
+        assert!(pem_certificates("").unwrap().is_empty());
+        assert!(pem_certificates("# No certificate here.\n").unwrap().is_empty());
+        assert!(pem_certificates("-----END CERTIFICATE-----").unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_pem_block() {
+        let bundle = include_str!("../tests/data/truncated.pem");
+        assert!(pem_certificates(bundle).is_err());
+        // A truncated certificate after a valid one is an error too.
+        let bundle = format!("{}{}", include_str!("../tests/data/ca_bundle.pem"), bundle);
+        assert!(pem_certificates(&bundle).is_err());
+    }
+
+    #[test]
+    fn ca_bundle() {
+        let builder = SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .ca_bundle_path(bundle("ca_bundle.pem"))
+            .unwrap();
+        assert_eq!(builder.root_certificates.len(), 2);
+    }
+
+    #[test]
+    fn empty_ca_bundle() {
+        let builder = SessionBuilder::plain(URL, "csproot", "csproot").unwrap();
+        assert!(is_invalid_data(builder.ca_bundle_path(bundle("empty.pem"))));
+    }
+
+    #[test]
+    fn truncated_ca_bundle() {
+        let builder = SessionBuilder::plain(URL, "csproot", "csproot").unwrap();
+        assert!(is_invalid_data(builder.ca_bundle_path(bundle("truncated.pem"))));
+    }
+
+    #[test]
+    fn invalid_ca_bundle() {
+        let builder = SessionBuilder::plain(URL, "csproot", "csproot").unwrap();
+        assert!(builder.ca_bundle_path(bundle("invalid.pem")).is_err());
+    }
+
+    #[test]
+    fn missing_ca_bundle() {
+        let builder = SessionBuilder::plain(URL, "csproot", "csproot").unwrap();
+        match builder.ca_bundle_path(bundle("missing.pem")) {
+            Err(Error::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            _ => panic!("expected a NotFound error"),
+        }
     }
 }
@@ -0,0 +1,47 @@
+use serde_json::{self, Value};
+
+/// The replacement of the values of sensitive attributes in logged bodies.
+const REDACTED: &str = "<redacted>";
+
+/// Return a body as it should be logged: JSON bodies are logged with the values of the
+/// attributes that look like credentials (passwords, API keys, secrets and tokens) redacted, and
+/// other bodies are only described by their size, since they cannot be redacted.
+pub fn redacted(body: &[u8]) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes>", body.len()),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match *value {
+        Value::Object(ref mut map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(ref mut values) => {
+            for value in values {
+                redact(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["password", "apikey", "secret", "token"]
+        .iter()
+        .any(|word| key.contains(word))
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use {Error, Session};
+
+/// What identifies a session of a `SessionManager`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub url: String,
+    pub organization: String,
+    pub username: String,
+}
+
+impl SessionKey {
+    pub fn new(url: &str, organization: &str, username: &str) -> Self {
+        SessionKey {
+            url: url.to_owned(),
+            organization: organization.to_owned(),
+            username: username.to_owned(),
+        }
+    }
+}
+
+type Factory = Box<Fn(&SessionKey) -> Result<Session, Error> + Send + Sync>;
+
+/// Holds the sessions of a tool talking to many enterprises, or many servers, at once. The
+/// sessions are created on first use by a factory, which is given the key of the session and
+/// must return it connected, and are then shared: `get` returns clones of the same session,
+/// which share their connection pool and API key.
+///
+/// Before returning a session whose API key is about to expire, the manager authenticates again
+/// with `Session::reauthenticate`, so that long-running controllers do not hit expired keys.
+///
+/// ```rust,ignore
+/// let manager = SessionManager::new(|key| {
+///     let password = vault.password(&key.organization, &key.username)?;
+///     let session = SessionBuilder::new(&key.url, &key.username, &password, &key.organization)?
+///         .build()?;
+///     session.connect(&mut Me::default())?;
+///     Ok(session)
+/// });
+/// let session = manager.get(&SessionKey::new("https://vsd:8443", "acme", "admin"))?;
+/// ```
+pub struct SessionManager {
+    factory: Factory,
+    // Each session has its own lock, so that creating or refreshing a session does not block
+    // the users of the other sessions.
+    sessions: Mutex<HashMap<SessionKey, Arc<Mutex<Option<Session>>>>>,
+    refresh_margin: Duration,
+}
+
+impl SessionManager {
+    pub fn new<F>(factory: F) -> Self
+        where F: Fn(&SessionKey) -> Result<Session, Error> + Send + Sync + 'static
+    {
+        SessionManager {
+            factory: Box::new(factory),
+            sessions: Mutex::new(HashMap::new()),
+            refresh_margin: Duration::from_secs(60),
+        }
+    }
+
+    /// Set how long before the expiry of its API key a session is authenticated again. It
+    /// defaults to one minute.
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Return the session for `key`, creating it if there is none yet, and authenticating it
+    /// again if its API key is about to expire. If the factory fails, the error is returned and
+    /// the next call tries again.
+    pub fn get(&self, key: &SessionKey) -> Result<Session, Error> {
+        let slot = self.sessions
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone();
+        let mut slot = slot.lock().unwrap();
+        if let Some(ref session) = *slot {
+            if expires_within(session, self.refresh_margin)? {
+                session.reauthenticate()?;
+            }
+            return Ok(session.clone());
+        }
+        let session = (self.factory)(key)?;
+        *slot = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Forget the session for `key`, for instance after its user was deleted. The next call to
+    /// `get` creates a new session.
+    pub fn remove(&self, key: &SessionKey) {
+        self.sessions.lock().unwrap().remove(key);
+    }
+
+    /// Forget all the sessions.
+    pub fn clear(&self) {
+        self.sessions.lock().unwrap().clear();
+    }
+
+    /// Return the keys of the sessions held by the manager.
+    pub fn keys(&self) -> Vec<SessionKey> {
+        self.sessions.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("sessions", &self.keys())
+            .field("refresh_margin", &self.refresh_margin)
+            .finish()
+    }
+}
+
+/// Return true if the API key of `session` expires within `margin`.
+fn expires_within(session: &Session, margin: Duration) -> Result<bool, Error> {
+    let expiry = match session.current_user()?.and_then(|user| user.api_key_expiry) {
+        Some(expiry) => expiry,
+        None => return Ok(false),
+    };
+    let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(now) => now,
+        Err(_) => return Ok(false),
+    };
+    let now_ms = now.as_secs() as i64 * 1000 + i64::from(now.subsec_nanos() / 1_000_000);
+    let margin_ms = margin.as_secs() as i64 * 1000 + i64::from(margin.subsec_nanos() / 1_000_000);
+    Ok(expiry - margin_ms <= now_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{Map, Value};
+
+    use SessionBuilder;
+    use super::*;
+
+    fn session() -> Session {
+        SessionBuilder::plain("https://vsd:8443/nuage/api/v5_0/", "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    /// Return a connected session, whose API key expires `offset` seconds from now, if any.
+    fn connected_session(offset: Option<i64>) -> Session {
+        let mut user = Map::new();
+        user.insert("ID".to_string(), Value::String("1".to_string()));
+        if let Some(offset) = offset {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            let expiry = (now.as_secs() as i64 + offset) * 1000;
+            user.insert("APIKeyExpiry".to_string(), Value::from(expiry));
+        }
+        let session = session();
+        *session.inner.current_user.write().unwrap() = Some(Value::Object(user));
+        session
+    }
+
+    #[test]
+    fn expiry() {
+        let margin = Duration::from_secs(60);
+        assert!(!expires_within(&connected_session(Some(3600)), margin).unwrap());
+        assert!(expires_within(&connected_session(Some(30)), margin).unwrap());
+        assert!(expires_within(&connected_session(Some(-30)), margin).unwrap());
+        assert!(!expires_within(&connected_session(Some(30)), Duration::from_secs(0)).unwrap());
+    }
+
+    #[test]
+    fn no_expiry() {
+        let margin = Duration::from_secs(60);
+        assert!(!expires_within(&session(), margin).unwrap());
+        assert!(!expires_within(&connected_session(None), margin).unwrap());
+    }
+
+    #[test]
+    fn sessions() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let counter = created.clone();
+        let manager = SessionManager::new(move |key| if key.organization == "broken" {
+                                              Err(Error::NoSession)
+                                          } else {
+                                              counter.fetch_add(1, Ordering::SeqCst);
+                                              Ok(session())
+                                          });
+        let acme = SessionKey::new("https://vsd:8443", "acme", "admin");
+        manager.get(&acme).unwrap();
+        manager.get(&acme).unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert_eq!(manager.keys(), vec![acme.clone()]);
+
+        let broken = SessionKey::new("https://vsd:8443", "broken", "admin");
+        assert!(manager.get(&broken).is_err());
+
+        manager.remove(&acme);
+        manager.get(&acme).unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        manager.clear();
+        assert!(manager.keys().is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Metadata attached to entities.
+//!
+//! Any entity of the VSD can hold metadata: named blobs of arbitrary data, that orchestrators
+//! use to keep their own state next to the entities they manage. Metadata are either local to
+//! the entity (`Metadata`), or global (`GlobalMetadata`) and then also visible from the other
+//! entities of the same kind. They can be grouped with tags (`MetadataTag`).
+//!
+//! ```rust,ignore
+//! let mut metadata = Metadata::new("owner", "team-a");
+//! metadata = domain.attach_metadata(metadata)?;
+//! for metadata in domain.metadata()? {
+//!     println!("{:?}: {:?}", metadata.name, metadata.blob);
+//! }
+//! metadata.delete()?;
+//! ```
+
+use {RestEntity, Session};
+
+/// Implement `RestEntity` for a metadata type, which keeps its session in a `session` field and
+/// its ID in an `id` field.
+macro_rules! rest_entity {
+    ($entity:ident, $path:expr, $group_path:expr) => {
+        impl RestEntity for $entity {
+            fn set_session(&mut self, session: Session) {
+                self.session = Some(session);
+            }
+
+            fn get_session(&self) -> Option<&Session> {
+                self.session.as_ref()
+            }
+
+            fn path() -> &'static str {
+                $path
+            }
+
+            fn group_path() -> &'static str {
+                $group_path
+            }
+
+            fn id(&self) -> Option<&str> {
+                self.id.as_ref().map(|id| id.as_str())
+            }
+
+            fn is_root(&self) -> bool {
+                false
+            }
+        }
+    }
+}
+
+/// A blob of data attached to an entity.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The data, often JSON serialized to a string.
+    #[serde(default)]
+    pub blob: Option<String>,
+    #[serde(default)]
+    pub global: Option<bool>,
+    /// The IDs of the tags of the metadata.
+    #[serde(rename = "metadataTagIDs", default)]
+    pub metadata_tag_ids: Vec<String>,
+    /// Do not notify the changes of the metadata on the event channel.
+    #[serde(rename = "networkNotificationDisabled", default)]
+    pub network_notification_disabled: Option<bool>,
+    #[serde(rename = "externalID", default)]
+    pub external_id: Option<String>,
+}
+
+impl Metadata {
+    pub fn new(name: &str, blob: &str) -> Self {
+        Metadata {
+            name: Some(name.to_owned()),
+            blob: Some(blob.to_owned()),
+            ..Metadata::default()
+        }
+    }
+}
+
+rest_entity!(Metadata, "metadata", "metadatas");
+
+/// A blob of data attached to an entity, and shared with the other entities of the same kind.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GlobalMetadata {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub blob: Option<String>,
+    #[serde(rename = "metadataTagIDs", default)]
+    pub metadata_tag_ids: Vec<String>,
+    #[serde(rename = "networkNotificationDisabled", default)]
+    pub network_notification_disabled: Option<bool>,
+    #[serde(rename = "externalID", default)]
+    pub external_id: Option<String>,
+}
+
+impl GlobalMetadata {
+    pub fn new(name: &str, blob: &str) -> Self {
+        GlobalMetadata {
+            name: Some(name.to_owned()),
+            blob: Some(blob.to_owned()),
+            ..GlobalMetadata::default()
+        }
+    }
+}
+
+rest_entity!(GlobalMetadata, "globalmetadata", "globalmetadatas");
+
+/// A tag grouping metadata. Tags are created under an enterprise, or under the root entity to
+/// be shared by all the enterprises.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MetadataTag {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether the tag was created by the server.
+    #[serde(rename = "autoCreated", default)]
+    pub auto_created: Option<bool>,
+    #[serde(rename = "externalID", default)]
+    pub external_id: Option<String>,
+}
+
+impl MetadataTag {
+    pub fn new(name: &str) -> Self {
+        MetadataTag {
+            name: Some(name.to_owned()),
+            ..MetadataTag::default()
+        }
+    }
+}
+
+rest_entity!(MetadataTag, "metadatatag", "metadatatags");
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use serde_json::{self, Value};
+
+    use SessionBuilder;
+    use super::*;
+
+    const URL: &'static str = "https://vsd:8443/nuage/api/v5_0/";
+
+    fn session() -> Session {
+        SessionBuilder::plain(URL, "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse(URL).unwrap().join(path).unwrap()
+    }
+
+    #[test]
+    fn urls() {
+        let session = session();
+        let mut metadata = Metadata::new("owner", "team-a");
+        metadata.id = Some("1".to_string());
+        let mut global = GlobalMetadata::new("owner", "team-a");
+        global.id = Some("2".to_string());
+        let mut tag = MetadataTag::new("team");
+        tag.id = Some("3".to_string());
+
+        assert_eq!(session.entity_url(&metadata).unwrap(), url("metadata/1"));
+        assert_eq!(session.entity_url(&global).unwrap(), url("globalmetadata/2"));
+        assert_eq!(session.entity_url(&tag).unwrap(), url("metadatatag/3"));
+        assert_eq!(session.children_url::<_, Metadata>(&tag).unwrap(),
+                   url("metadatatag/3/metadatas"));
+        assert_eq!(session.children_url::<_, GlobalMetadata>(&tag).unwrap(),
+                   url("metadatatag/3/globalmetadatas"));
+        assert_eq!(session.children_url::<_, MetadataTag>(&metadata).unwrap(),
+                   url("metadata/1/metadatatags"));
+    }
+
+    #[test]
+    fn session_and_id() {
+        let mut metadata = Metadata::default();
+        assert!(metadata.get_session().is_none());
+        assert_eq!(RestEntity::id(&metadata), None);
+        assert!(!metadata.is_root());
+        metadata.set_session(session());
+        metadata.id = Some("1".to_string());
+        assert!(metadata.get_session().is_some());
+        assert_eq!(RestEntity::id(&metadata), Some("1"));
+    }
+
+    #[test]
+    fn serialize() {
+        let value = serde_json::to_value(&Metadata::new("owner", "team-a")).unwrap();
+        let object = value.as_object().unwrap();
+        assert!(!object.contains_key("ID"));
+        assert_eq!(object["name"], Value::String("owner".to_string()));
+        assert_eq!(object["blob"], Value::String("team-a".to_string()));
+        assert_eq!(object["metadataTagIDs"], Value::Array(vec![]));
+
+        let value = serde_json::to_value(&MetadataTag::new("team")).unwrap();
+        assert_eq!(value.as_object().unwrap()["name"], Value::String("team".to_string()));
+    }
+
+    #[test]
+    fn deserialize() {
+        let json = r#"{"ID": "2", "name": "owner", "metadataTagIDs": ["3"],
+                       "networkNotificationDisabled": true, "externalID": "ext"}"#;
+        let metadata: GlobalMetadata = serde_json::from_str(json).unwrap();
+        assert_eq!(metadata.id, Some("2".to_string()));
+        assert_eq!(metadata.name, Some("owner".to_string()));
+        assert_eq!(metadata.blob, None);
+        assert_eq!(metadata.metadata_tag_ids, vec!["3".to_string()]);
+        assert_eq!(metadata.network_notification_disabled, Some(true));
+        assert_eq!(metadata.external_id, Some("ext".to_string()));
+
+        let tag: MetadataTag = serde_json::from_str(r#"{"ID": "3", "autoCreated": true}"#).unwrap();
+        assert_eq!(tag.id, Some("3".to_string()));
+        assert_eq!(tag.auto_created, Some(true));
+    }
+}
@@ -0,0 +1,82 @@
+//! SPNEGO (Kerberos) authentication, for deployments where the API, or the proxy in front of
+//! it, mandates single sign-on instead of passwords.
+//!
+//! The Kerberos credentials of the current user are used (for instance, obtained with `kinit`).
+//! Until the server hands out an API key, each request carries a fresh `Negotiate` token for the
+//! `HTTP@<host>` service. Afterwards, the API key is used like with `auth::BasicAuth`.
+
+use std::sync::RwLock;
+
+use base64;
+use libgssapi::context::{ClientCtx, CtxFlags};
+use libgssapi::name::Name;
+use libgssapi::oid::{GSS_MECH_SPNEGO, GSS_NT_HOSTBASED_SERVICE};
+use reqwest::header::{Headers, Authorization, Basic};
+
+use auth::{AuthProvider, RequestParts};
+use Error;
+use Secret;
+
+/// An authentication provider using SPNEGO, and then the API key obtained when connecting.
+#[derive(Debug)]
+pub struct NegotiateAuth {
+    username: String,
+    api_key: RwLock<Option<Secret>>,
+}
+
+impl NegotiateAuth {
+    /// Create a provider for `username`, which must be the user the Kerberos credentials belong
+    /// to.
+    pub fn new(username: &str) -> Self {
+        NegotiateAuth {
+            username: username.to_owned(),
+            api_key: RwLock::new(None),
+        }
+    }
+
+    fn token(&self, host: &str) -> Result<Vec<u8>, Error> {
+        let service = format!("HTTP@{}", host);
+        let name = Name::new(service.as_bytes(), Some(&GSS_NT_HOSTBASED_SERVICE))
+            .map_err(|e| Error::Negotiate(e.to_string()))?;
+        let mut context =
+            ClientCtx::new(None, name, CtxFlags::GSS_C_MUTUAL_FLAG, Some(&GSS_MECH_SPNEGO));
+        let token = context
+            .step(None, None)
+            .map_err(|e| Error::Negotiate(e.to_string()))?
+            .ok_or_else(|| Error::Negotiate("no initial token".to_string()))?;
+        Ok(token.to_vec())
+    }
+}
+
+impl AuthProvider for NegotiateAuth {
+    fn apply(&self, headers: &mut Headers) {
+        if let Some(ref api_key) = *self.api_key.read().unwrap() {
+            headers.set(Authorization(Basic {
+                                          username: self.username.clone(),
+                                          password: Some(api_key.expose().to_owned()),
+                                      }));
+        }
+    }
+
+    fn sign(&self, request: &RequestParts, headers: &mut Headers) -> Result<(), Error> {
+        if self.api_key.read().unwrap().is_some() {
+            return Ok(());
+        }
+        let host = request
+            .url
+            .host_str()
+            .ok_or_else(|| Error::Negotiate("the URL has no host".to_string()))?;
+        let token = self.token(host)?;
+        headers.set_raw("Authorization",
+                        vec![format!("Negotiate {}", base64::encode(&token)).into_bytes()]);
+        Ok(())
+    }
+
+    fn api_key(&self) -> Option<Secret> {
+        self.api_key.read().unwrap().clone()
+    }
+
+    fn set_api_key(&self, api_key: Option<Secret>) {
+        *self.api_key.write().unwrap() = api_key;
+    }
+}
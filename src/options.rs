@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use reqwest::header::{EntityTag, Header, Headers, IfMatch};
+
+use CancellationToken;
+
+/// Options that apply to a single request, on top of the session's defaults.
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    query: Vec<(String, String)>,
+    headers: Headers,
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    unqueued: bool,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        RequestOptions::default()
+    }
+
+    /// Add a query parameter to the request. It overrides the session's default query parameter
+    /// with the same name, if any.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Add a header to the request, for instance a one-off filter or a tracing header. It
+    /// overrides the session's header with the same name, if any.
+    pub fn header<H: Header>(mut self, header: H) -> Self {
+        self.headers.set(header);
+        self
+    }
+
+    /// Add a header that does not have a typed representation to the request.
+    pub fn raw_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .set_raw(name.to_owned(), vec![value.as_bytes().to_vec()]);
+        self
+    }
+
+    /// Return the headers specific to this request.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Set a timeout for this request, for instance for a long-running job creation, instead of
+    /// the client's default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Return the timeout specific to this request, if any.
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Abort the request when `token` is cancelled: it is then not sent, or not retried, and
+    /// fails with `Error::Cancelled`.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Return the cancellation token of this request, if any.
+    pub fn get_cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Send a `GET` request right away, without waiting for a slot of
+    /// `SessionBuilder::max_concurrent_gets` nor holding one. This is meant for the requests whose
+    /// response may stay unread for long, such as the long polls of a `PushCenter` or the body
+    /// of a `ChildStream`, which would otherwise keep the other requests waiting.
+    pub fn unqueued(mut self) -> Self {
+        self.unqueued = true;
+        self
+    }
+
+    /// Return true if the request does not wait for a slot, see `unqueued`.
+    pub fn is_unqueued(&self) -> bool {
+        self.unqueued
+    }
+
+    /// Answer a confirmation request from the server, such as
+    /// `DeleteOutcome::ConfirmationRequired`, with the ID of the chosen choice.
+    pub fn response_choice(self, choice: i64) -> Self {
+        self.query("responseChoice", &choice.to_string())
+    }
+
+    /// Only perform the request if the entity still has the given version (its `ETag`, as
+    /// returned by the server, weak or strong). When it changed in the meantime, the request
+    /// fails with `Error::Conflict`. Entities that keep their `ETag` (see `RestEntity::etag`)
+    /// send it automatically when they are saved or deleted.
+    pub fn if_match(self, etag: EntityTag) -> Self {
+        self.header(IfMatch::Items(vec![etag]))
+    }
+
+    /// Return the query parameters of the request, merged with the session's defaults.
+    pub fn merged_query<'q>(&'q self,
+                            defaults: &'q [(String, String)])
+                            -> Vec<&'q (String, String)> {
+        defaults
+            .iter()
+            .filter(|&&(ref name, _)| !self.query.iter().any(|&(ref n, _)| n == name))
+            .chain(self.query.iter())
+            .collect()
+    }
+}
@@ -0,0 +1,39 @@
+use serde_json;
+
+use Error;
+use codec::Codec;
+
+/// The outcome of a delete request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeleteOutcome {
+    /// The entity has been deleted.
+    Deleted,
+    /// The entity did not exist on the server (anymore).
+    AlreadyGone,
+    /// The server needs a confirmation before deleting the entity, usually because other entities
+    /// would be deleted as well. To confirm, send the request again with
+    /// `RequestOptions::response_choice` set to the ID of one of the choices.
+    ConfirmationRequired { choices: Vec<Choice> },
+}
+
+/// A choice offered by the server when it needs a confirmation.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Choice {
+    pub id: i64,
+    pub label: String,
+}
+
+/// The body of a response asking for a confirmation.
+#[derive(Deserialize)]
+pub struct Choices {
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+}
+
+impl Choices {
+    /// Decode the choices in the body of a `300 Multiple Choices` response, encoded with `codec`.
+    pub fn decode(body: &[u8], codec: &Codec) -> Result<Vec<Choice>, Error> {
+        let choices: Choices = serde_json::from_value(codec.decode(body)?)?;
+        Ok(choices.choices)
+    }
+}
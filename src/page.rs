@@ -0,0 +1,145 @@
+use std::ops::Deref;
+use std::vec;
+
+use Response;
+use {XNuageCount, XNuageOrderBy, XNuagePage, XNuagePageSize};
+
+/// A page of children, returned by `Fetcher::fetch`, with the pagination headers of the
+/// response. It dereferences to a slice of the children, and can be iterated over like a `Vec`.
+///
+/// ```rust,ignore
+/// let page = session.fetcher::<_, Domain>(&enterprise)?
+///     .page(2)
+///     .page_size(50)
+///     .fetch()?;
+/// println!("{} domains out of {:?}", page.len(), page.count);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Page<C> {
+    pub entities: Vec<C>,
+    /// The total number of children in the collection, from the `X-Nuage-Count` header.
+    pub count: Option<u64>,
+    /// The page returned, starting at 0, from the `X-Nuage-Page` header.
+    pub page: Option<u32>,
+    /// The maximum number of children per page, from the `X-Nuage-PageSize` header.
+    pub page_size: Option<u32>,
+    /// The order of the children, from the `X-Nuage-OrderBy` header.
+    pub order_by: Option<String>,
+}
+
+impl<C> Page<C> {
+    /// Return a page holding `entities`, with the pagination headers of `resp`.
+    pub fn new(entities: Vec<C>, resp: &Response) -> Self {
+        let headers = resp.headers();
+        Page {
+            entities: entities,
+            count: headers.get::<XNuageCount>().map(|count| count.0),
+            page: headers.get::<XNuagePage>().map(|page| page.0),
+            page_size: headers.get::<XNuagePageSize>().map(|size| size.0),
+            order_by: headers.get::<XNuageOrderBy>().map(|order| order.0.clone()),
+        }
+    }
+
+    /// Return the number of pages in the collection, if the server sent both the total number
+    /// of children and the page size.
+    pub fn page_count(&self) -> Option<u64> {
+        match (self.count, self.page_size) {
+            (Some(count), Some(size)) if size > 0 => {
+                Some((count + u64::from(size) - 1) / u64::from(size))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the children, dropping the pagination headers.
+    pub fn into_vec(self) -> Vec<C> {
+        self.entities
+    }
+}
+
+impl<C> Deref for Page<C> {
+    type Target = [C];
+
+    fn deref(&self) -> &[C] {
+        &self.entities
+    }
+}
+
+impl<C> IntoIterator for Page<C> {
+    type Item = C;
+    type IntoIter = vec::IntoIter<C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.into_iter()
+    }
+}
+
+impl<'a, C> IntoIterator for &'a Page<C> {
+    type Item = &'a C;
+    type IntoIter = ::std::slice::Iter<'a, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::Headers;
+
+    use super::*;
+
+    fn response(headers: Headers) -> Response {
+        Response::new(StatusCode::Ok,
+                      headers,
+                      Url::parse("https://vsd:8443/nuage/api/v5_0/enterprises").unwrap(),
+                      Box::new(Cursor::new(Vec::new())))
+    }
+
+    #[test]
+    fn headers() {
+        let mut headers = Headers::new();
+        headers.set(XNuageCount(120));
+        headers.set(XNuagePage(2));
+        headers.set(XNuagePageSize(50));
+        headers.set(XNuageOrderBy("name ASC".to_string()));
+        let page = Page::new(vec![1, 2, 3], &response(headers));
+        assert_eq!(page.count, Some(120));
+        assert_eq!(page.page, Some(2));
+        assert_eq!(page.page_size, Some(50));
+        assert_eq!(page.order_by, Some("name ASC".to_string()));
+        assert_eq!(page.page_count(), Some(3));
+    }
+
+    #[test]
+    fn no_headers() {
+        let page = Page::new(vec![1, 2, 3], &response(Headers::new()));
+        assert_eq!(page.count, None);
+        assert_eq!(page.page, None);
+        assert_eq!(page.page_count(), None);
+    }
+
+    #[test]
+    fn page_count() {
+        let mut page = Page::new(Vec::<u32>::new(), &response(Headers::new()));
+        page.count = Some(100);
+        page.page_size = Some(50);
+        assert_eq!(page.page_count(), Some(2));
+        page.count = Some(0);
+        assert_eq!(page.page_count(), Some(0));
+        page.page_size = Some(0);
+        assert_eq!(page.page_count(), None);
+    }
+
+    #[test]
+    fn entities() {
+        let page = Page::new(vec![1u32, 2, 3], &response(Headers::new()));
+        assert_eq!(page.len(), 3);
+        assert_eq!(page[1], 2);
+        assert_eq!((&page).into_iter().sum::<u32>(), 6);
+        assert_eq!(page.into_vec(), vec![1, 2, 3]);
+    }
+}
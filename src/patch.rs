@@ -0,0 +1,97 @@
+use hyper::mime::{Mime, TopLevel, SubLevel};
+use serde_json::{self, Map, Value};
+
+use Error;
+
+/// A single RFC 6902 JSON Patch operation. Paths are JSON pointers, e.g. `/name`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// The body of a `PATCH` request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Patch {
+    /// A list of RFC 6902 operations, sent as `application/json-patch+json`.
+    Json(Vec<Operation>),
+    /// An RFC 7396 merge patch, sent as `application/merge-patch+json`.
+    Merge(Value),
+    /// The attributes to update, by their serialized names, sent as `application/json`. This is
+    /// what the VSD endpoints that support `PATCH` expect.
+    Partial(Map<String, Value>),
+}
+
+impl Patch {
+    pub fn content_type(&self) -> Mime {
+        let sub_level = match *self {
+            Patch::Json(_) => "json-patch+json",
+            Patch::Merge(_) => "merge-patch+json",
+            Patch::Partial(_) => return Mime(TopLevel::Application, SubLevel::Json, vec![]),
+        };
+        Mime(TopLevel::Application,
+             SubLevel::Ext(sub_level.to_string()),
+             vec![])
+    }
+
+    pub fn body(&self) -> Result<Vec<u8>, Error> {
+        let body = match *self {
+            Patch::Json(ref operations) => serde_json::to_vec(operations)?,
+            Patch::Merge(ref value) => serde_json::to_vec(value)?,
+            Patch::Partial(ref attributes) => serde_json::to_vec(attributes)?,
+        };
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, Map, Value};
+
+    use super::*;
+
+    fn body(patch: &Patch) -> Value {
+        serde_json::from_slice(&patch.body().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_patch() {
+        let patch = Patch::Json(vec![Operation::Replace {
+                                         path: "/name".to_string(),
+                                         value: Value::String("acme".to_string()),
+                                     },
+                                     Operation::Remove { path: "/description".to_string() },
+                                     Operation::Move {
+                                         from: "/a".to_string(),
+                                         path: "/b".to_string(),
+                                     }]);
+        assert_eq!(patch.content_type().to_string(), "application/json-patch+json");
+        let expected = r#"[{"op": "replace", "path": "/name", "value": "acme"},
+                           {"op": "remove", "path": "/description"},
+                           {"op": "move", "from": "/a", "path": "/b"}]"#;
+        assert_eq!(body(&patch), serde_json::from_str::<Value>(expected).unwrap());
+    }
+
+    #[test]
+    fn merge_patch() {
+        let value: Value = serde_json::from_str(r#"{"name": "acme", "description": null}"#)
+            .unwrap();
+        let patch = Patch::Merge(value.clone());
+        assert_eq!(patch.content_type().to_string(), "application/merge-patch+json");
+        assert_eq!(body(&patch), value);
+    }
+
+    #[test]
+    fn partial() {
+        let mut attributes = Map::new();
+        attributes.insert("name".to_string(), Value::String("acme".to_string()));
+        let patch = Patch::Partial(attributes.clone());
+        assert_eq!(patch.content_type().to_string(), "application/json");
+        assert_eq!(body(&patch), Value::Object(attributes));
+    }
+}
@@ -0,0 +1,129 @@
+//! Construction of the URLs of the entities.
+//!
+//! `Url::join` resolves a reference the way a browser does, so it replaces the last segment of
+//! the base when the base does not end with a slash: joining `domains` to `.../enterprise/42`
+//! gives `.../enterprise/domains`. The URLs of the entities are built by appending segments
+//! instead.
+
+use reqwest::Url;
+use hyper::error::ParseError;
+
+use Error;
+
+/// Return `base` with `segments` appended to its path, whether or not the base ends with a
+/// slash. Each segment may hold several path segments separated by slashes, and is
+/// percent-encoded.
+pub fn append(base: &Url, segments: &[&str]) -> Result<Url, Error> {
+    let mut url = base.clone();
+    {
+        let mut path = url.path_segments_mut()
+            .map_err(|_| ParseError::RelativeUrlWithCannotBeABaseBase)?;
+        path.pop_if_empty();
+        for segment in segments {
+            path.extend(segment.split('/').filter(|s| !s.is_empty()));
+        }
+    }
+    Ok(url)
+}
+
+/// Return the URL of an entity: `<base>/<path>/<id>`.
+pub fn entity(base: &Url, path: &str, id: &str) -> Result<Url, Error> {
+    append(base, &[path, id])
+}
+
+/// Return the URL of a root entity: `<base>/<path>`. Root entities, such as `me`, are
+/// singletons with no ID in their URL.
+pub fn root(base: &Url, path: &str) -> Result<Url, Error> {
+    append(base, &[path])
+}
+
+/// Return the URL of a group of children: `<base>/<group path>` for the children of a root
+/// entity, or `<base>/<parent path>/<parent id>/<group path>` otherwise.
+pub fn group(base: &Url, parent: Option<(&str, &str)>, group_path: &str) -> Result<Url, Error> {
+    match parent {
+        Some((path, id)) => append(base, &[path, id, group_path]),
+        None => append(base, &[group_path]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::*;
+
+    fn base(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn root_children() {
+        let url = group(&base("https://vsd:8443/nuage/api/v5_0/"), None, "enterprises").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/enterprises");
+    }
+
+    #[test]
+    fn base_without_trailing_slash() {
+        let url = group(&base("https://vsd:8443/nuage/api/v5_0"), None, "enterprises").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/enterprises");
+
+        let url = entity(&base("https://vsd:8443/nuage/api/v5_0"), "enterprise", "42").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/enterprise/42");
+    }
+
+    #[test]
+    fn entity_url() {
+        let url = entity(&base("https://vsd:8443/nuage/api/v5_0/"), "enterprise", "42").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/enterprise/42");
+    }
+
+    #[test]
+    fn root_entity() {
+        let url = root(&base("https://vsd:8443/nuage/api/v5_0/"), "me").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/me");
+
+        let url = root(&base("https://vsd:8443/nuage/api/v5_0"), "me").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/me");
+    }
+
+    #[test]
+    fn nested_root_path() {
+        // Some APIs authenticate against a nested resource rather than a top-level one.
+        let url = root(&base("https://vsd:8443/api/"), "auth/me").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/api/auth/me");
+    }
+
+    #[test]
+    fn nested_children() {
+        let url = group(&base("https://vsd:8443/nuage/api/v5_0/"),
+                        Some(("enterprise", "42")),
+                        "domains")
+                .unwrap();
+        assert_eq!(url.as_str(),
+                   "https://vsd:8443/nuage/api/v5_0/enterprise/42/domains");
+    }
+
+    #[test]
+    fn deeply_nested_children() {
+        // The children of an entity are always under the entity itself, however deep the
+        // entity is in the hierarchy.
+        let url = group(&base("https://vsd:8443/nuage/api/v5_0"),
+                        Some(("subnet", "7")),
+                        "vports")
+                .unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/nuage/api/v5_0/subnet/7/vports");
+    }
+
+    #[test]
+    fn segments_are_escaped() {
+        let url = entity(&base("https://vsd:8443/nuage/api/v5_0/"), "enterprise", "a b?c").unwrap();
+        assert_eq!(url.as_str(),
+                   "https://vsd:8443/nuage/api/v5_0/enterprise/a%20b%3Fc");
+    }
+
+    #[test]
+    fn query_is_kept() {
+        let url = entity(&base("https://vsd:8443/api?x=1"), "enterprise", "42").unwrap();
+        assert_eq!(url.as_str(), "https://vsd:8443/api/enterprise/42?x=1");
+    }
+}
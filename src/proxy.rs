@@ -0,0 +1,69 @@
+use std::env;
+
+use reqwest::{Proxy, Url};
+
+use Error;
+
+/// The proxy configuration of a session.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyConfig {
+    pub url: Option<Url>,
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Read the configuration from the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment
+    /// variables, or their lower case versions.
+    pub fn from_env() -> Result<Self, Error> {
+        let url = match var("HTTPS_PROXY").or_else(|| var("HTTP_PROXY")) {
+            Some(url) => Some(Url::parse(&url)?),
+            None => None,
+        };
+        let no_proxy = var("NO_PROXY")
+            .map(|hosts| split_hosts(&hosts))
+            .unwrap_or_default();
+        Ok(ProxyConfig {
+               url: url,
+               no_proxy: no_proxy,
+           })
+    }
+
+    /// Return the proxy to give to the client, if any.
+    pub fn proxy(&self) -> Option<Proxy> {
+        let proxy_url = match self.url {
+            Some(ref url) => url.clone(),
+            None => return None,
+        };
+        let no_proxy = self.no_proxy.clone();
+        Some(Proxy::custom(move |url| match url.host_str() {
+                               Some(host) if bypasses(&no_proxy, host) => None,
+                               _ => Some(proxy_url.clone()),
+                           }))
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name)
+        .or_else(|_| env::var(name.to_lowercase()))
+        .ok()
+        .and_then(|value| if value.is_empty() { None } else { Some(value) })
+}
+
+/// Split a comma separated list of hosts, as found in `NO_PROXY`.
+pub fn split_hosts(hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+        .map(|host| host.to_owned())
+        .collect()
+}
+
+/// Return true if `host` must be reached without the proxy: if it is one of the `no_proxy` hosts
+/// or one of their subdomains, or if `no_proxy` contains `*`.
+fn bypasses(no_proxy: &[String], host: &str) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim_left_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
@@ -0,0 +1,283 @@
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json;
+
+use path;
+use {Alarm, AlarmQuery, CancellationToken, Error, Event, Method, RequestOptions, RestEntity,
+     Session};
+
+/// The kind of change an event notifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EventType {
+    Create,
+    Update,
+    Delete,
+    Grant,
+    Revoke,
+}
+
+/// A change notified by the server on the event channel.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    /// The type of the entities that changed, e.g. `"enterprise"`.
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    #[serde(rename = "updateMechanism", default)]
+    pub update_mechanism: Option<String>,
+    /// The entities that changed, as sent by the server.
+    #[serde(default)]
+    pub entities: Vec<serde_json::Value>,
+    /// The time the server received the event, in milliseconds since the Unix epoch.
+    #[serde(rename = "eventReceivedTime", default)]
+    pub received_at: Option<i64>,
+}
+
+impl PushEvent {
+    /// Deserialize the entities of the event. The entities are not given a session.
+    pub fn entities_as<E>(&self) -> Result<Vec<E>, Error>
+        where E: RestEntity
+    {
+        self.entities
+            .iter()
+            .map(|entity| Ok(serde_json::from_value(entity.clone())?))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct Notification {
+    uuid: String,
+    #[serde(default)]
+    events: Vec<PushEvent>,
+}
+
+type Callback = Box<Fn(&PushEvent) + Send>;
+
+/// Maintains the event channel of a session, by long-polling the `events` resource of the API,
+/// and dispatches the events to the registered callbacks.
+///
+/// ```rust,ignore
+/// let mut push = PushCenter::new(session.clone());
+/// let events = push.subscribe();
+/// let handle = push.start();
+/// for event in events {
+///     println!("{:?} {}", event.event_type, event.entity_type);
+/// }
+/// ```
+pub struct PushCenter {
+    session: Session,
+    uuid: Option<String>,
+    callbacks: Vec<Callback>,
+    timeout: Duration,
+    retry_delay: Duration,
+    cancellation: CancellationToken,
+}
+
+impl PushCenter {
+    pub fn new(session: Session) -> Self {
+        PushCenter {
+            session: session,
+            uuid: None,
+            callbacks: Vec::new(),
+            timeout: Duration::from_secs(180),
+            retry_delay: Duration::from_secs(5),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Set the timeout of each poll. The server holds the request until an event happens, so
+    /// the client's timeout, meant for the other requests, is not used: the timeout defaults to
+    /// 3 minutes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the delay before polling again after a failure. It defaults to 5 seconds.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Stop polling the event channel when `token` is cancelled. The push center then fails with
+    /// `Error::Cancelled` instead of polling, and the events of a poll that was in flight are
+    /// dropped. `PushHandle::stop` and `PushHandle::cancel` cancel this token.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Register a function called with every event.
+    pub fn add_callback<F>(&mut self, callback: F)
+        where F: Fn(&PushEvent) + Send + 'static
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Return a channel on which every event is sent.
+    pub fn subscribe(&mut self) -> Receiver<PushEvent> {
+        let (sender, receiver) = channel();
+        self.add_callback(move |event| {
+                              // The receiver may have been dropped.
+                              let _ = sender.send(event.clone());
+                          });
+        receiver
+    }
+
+    /// Return a channel on which the alarms matching `query` are sent as soon as they are
+    /// raised. This is the push counterpart of `Session::watch_alarms`.
+    pub fn subscribe_alarms(&mut self, query: AlarmQuery) -> Receiver<Alarm> {
+        let (sender, receiver) = channel();
+        self.add_callback(move |event| for alarm in new_alarms(event, &query) {
+                              // The receiver may have been dropped.
+                              let _ = sender.send(alarm);
+                          });
+        receiver
+    }
+
+    /// Wait for the next events, pass them to the callbacks and return them.
+    pub fn poll(&mut self) -> Result<Vec<PushEvent>, Error> {
+        let mut url = path::append(self.session.url(), &["events"])?;
+        if let Some(ref uuid) = self.uuid {
+            url.query_pairs_mut().append_pair("uuid", uuid);
+        }
+        // The server holds the request until an event happens: it must not hold a slot of
+        // `SessionBuilder::max_concurrent_gets` meanwhile.
+        let options = RequestOptions::new()
+            .cancellation(self.cancellation.clone())
+            .timeout(self.timeout)
+            .unqueued();
+        let mut resp = self.session
+            .send_with(Method::Get, url, self.session.headers(), None, &options)?;
+        self.session.check_status(&mut resp)?;
+        let body = self.session.read_body(&mut resp)?;
+        let notification: Notification =
+            serde_json::from_value(self.session.inner.codec.decode(&body)?)?;
+        self.uuid = Some(notification.uuid);
+        self.cancellation.check()?;
+        for event in &notification.events {
+            for callback in &self.callbacks {
+                callback(event);
+            }
+        }
+        Ok(notification.events)
+    }
+
+    /// Poll the event channel in a background thread, until the returned handle is stopped or
+    /// the cancellation token is cancelled. When a poll fails, `Event::PushChannelLost` is
+    /// emitted on the session, and the channel is opened again after the retry delay.
+    pub fn start(mut self) -> PushHandle {
+        let cancellation = self.cancellation.clone();
+        let thread = thread::spawn(move || {
+            while !self.cancellation.is_cancelled() {
+                match self.poll() {
+                    Ok(_) |
+                    Err(Error::Cancelled) => {}
+                    Err(e) => {
+                        self.uuid = None;
+                        let event = Event::PushChannelLost(e.to_string());
+                        self.session.inner.listeners.emit(event);
+                        self.cancellation.wait(self.retry_delay);
+                    }
+                }
+            }
+        });
+        PushHandle {
+            cancellation: cancellation,
+            thread: thread,
+        }
+    }
+}
+
+/// Return the alarms raised by `event` that match `query`. The entities that cannot be
+/// deserialized as alarms are skipped.
+fn new_alarms(event: &PushEvent, query: &AlarmQuery) -> Vec<Alarm> {
+    if event.event_type != EventType::Create || event.entity_type != "alarm" {
+        return Vec::new();
+    }
+    event
+        .entities
+        .iter()
+        .filter_map(|entity| serde_json::from_value::<Alarm>(entity.clone()).ok())
+        .filter(|alarm| query.matches(alarm))
+        .collect()
+}
+
+/// A handle to a push center running in the background, returned by `PushCenter::start`.
+pub struct PushHandle {
+    cancellation: CancellationToken,
+    thread: JoinHandle<()>,
+}
+
+impl PushHandle {
+    /// Stop polling the event channel, and wait for the current poll to return.
+    pub fn stop(self) {
+        self.cancellation.cancel();
+        let _ = self.thread.join();
+    }
+
+    /// Stop polling the event channel without waiting for the current poll to return. The
+    /// background thread exits when it does, without passing its events to the callbacks.
+    pub fn cancel(self) {
+        self.cancellation.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use alarm::Severity;
+    use {AlarmQuery, SessionBuilder};
+    use super::*;
+
+    #[test]
+    fn timeout() {
+        let url = "https://vsd:8443/nuage/api/v5_0/";
+        let session = SessionBuilder::plain(url, "csproot", "csproot")
+            .unwrap()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+        let push = PushCenter::new(session);
+        assert_eq!(push.timeout, Duration::from_secs(180));
+        let push = push.timeout(Duration::from_secs(60));
+        assert_eq!(push.timeout, Duration::from_secs(60));
+    }
+
+    fn event(event_type: &str, entity_type: &str) -> PushEvent {
+        serde_json::from_str(&format!(r#"{{
+            "type": "{}",
+            "entityType": "{}",
+            "entities": [
+                {{"ID": "1", "severity": "CRITICAL", "timestamp": 2000}},
+                {{"ID": "2", "severity": "MINOR", "timestamp": 2000}},
+                {{"ID": "3"}}
+            ]
+        }}"#,
+                                      event_type,
+                                      entity_type))
+            .unwrap()
+    }
+
+    #[test]
+    fn alarms() {
+        let ids = |event: &PushEvent, query: &AlarmQuery| {
+            new_alarms(event, query)
+                .into_iter()
+                .map(|alarm| alarm.id)
+                .collect::<Vec<_>>()
+        };
+        let query = AlarmQuery::new();
+        assert_eq!(ids(&event("CREATE", "alarm"), &query), vec!["1", "2"]);
+        assert!(ids(&event("UPDATE", "alarm"), &query).is_empty());
+        assert!(ids(&event("CREATE", "enterprise"), &query).is_empty());
+        let query = AlarmQuery::new().min_severity(Severity::Major);
+        assert_eq!(ids(&event("CREATE", "alarm"), &query), vec!["1"]);
+    }
+}
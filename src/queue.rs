@@ -0,0 +1,220 @@
+use std::io::{self, Read};
+use std::sync::{Arc, Condvar, Mutex};
+
+use transport::{Request, Response, Transport};
+use Error;
+
+/// A limit on the number of requests in flight, and therefore on the number of connections used,
+/// when many threads share a session and send requests at the same time. A request holds its
+/// slot until the body of its response has been read, or the response has been dropped, since
+/// the connection is busy until then.
+///
+/// The requests waiting for a slot are served in the order they arrived, so none of them waits
+/// forever while others keep taking the slots released.
+#[derive(Debug)]
+pub struct Queue {
+    transport: Arc<Transport>,
+    slots: Arc<Slots>,
+}
+
+impl Queue {
+    /// Create a queue that sends at most `limit` requests at a time with `transport`. A limit of
+    /// 0 means no limit.
+    pub fn new(limit: usize, transport: Arc<Transport>) -> Self {
+        Queue {
+            transport: transport,
+            slots: Arc::new(Slots {
+                                limit: limit,
+                                state: Mutex::new(State::default()),
+                                released: Condvar::new(),
+                            }),
+        }
+    }
+
+    /// Send a request once a slot is available, and return the response. The slot is released
+    /// once the body of the response has been read.
+    pub fn execute(&self, request: Request) -> Result<Response, Error> {
+        let slot = Slot::acquire(self.slots.clone());
+        // If the request fails, the slot is dropped, and therefore released, right away.
+        let resp = self.transport.execute(request)?;
+        Ok(Response::new(resp.status(),
+                         resp.headers().clone(),
+                         resp.url().clone(),
+                         Box::new(Body {
+                                      resp: resp,
+                                      slot: Some(slot),
+                                  })))
+    }
+}
+
+#[derive(Debug)]
+struct Slots {
+    limit: usize,
+    state: Mutex<State>,
+    released: Condvar,
+}
+
+/// The slots in use, and the tickets of the requests waiting for one.
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    /// The ticket of the next request to arrive.
+    next_ticket: u64,
+    /// The ticket of the next request to take a slot.
+    serving: u64,
+}
+
+/// A slot taken in a queue, released when dropped.
+struct Slot(Arc<Slots>);
+
+impl Slot {
+    fn acquire(slots: Arc<Slots>) -> Self {
+        if slots.limit > 0 {
+            {
+                let mut state = slots.state.lock().unwrap();
+                let ticket = state.next_ticket;
+                state.next_ticket = state.next_ticket.wrapping_add(1);
+                while ticket != state.serving || state.in_flight >= slots.limit {
+                    state = slots.released.wait(state).unwrap();
+                }
+                state.serving = state.serving.wrapping_add(1);
+                state.in_flight += 1;
+            }
+            // The next request in line may be able to take a slot as well.
+            slots.released.notify_all();
+        }
+        Slot(slots)
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if self.0.limit > 0 {
+            self.0.state.lock().unwrap().in_flight -= 1;
+            self.0.released.notify_all();
+        }
+    }
+}
+
+/// The body of a response sent through a queue, holding the slot of the request until it is
+/// read to the end or dropped.
+struct Body {
+    resp: Response,
+    slot: Option<Slot>,
+}
+
+impl Read for Body {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.resp.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.slot.take();
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::Headers;
+
+    use transport::{Request, Response, Transport};
+    use {Error, Method};
+    use super::*;
+
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Transport for Echo {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            Ok(Response::new(StatusCode::Ok,
+                             Headers::new(),
+                             request.url,
+                             Box::new(Cursor::new(b"body".to_vec()))))
+        }
+    }
+
+    fn request() -> Request {
+        Request {
+            method: Method::Get,
+            url: Url::parse("https://vsd:8443/nuage/api/v5_0/enterprises").unwrap(),
+            headers: Headers::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    #[test]
+    fn slot_held_until_body_read() {
+        let queue = Arc::new(Queue::new(1, Arc::new(Echo)));
+        let mut first = queue.execute(request()).unwrap();
+
+        let (sent, done) = channel();
+        let waiting = queue.clone();
+        let thread = thread::spawn(move || {
+                                       let resp = waiting.execute(request());
+                                       sent.send(()).unwrap();
+                                       resp.unwrap();
+                                   });
+        assert!(done.recv_timeout(Duration::from_millis(100)).is_err());
+
+        let mut body = String::new();
+        first.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "body");
+        done.recv_timeout(Duration::from_secs(5)).unwrap();
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn slot_released_when_dropped() {
+        let queue = Queue::new(1, Arc::new(Echo));
+        drop(queue.execute(request()).unwrap());
+        queue.execute(request()).unwrap();
+    }
+
+    #[test]
+    fn waiters_served_in_order() {
+        let queue = Arc::new(Queue::new(1, Arc::new(Echo)));
+        let first = queue.execute(request()).unwrap();
+
+        let (served, order) = channel();
+        let threads = (0..4)
+            .map(|i| {
+                let waiting = queue.clone();
+                let served = served.clone();
+                let thread = thread::spawn(move || {
+                                               let resp = waiting.execute(request()).unwrap();
+                                               served.send(i).unwrap();
+                                               drop(resp);
+                                           });
+                // Wait for the thread to take its ticket before starting the next one.
+                while queue.slots.state.lock().unwrap().next_ticket < i + 2 {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                thread
+            })
+            .collect::<Vec<_>>();
+        assert!(order.recv_timeout(Duration::from_millis(100)).is_err());
+
+        drop(first);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(order.try_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn zero_means_no_limit() {
+        let queue = Queue::new(0, Arc::new(Echo));
+        let responses = (0..4)
+            .map(|_| queue.execute(request()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 4);
+    }
+}
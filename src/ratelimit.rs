@@ -0,0 +1,193 @@
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reqwest::header::Headers;
+
+use Error;
+
+/// A token bucket bounding the rate of the requests sent by a session. It holds up to `burst`
+/// tokens, refilled at `rate` tokens per second, and every request takes one.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    refilled_at: Instant,
+    // Set when the server asked to slow down: no request is sent before then.
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Create a limiter. The rate and the burst must have been checked with `check`.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst);
+        RateLimiter {
+            rate: requests_per_second,
+            burst: burst,
+            state: Mutex::new(State {
+                                  tokens: burst,
+                                  refilled_at: Instant::now(),
+                                  paused_until: None,
+                              }),
+        }
+    }
+
+    /// Wait until a request may be sent.
+    pub fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        state.paused_until = None;
+                        let elapsed = now - state.refilled_at;
+                        state.tokens = (state.tokens + seconds(elapsed) * self.rate)
+                            .min(self.burst);
+                        state.refilled_at = now;
+                        if state.tokens >= 1.0 {
+                            state.tokens -= 1.0;
+                            return;
+                        }
+                        from_seconds((1.0 - state.tokens) / self.rate)
+                    }
+                }
+            };
+            thread::sleep(wait);
+        }
+    }
+
+    /// Hold all the requests for `delay`, for instance when the server answered with
+    /// `429 Too Many Requests`.
+    pub fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut state = self.state.lock().unwrap();
+        if state.paused_until.map_or(true, |paused| paused < until) {
+            state.paused_until = Some(until);
+        }
+    }
+}
+
+/// Fail if `requests_per_second` and `burst` are not a rate limit a limiter can enforce: with a
+/// rate or a burst of 0 no request would ever be sent, and a negative or NaN rate would never
+/// refill the bucket.
+pub fn check(requests_per_second: f64, burst: u32) -> Result<(), Error> {
+    let message = if !(requests_per_second > 0.0 && requests_per_second.is_finite()) {
+        format!("the rate limit must be a positive number of requests per second, not {}",
+                requests_per_second)
+    } else if burst == 0 {
+        "the burst of a rate limit must be at least 1 request".to_string()
+    } else {
+        return Ok(());
+    };
+    Err(Error::Io(io::Error::new(io::ErrorKind::InvalidInput, message)))
+}
+
+/// Return the delay given by the `Retry-After` header of a response, if any. Only the number of
+/// seconds form of the header is supported.
+pub fn retry_after(headers: &Headers) -> Option<Duration> {
+    headers
+        .get_raw("Retry-After")
+        .and_then(|values| values.first())
+        .and_then(|value| ::std::str::from_utf8(value).ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+fn from_seconds(seconds: f64) -> Duration {
+    Duration::new(seconds.trunc() as u64, (seconds.fract() * 1e9) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use reqwest::header::Headers;
+
+    use super::*;
+
+    #[test]
+    fn burst_is_not_delayed() {
+        let limiter = RateLimiter::new(1.0, 5);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire();
+        }
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn rate_is_enforced() {
+        let limiter = RateLimiter::new(20.0, 1);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire();
+        }
+        // The first request takes the initial token, and the next two wait 50 ms each.
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn pause_holds_requests() {
+        let limiter = RateLimiter::new(1000.0, 10);
+        limiter.pause(Duration::from_millis(100));
+        let start = Instant::now();
+        limiter.acquire();
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    fn is_invalid_input(result: Result<(), Error>) -> bool {
+        match result {
+            Err(Error::Io(ref e)) => e.kind() == io::ErrorKind::InvalidInput,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn valid_limit() {
+        assert!(check(0.5, 1).is_ok());
+    }
+
+    #[test]
+    fn zero_rate() {
+        assert!(is_invalid_input(check(0.0, 1)));
+    }
+
+    #[test]
+    fn negative_rate() {
+        assert!(is_invalid_input(check(-1.0, 1)));
+    }
+
+    #[test]
+    fn nan_rate() {
+        assert!(is_invalid_input(check(::std::f64::NAN, 1)));
+        assert!(is_invalid_input(check(::std::f64::INFINITY, 1)));
+    }
+
+    #[test]
+    fn zero_burst() {
+        assert!(is_invalid_input(check(1.0, 0)));
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let mut headers = Headers::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.set_raw("Retry-After", vec![b"2".to_vec()]);
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.set_raw("Retry-After", vec![b"Wed, 21 Oct 2015 07:28:00 GMT".to_vec()]);
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,67 @@
+use Error;
+use RestEntity;
+use Session;
+
+/// A lazy reference to an entity, made of its ID and a session. The entity is fetched the first
+/// time it is accessed, and then cached. This makes it easy to follow references between
+/// entities, such as the ID of a gateway held by a port.
+#[derive(Debug)]
+pub struct Ref<E> {
+    id: String,
+    session: Session,
+    target: Option<E>,
+}
+
+impl<E> Ref<E>
+    where E: RestEntity
+{
+    /// Create a reference to the entity with the given ID. Nothing is fetched yet.
+    pub fn new(session: Session, id: &str) -> Self {
+        Ref {
+            id: id.to_owned(),
+            session: session,
+            target: None,
+        }
+    }
+
+    /// Return the ID of the referenced entity.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Return true if the referenced entity has already been fetched.
+    pub fn is_loaded(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Return the referenced entity, fetching it if it has not been fetched yet.
+    pub fn get(&mut self) -> Result<&E, Error> {
+        self.load()?;
+        Ok(self.target.as_ref().unwrap())
+    }
+
+    /// Return the referenced entity, fetching it if it has not been fetched yet.
+    pub fn get_mut(&mut self) -> Result<&mut E, Error> {
+        self.load()?;
+        Ok(self.target.as_mut().unwrap())
+    }
+
+    /// Fetch the referenced entity again, even if it was already cached.
+    pub fn refresh(&mut self) -> Result<&E, Error> {
+        self.target = None;
+        self.get()
+    }
+
+    /// Consume the reference and return the referenced entity, fetching it if necessary.
+    pub fn into_inner(mut self) -> Result<E, Error> {
+        self.load()?;
+        Ok(self.target.unwrap())
+    }
+
+    fn load(&mut self) -> Result<(), Error> {
+        if self.target.is_none() {
+            self.target = Some(self.session.get(&self.id)?);
+        }
+        Ok(())
+    }
+}
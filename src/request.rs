@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use reqwest::header::{Header, Headers};
+use serde::{Deserialize, Serialize};
+
+use {CancellationToken, Error, Method, RequestOptions, Response, Session};
+
+/// A request to an endpoint that is not modeled by any entity, such as statistics or job
+/// results, created by `Session::request`. It starts with the session's headers, including the
+/// organization, and the session's authentication is added when it is sent:
+///
+/// ```rust,ignore
+/// let licenses: Vec<License> = session.request(Method::Get, "licenses")?
+///     .query("details", "true")
+///     .send_as()?;
+/// ```
+pub struct RequestBuilder {
+    session: Session,
+    method: Method,
+    url: Url,
+    headers: Headers,
+    body: Option<Vec<u8>>,
+    options: RequestOptions,
+    // An error that happened while building the request, returned when it is sent.
+    error: Option<Error>,
+}
+
+impl RequestBuilder {
+    pub fn new(session: Session, method: Method, url: Url) -> Self {
+        let headers = session.headers();
+        RequestBuilder {
+            session: session,
+            method: method,
+            url: url,
+            headers: headers,
+            body: None,
+            options: RequestOptions::new(),
+            error: None,
+        }
+    }
+
+    /// Add a header, or replace the session's header with the same name.
+    pub fn header<H: Header>(mut self, header: H) -> Self {
+        self.headers.set(header);
+        self
+    }
+
+    /// Add a header that does not have a typed representation.
+    pub fn raw_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .set_raw(name.to_owned(), vec![value.as_bytes().to_vec()]);
+        self
+    }
+
+    /// Return the headers of the request so far.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Add a query parameter. It overrides the session's default query parameter with the same
+    /// name, if any.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.options = self.options.query(name, value);
+        self
+    }
+
+    /// Set the timeout of the request, instead of the session's.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options = self.options.timeout(timeout);
+        self
+    }
+
+    /// Abort the request when `token` is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.options = self.options.cancellation(token);
+        self
+    }
+
+    /// Send `body` encoded with the session's codec.
+    pub fn body<B: Serialize>(mut self, body: &B) -> Self {
+        match self.session.encode(body) {
+            Ok(body) => self.body = Some(body),
+            Err(e) => self.error = Some(e),
+        }
+        self
+    }
+
+    /// Send `body` as is. The `Content-Type` header should then be set as well.
+    pub fn raw_body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Send the request, and return the response as is, whatever its status.
+    pub fn send(self) -> Result<Response, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.session
+            .send_with(self.method, self.url, self.headers, self.body, &self.options)
+    }
+
+    /// Send the request and decode the response into a `T`. An empty response is decoded as
+    /// `null`, so that `T` can be `()` or an `Option`. A response with an error status fails
+    /// like the other requests of the session.
+    pub fn send_as<T>(self) -> Result<T, Error>
+        where T: for<'de> Deserialize<'de>
+    {
+        let session = self.session.clone();
+        let mut resp = self.send()?;
+        session.decode_value(&mut resp)
+    }
+}
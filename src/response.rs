@@ -0,0 +1,46 @@
+use reqwest::{StatusCode, Url};
+use reqwest::header::Headers;
+
+use Response;
+use XNuageCount;
+use XNuagePage;
+
+/// What remains of a response once its body has been read: its status, its headers, and the
+/// pagination headers set by the server.
+#[derive(Clone, Debug)]
+pub struct ResponseInfo {
+    pub status: StatusCode,
+    pub headers: Headers,
+    /// The URL of the response, which may differ from the URL of the request after a
+    /// redirection.
+    pub url: Url,
+    /// The total number of entities in the collection, from the `X-Nuage-Count` header.
+    pub count: Option<u64>,
+    /// The page returned, from the `X-Nuage-Page` header.
+    pub page: Option<u32>,
+}
+
+impl ResponseInfo {
+    pub fn new(resp: &Response) -> Self {
+        let headers = resp.headers();
+        ResponseInfo {
+            status: resp.status(),
+            headers: headers.clone(),
+            url: resp.url().clone(),
+            count: headers.get::<XNuageCount>().map(|count| count.0),
+            page: headers.get::<XNuagePage>().map(|page| page.0),
+        }
+    }
+
+    /// Describe a request that was not sent to `url` because there was nothing to update. Its
+    /// status is `304 Not Modified`, and it has no headers.
+    pub fn unchanged(url: Url) -> Self {
+        ResponseInfo {
+            status: StatusCode::NotModified,
+            headers: Headers::new(),
+            url: url,
+            count: None,
+            page: None,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{cmp, io};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper;
+use reqwest::StatusCode;
+
+use Error;
+
+/// When and how often a session retries the idempotent requests (`GET`, `PUT`, `DELETE`, `HEAD`
+/// and `OPTIONS`) that fail with a transient status, or because the connection could not be
+/// established, timed out or was reset. The other errors, such as TLS or decoding errors, are
+/// never retried. The delay between two attempts grows exponentially:
+///
+/// ```rust,ignore
+/// let policy = RetryPolicy::new()
+///     .max_attempts(5)
+///     .backoff_base(Duration::from_millis(200));
+/// let session = SessionBuilder::new(url, "csproot", "csproot", "csp")?
+///     .retry_policy(policy)
+///     .build()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff_base: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    statuses: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff_base: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            statuses: vec![StatusCode::BadGateway,
+                           StatusCode::ServiceUnavailable,
+                           StatusCode::GatewayTimeout],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Return the default policy: 3 attempts, starting with a 100ms delay, with jitter, on
+    /// connection errors and on `502`, `503` and `504` responses.
+    pub fn new() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// Set the maximum number of attempts, including the first one.
+    pub fn max_attempts(mut self, n: u32) -> Self {
+        self.max_attempts = n;
+        self
+    }
+
+    /// Set the delay before the first retry. It doubles with each retry.
+    pub fn backoff_base(mut self, delay: Duration) -> Self {
+        self.backoff_base = delay;
+        self
+    }
+
+    /// Set the maximum delay between two attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Randomize the delays, so that many clients that failed at the same time do not retry at
+    /// the same time. It is enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the response statuses on which requests are retried.
+    pub fn retry_on(mut self, statuses: Vec<StatusCode>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Return true if another attempt is allowed after `attempts` attempts.
+    pub fn allows(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    /// Return true if a response with the given status should be retried.
+    pub fn retries_status(&self, status: StatusCode) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// Return true if a request that failed with `error` should be retried: only the connection
+    /// errors that may not happen again are.
+    pub fn retries_error(&self, error: &Error) -> bool {
+        match *error {
+            Error::Io(ref e) => transient(e),
+            Error::Reqwest(ref e) => {
+                match e.get_ref() {
+                    Some(cause) => {
+                        match cause.downcast_ref::<io::Error>() {
+                            Some(e) => transient(e),
+                            None => {
+                                match cause.downcast_ref::<hyper::Error>() {
+                                    Some(&hyper::Error::Io(ref e)) => transient(e),
+                                    _ => false,
+                                }
+                            }
+                        }
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the delay before the next attempt, after `attempts` attempts.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::max_value());
+        let delay = self.backoff_base
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| cmp::min(delay, self.max_delay));
+        if !self.jitter {
+            return delay;
+        }
+        // Wait between half and all of the delay. The jitter does not need to be unpredictable,
+        // only different between clients, so the clock is a good enough source of randomness.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let half = delay / 2;
+        half + half * (nanos % 1000) / 1000
+    }
+}
+
+/// Return true if an I/O error means that the connection could not be established, timed out or
+/// was reset.
+fn transient(error: &io::Error) -> bool {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused |
+        io::ErrorKind::ConnectionReset |
+        io::ErrorKind::ConnectionAborted |
+        io::ErrorKind::NotConnected |
+        io::ErrorKind::BrokenPipe |
+        io::ErrorKind::TimedOut |
+        // Timeouts of blocking sockets are reported as `WouldBlock` on some platforms.
+        io::ErrorKind::WouldBlock => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+
+    use Error;
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .max_attempts(4)
+            .backoff_base(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(350))
+            .jitter(false)
+    }
+
+    #[test]
+    fn allows() {
+        let policy = policy();
+        assert!(policy.allows(1));
+        assert!(policy.allows(3));
+        assert!(!policy.allows(4));
+        assert!(!RetryPolicy::new().max_attempts(0).allows(0));
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let policy = policy();
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(350));
+        assert_eq!(policy.delay(64), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn delay_with_jitter() {
+        let policy = policy().jitter(true);
+        for attempts in 1..5 {
+            let delay = policy.delay(attempts);
+            let max = policy.clone().jitter(false).delay(attempts);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn retried_errors() {
+        let policy = policy();
+        let error = |kind| Error::Io(io::Error::new(kind, "test"));
+        assert!(policy.retries_error(&error(io::ErrorKind::ConnectionRefused)));
+        assert!(policy.retries_error(&error(io::ErrorKind::ConnectionReset)));
+        assert!(policy.retries_error(&error(io::ErrorKind::TimedOut)));
+        assert!(!policy.retries_error(&error(io::ErrorKind::InvalidData)));
+        assert!(!policy.retries_error(&error(io::ErrorKind::PermissionDenied)));
+        assert!(!policy.retries_error(&Error::NoEntity));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// A credential, such as a password or an API key.
+///
+/// Its `Debug` implementation does not show the value. With the `zeroize` feature, the memory
+/// holding the value is wiped when the secret, or any of its clones, is dropped. It is
+/// serialized as a plain string.
+///
+/// Only the secret itself is wiped. The copies made to use it are not: the `Authorization`
+/// header encoded for every request, the buffers of the HTTP client, the response body an API
+/// key was read from, and the strings returned by `expose` and copied by the caller. The feature
+/// reduces the number of copies lingering in the memory of long-running processes, but does not
+/// guarantee that none is left.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        Secret(secret.into())
+    }
+
+    /// Return the actual value of the secret.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret(secret)
+    }
+}
+
+impl<'a> From<&'a str> for Secret {
+    fn from(secret: &'a str) -> Self {
+        Secret(secret.to_owned())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
@@ -0,0 +1,36 @@
+use serde_json::Value;
+
+/// An API version supported by the server.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ApiVersion {
+    /// The version, e.g. `"v5.0"`.
+    pub version: String,
+    /// The status of the version, e.g. `"CURRENT"` or `"DEPRECATED"`.
+    pub status: String,
+}
+
+/// The body of the server's version endpoint.
+#[derive(Deserialize)]
+pub struct Versions {
+    #[serde(default)]
+    pub versions: Vec<ApiVersion>,
+}
+
+/// Information about the server, used to adapt the behavior of a client to the server version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerInfo {
+    /// The API versions supported by the server.
+    pub versions: Vec<ApiVersion>,
+    /// The system configuration of the server, if the user is allowed to read it.
+    pub system_config: Option<Value>,
+}
+
+impl ServerInfo {
+    /// Return the current API version of the server.
+    pub fn current_version(&self) -> Option<&str> {
+        self.versions
+            .iter()
+            .find(|v| v.status == "CURRENT")
+            .map(|v| v.version.as_str())
+    }
+}
@@ -0,0 +1,25 @@
+use Secret;
+use user::CurrentUser;
+
+/// The state of a session, to resume it later without authenticating with the password again.
+/// It is returned by `Session::serialize_state`, and restored with `Session::from_state` or
+/// `SessionBuilder::from_state`.
+///
+/// It can be serialized, for instance by a command line tool that caches it on disk between two
+/// invocations. Since it holds the API key, it must be stored with the same care as a password.
+/// The API key is serialized as is, but not shown by the `Debug` implementation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// The URL of the API.
+    pub url: String,
+    pub username: String,
+    #[serde(default)]
+    pub organization: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<Secret>,
+    #[serde(default)]
+    pub current_user: Option<CurrentUser>,
+    /// The user the session is impersonating, as `user@enterprise`.
+    #[serde(default)]
+    pub proxy_user: Option<String>,
+}
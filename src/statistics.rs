@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+/// The statistics of an entity, as returned by its `statistics` endpoint.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Statistics {
+    /// The start of the period, in seconds since the Unix epoch.
+    #[serde(rename = "startTime")]
+    pub start_time: i64,
+    /// The end of the period, in seconds since the Unix epoch.
+    #[serde(rename = "endTime")]
+    pub end_time: i64,
+    #[serde(rename = "numberOfDataPoints")]
+    pub number_of_data_points: u32,
+    /// The data points of each metric. A point is missing when nothing was collected for it.
+    #[serde(default)]
+    pub stats: BTreeMap<String, Vec<Option<f64>>>,
+}
+
+/// A time series of metrics, returned by `Session::statistics`: the values of each metric at
+/// every timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeSeries {
+    /// The time of each data point, in seconds since the Unix epoch.
+    pub timestamps: Vec<i64>,
+    /// The values of each metric, in the same order as the timestamps. A value is missing when
+    /// nothing was collected for it.
+    pub values: BTreeMap<String, Vec<Option<f64>>>,
+}
+
+impl TimeSeries {
+    /// Return the values of a metric, e.g. `"BYTES_IN"`.
+    pub fn metric(&self, name: &str) -> Option<&[Option<f64>]> {
+        self.values.get(name).map(|values| values.as_slice())
+    }
+
+    /// Return the pairs of timestamp and value of a metric, skipping the missing values.
+    pub fn points(&self, name: &str) -> Vec<(i64, f64)> {
+        match self.values.get(name) {
+            Some(values) => {
+                self.timestamps
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(&time, value)| value.map(|value| (time, value)))
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+impl From<Statistics> for TimeSeries {
+    /// The data points are evenly spread over the period, the first one being at its start.
+    fn from(statistics: Statistics) -> Self {
+        let points = i64::from(statistics.number_of_data_points);
+        let step = if points > 0 {
+            (statistics.end_time - statistics.start_time) / points
+        } else {
+            0
+        };
+        TimeSeries {
+            timestamps: (0..points)
+                .map(|i| statistics.start_time + i * step)
+                .collect(),
+            values: statistics.stats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    fn statistics(json: &str) -> TimeSeries {
+        serde_json::from_str::<Statistics>(json).unwrap().into()
+    }
+
+    #[test]
+    fn time_series() {
+        let series = statistics(r#"{"startTime": 1000, "endTime": 1040,
+                                    "numberOfDataPoints": 4,
+                                    "stats": {"BYTES_IN": [1.0, null, 3.0, 4.0]}}"#);
+        assert_eq!(series.timestamps, vec![1000, 1010, 1020, 1030]);
+        assert_eq!(series.metric("BYTES_IN"),
+                   Some(&[Some(1.0), None, Some(3.0), Some(4.0)][..]));
+        assert_eq!(series.points("BYTES_IN"),
+                   vec![(1000, 1.0), (1020, 3.0), (1030, 4.0)]);
+        assert_eq!(series.metric("BYTES_OUT"), None);
+        assert!(series.points("BYTES_OUT").is_empty());
+    }
+
+    #[test]
+    fn no_data_points() {
+        let series = statistics(r#"{"startTime": 1000, "endTime": 1040,
+                                    "numberOfDataPoints": 0}"#);
+        assert!(series.timestamps.is_empty());
+        assert!(series.values.is_empty());
+    }
+}
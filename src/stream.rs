@@ -0,0 +1,265 @@
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+use serde::Deserializer;
+use serde::de::{self, DeserializeOwned, SeqAccess, Visitor};
+use serde_json;
+
+use body;
+use CancellationToken;
+use Error;
+use Response;
+use Session;
+use RestEntity;
+
+/// A serde visitor that deserializes a JSON array of entities one element at a time, handing
+/// each element over to a callback instead of collecting them into a `Vec`.
+pub struct EachChild<'a, C, F> {
+    session: &'a Session,
+    callback: F,
+    marker: PhantomData<C>,
+}
+
+impl<'a, C, F> EachChild<'a, C, F>
+    where C: RestEntity,
+          F: FnMut(C)
+{
+    pub fn new(session: &'a Session, callback: F) -> Self {
+        EachChild {
+            session: session,
+            callback: callback,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'a, C, F> Visitor<'de> for EachChild<'a, C, F>
+    where C: RestEntity,
+          F: FnMut(C)
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of entities")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<(), A::Error>
+        where A: SeqAccess<'de>
+    {
+        while let Some(mut child) = seq.next_element::<C>()? {
+            self.session.bind(&mut child);
+            (self.callback)(child);
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the children in a response body, deserialized one at a time as the body is
+/// read. It is created by `Session::stream_children` or `Fetcher::stream`.
+///
+/// The body is read on a separate thread, which hands the children over one at a time, so only
+/// the child being deserialized is held in memory: listing a very large collection uses as
+/// little memory as listing a small one. The body is always expected to be a JSON array,
+/// whatever codec the session uses.
+pub struct ChildStream<C> {
+    session: Session,
+    children: Receiver<Result<C, Error>>,
+    cancellation: Option<CancellationToken>,
+    done: bool,
+}
+
+impl<C> ChildStream<C>
+    where C: RestEntity + Send + 'static
+{
+    pub fn new(session: Session,
+               response: Response,
+               cancellation: Option<CancellationToken>)
+               -> Self {
+        let (sender, children) = sync_channel(1);
+        let token = cancellation.clone();
+        thread::spawn(move || if let Err(e) = read_children(response, &sender, token) {
+                          // The stream may have been dropped in the meantime.
+                          let _ = sender.send(Err(e));
+                      });
+        ChildStream {
+            session: session,
+            children: children,
+            cancellation: cancellation,
+            done: false,
+        }
+    }
+}
+
+impl<C> Iterator for ChildStream<C>
+    where C: RestEntity
+{
+    type Item = Result<C, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(ref token) = self.cancellation {
+            if let Err(e) = token.check() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        match self.children.recv() {
+            Ok(Ok(mut child)) => {
+                self.session.bind(&mut child);
+                Some(Ok(child))
+            }
+            Ok(Err(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+            // The whole body was read.
+            Err(_) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+/// Deserialize the children in the body of `response`, and send them one by one to `sender`.
+fn read_children<C>(response: Response,
+                    sender: &SyncSender<Result<C, Error>>,
+                    cancellation: Option<CancellationToken>)
+                    -> Result<(), Error>
+    where C: DeserializeOwned
+{
+    let mut reader = BufReader::new(response);
+    // The server answers with an empty body when there is no child.
+    if reader.fill_buf()?.is_empty() {
+        return Ok(());
+    }
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    de.deserialize_seq(SendChildren {
+                             sender: sender,
+                             cancellation: cancellation,
+                         })
+        .map_err(body::from_json)?;
+    de.end().map_err(body::from_json)
+}
+
+/// A serde visitor that sends each element of a JSON array of entities over a channel.
+struct SendChildren<'a, C: 'a> {
+    sender: &'a SyncSender<Result<C, Error>>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl<'de, 'a, C> Visitor<'de> for SendChildren<'a, C>
+    where C: DeserializeOwned
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of entities")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+        where A: SeqAccess<'de>
+    {
+        loop {
+            // Stop reading the body as soon as the stream is cancelled or dropped. The error is
+            // never seen: the stream reports the cancellation itself.
+            if self.cancellation.as_ref().map_or(false, |token| token.is_cancelled()) {
+                return Err(de::Error::custom("the stream was cancelled"));
+            }
+            let child = match seq.next_element::<C>()? {
+                Some(child) => child,
+                None => return Ok(()),
+            };
+            if self.sender.send(Ok(child)).is_err() {
+                return Err(de::Error::custom("the stream was dropped"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read};
+
+    use reqwest::{StatusCode, Url};
+    use reqwest::header::Headers;
+
+    use {Error, Metadata, Response, Session, SessionBuilder};
+    use super::*;
+
+    /// A body read one byte at a time, so that every token is split across reads.
+    struct OneByte(Cursor<Vec<u8>>);
+
+    impl Read for OneByte {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = if buf.is_empty() { 0 } else { 1 };
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    fn session() -> Session {
+        SessionBuilder::plain("https://vsd:8443/nuage/api/v5_0/", "csproot", "csproot")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn stream(body: &str) -> ChildStream<Metadata> {
+        let response = Response::new(StatusCode::Ok,
+                                     Headers::new(),
+                                     Url::parse("https://vsd:8443/nuage/api/v5_0/metadatas")
+                                         .unwrap(),
+                                     Box::new(OneByte(Cursor::new(body.as_bytes().to_vec()))));
+        ChildStream::new(session(), response, None)
+    }
+
+    fn names(body: &str) -> Vec<Option<String>> {
+        stream(body)
+            .map(|metadata| metadata.unwrap().name)
+            .collect()
+    }
+
+    #[test]
+    fn split_tokens() {
+        let body = r#"[ {"ID": "1", "name": "a"} ,{"ID": "2", "name": "b", "global": true}]"#;
+        assert_eq!(names(body), vec![Some("a".to_string()), Some("b".to_string())]);
+    }
+
+    #[test]
+    fn escapes() {
+        let body = r#"[{"name": "a \"quoted\" ], {name}", "blob": "\\"}, {"name": "é"}]"#;
+        assert_eq!(names(body),
+                   vec![Some(r#"a "quoted" ], {name}"#.to_string()), Some("é".to_string())]);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(names("[]").is_empty());
+        assert!(names(" [ ] ").is_empty());
+        assert!(names("").is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let mut children = stream(r#"[{"name": "a"}, {"name": }]"#);
+        assert!(children.next().unwrap().is_ok());
+        match children.next() {
+            Some(Err(Error::Json(_))) => {}
+            other => panic!("unexpected item: {:?}", other),
+        }
+        assert!(children.next().is_none());
+    }
+
+    #[test]
+    fn not_an_array() {
+        match stream(r#"{"name": "a"}"#).next() {
+            Some(Err(Error::Json(_))) => {}
+            other => panic!("unexpected item: {:?}", other),
+        }
+    }
+}
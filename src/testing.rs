@@ -0,0 +1,265 @@
+//! Helpers to test code built on bambou without a live server.
+//!
+//! `MockTransport` is a `Transport` that answers with canned responses and records the requests
+//! it receives. Sessions built with it go through the whole request path, including the
+//! authentication, the interceptors and the retries, but never open a connection:
+//!
+//! ```rust,ignore
+//! let mock = MockTransport::new();
+//! mock.respond(Method::Get, "/enterprises/42", 200, r#"[{"ID": "42", "name": "acme"}]"#);
+//! let session = mock.session()?;
+//! let enterprise: Enterprise = session.get("42")?;
+//! assert_eq!(mock.requests()[0].path, "/enterprises/42");
+//! ```
+
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use reqwest::{StatusCode, Url};
+use reqwest::header::{ContentType, Headers};
+use serde::Deserialize;
+use serde_json;
+
+use transport::{Request, Response, Transport};
+use {Error, Method, Session, SessionBuilder};
+
+/// The URL of the sessions returned by `MockTransport::session`.
+const URL: &'static str = "http://mock/";
+
+/// A canned response.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        MockResponse {
+            status: status,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// Add a header to the response, e.g. `X-Nuage-Count`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+/// A request received by a `MockTransport`.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// The path of the request, including the query if any.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// Return the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|&&(ref n, _)| n.to_lowercase() == name.to_lowercase())
+            .map(|&(_, ref value)| value.as_str())
+    }
+
+    /// Deserialize the body of the request, which must be JSON.
+    pub fn json<T>(&self) -> Result<T, Error>
+        where T: for<'de> Deserialize<'de>
+    {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: Vec<(Method, String, MockResponse)>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// A transport answering with canned responses. Its clones share the responses and the requests
+/// received.
+#[derive(Clone, Debug, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    /// Return the URL of the sessions returned by `session`. Sessions with other options can be
+    /// built for it with `SessionBuilder::transport`.
+    pub fn url(&self) -> Url {
+        Url::parse(URL).unwrap()
+    }
+
+    /// Return a session sending its requests to the transport, with dummy credentials.
+    pub fn session(&self) -> Result<Session, Error> {
+        SessionBuilder::plain(URL, "user", "password")?
+            .transport(self.clone())
+            .build()
+    }
+
+    /// Answer the requests with the given method and path (without the query) with `status` and
+    /// `body`. The latest matching response registered is used.
+    pub fn respond(&self, method: Method, path: &str, status: u16, body: &str) {
+        self.respond_with(method, path, MockResponse::new(status, body));
+    }
+
+    /// Answer the requests with the given method and path (without the query) with `response`.
+    pub fn respond_with(&self, method: Method, path: &str, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .push((method, path.to_owned(), response));
+    }
+
+    /// Return the requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Forget the requests received so far.
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+}
+
+impl Transport for MockTransport {
+    fn execute(&self, request: Request) -> Result<Response, Error> {
+        let path = request.url.path().to_owned();
+        let recorded = RecordedRequest {
+            method: request.method.clone(),
+            path: match request.url.query() {
+                Some(query) => format!("{}?{}", path, query),
+                None => path.clone(),
+            },
+            headers: request
+                .headers
+                .iter()
+                .map(|header| (header.name().to_owned(), header.value_string()))
+                .collect(),
+            body: request.body.unwrap_or_default(),
+        };
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            let response = state
+                .routes
+                .iter()
+                .rev()
+                .find(|&&(ref method, ref p, _)| *method == request.method && *p == path)
+                .map(|&(_, _, ref response)| response.clone())
+                .unwrap_or_else(|| not_found(&recorded));
+            state.requests.push(recorded);
+            response
+        };
+
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        for &(ref name, ref value) in &response.headers {
+            headers.set_raw(name.clone(), vec![value.as_bytes().to_vec()]);
+        }
+        // The response to a `HEAD` request has no body.
+        let body = if request.method == Method::Head {
+            Vec::new()
+        } else {
+            response.body
+        };
+        Ok(Response::new(StatusCode::from_u16(response.status),
+                         headers,
+                         request.url,
+                         Box::new(Cursor::new(body))))
+    }
+}
+
+fn not_found(request: &RecordedRequest) -> MockResponse {
+    let description = format!("No response registered for {} {}", request.method, request.path);
+    let body = format!(concat!(r#"{{"errors": [{{"property": "", "descriptions": "#,
+                               r#"[{{"title": "No mock response", "description": {}}}]}}]}}"#),
+                       serde_json::Value::String(description));
+    MockResponse::new(404, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use reqwest::StatusCode;
+
+    use transport::{Request, Transport};
+    use {Error, Method, Metadata, RestEntity};
+    use super::*;
+
+    fn execute(mock: &MockTransport, method: Method, path: &str) -> (StatusCode, String) {
+        let request = Request {
+            method: method,
+            url: mock.url().join(path).unwrap(),
+            headers: Headers::new(),
+            body: None,
+            timeout: None,
+        };
+        let mut resp = mock.execute(request).unwrap();
+        let mut body = String::new();
+        resp.read_to_string(&mut body).unwrap();
+        (resp.status(), body)
+    }
+
+    #[test]
+    fn routes() {
+        let mock = MockTransport::new();
+        mock.respond(Method::Get, "/metadatas", 200, "[]");
+        mock.respond(Method::Get, "/metadatas", 200, r#"[{"ID": "1"}]"#);
+        mock.respond_with(Method::Head,
+                          "/metadatas",
+                          MockResponse::new(200, "ignored").header("X-Nuage-Count", "1"));
+
+        assert_eq!(execute(&mock, Method::Get, "metadatas?page=1"),
+                   (StatusCode::Ok, r#"[{"ID": "1"}]"#.to_string()));
+        assert_eq!(execute(&mock, Method::Head, "metadatas"),
+                   (StatusCode::Ok, String::new()));
+        let (status, body) = execute(&mock, Method::Delete, "metadatas");
+        assert_eq!(status, StatusCode::NotFound);
+        assert!(body.contains("No response registered for DELETE /metadatas"));
+    }
+
+    #[test]
+    fn recorded_requests() {
+        let mock = MockTransport::new();
+        mock.respond(Method::Post, "/metadatas", 201, r#"[{"ID": "1", "name": "a"}]"#);
+        let created: Vec<Metadata> = mock.session()
+            .unwrap()
+            .post_as("metadatas", &Metadata::new("a", "blob"))
+            .unwrap();
+        assert_eq!(created[0].id(), Some("1"));
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Post);
+        assert_eq!(requests[0].path, "/metadatas");
+        assert!(requests[0].header("authorization").is_some());
+        let body: Metadata = requests[0].json().unwrap();
+        assert_eq!(body.name, Some("a".to_string()));
+
+        mock.clear_requests();
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let mock = MockTransport::new();
+        mock.respond(Method::Get, "/metadatas/1", 404, "");
+        match mock.session().unwrap().get_as::<Vec<Metadata>>("metadatas/1") {
+            Err(Error::Api { status: StatusCode::NotFound, .. }) => {}
+            other => panic!("expected a not found error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode, Url};
+use reqwest::header::Headers;
+
+use Error;
+use Method;
+
+/// A request, as handed over to a `Transport` once the session has set all its headers.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub url: Url,
+    pub headers: Headers,
+    pub body: Option<Vec<u8>>,
+    /// The timeout specific to this request, if any.
+    pub timeout: Option<Duration>,
+}
+
+/// A response returned by a `Transport`. Its body is read through the `Read` implementation.
+pub struct Response {
+    status: StatusCode,
+    headers: Headers,
+    url: Url,
+    body: Box<Read + Send>,
+}
+
+impl Response {
+    pub fn new(status: StatusCode, headers: Headers, url: Url, body: Box<Read + Send>) -> Self {
+        Response {
+            status: status,
+            headers: headers,
+            url: url,
+            body: body,
+        }
+    }
+
+    /// Return the status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Return the headers of the response.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Return the URL of the response, which may differ from the URL of the request after a
+    /// redirection.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl From<::reqwest::Response> for Response {
+    fn from(resp: ::reqwest::Response) -> Self {
+        Response::new(resp.status(),
+                      resp.headers().clone(),
+                      resp.url().clone(),
+                      Box::new(resp))
+    }
+}
+
+impl Read for Response {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+/// The HTTP stack used by a session to send its requests. The default transport uses a
+/// `reqwest` client, but another one can be set with `SessionBuilder::transport`, for instance
+/// to record and replay requests, or to use another HTTP library.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a request and return the response, without reading its body.
+    fn execute(&self, request: Request) -> Result<Response, Error>;
+}
+
+/// The default transport, built on a `reqwest` client.
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        ReqwestTransport { client: client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn execute(&self, request: Request) -> Result<Response, Error> {
+        let mut builder = self.client
+            .request(request.method, request.url)?
+            .headers(request.headers);
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        Ok(builder.send()?.into())
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
+
+/// The body of an upload request.
+pub enum Upload {
+    /// A raw body, sent as is with the given content type.
+    Raw { content_type: Mime, data: Vec<u8> },
+    /// A `multipart/form-data` body. The content type, including the boundary, is set
+    /// automatically.
+    Multipart(Form),
+}
+
+impl Upload {
+    /// Create a raw upload.
+    pub fn raw(content_type: Mime, data: Vec<u8>) -> Self {
+        Upload::Raw {
+            content_type: content_type,
+            data: data,
+        }
+    }
+
+    /// Create a multipart upload.
+    pub fn multipart(form: Form) -> Self {
+        Upload::Multipart(form)
+    }
+
+    /// Return the content type and the bytes of the body.
+    pub fn into_body(self) -> (Mime, Vec<u8>) {
+        match self {
+            Upload::Raw { content_type, data } => (content_type, data),
+            Upload::Multipart(form) => form.encode(),
+        }
+    }
+}
+
+/// The parts of a `multipart/form-data` body. The body is encoded in memory, so that it is sent,
+/// signed and logged like any other request body.
+///
+/// ```rust,ignore
+/// let form = Form::new()
+///     .text("name", "vsd-cert")
+///     .file("certificate", "/etc/pki/vsd.pem")?;
+/// session.upload("certificates", Upload::multipart(form))?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Form {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: Vec<u8>,
+}
+
+impl Form {
+    pub fn new() -> Self {
+        Form::default()
+    }
+
+    /// Add a text field.
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.parts.push(Part {
+                            name: name.to_owned(),
+                            filename: None,
+                            content_type: None,
+                            data: value.as_bytes().to_vec(),
+                        });
+        self
+    }
+
+    /// Add a file, with the given file name and content type.
+    pub fn bytes(mut self,
+                 name: &str,
+                 filename: &str,
+                 content_type: Mime,
+                 data: Vec<u8>)
+                 -> Self {
+        self.parts.push(Part {
+                            name: name.to_owned(),
+                            filename: Some(filename.to_owned()),
+                            content_type: Some(content_type),
+                            data: data,
+                        });
+        self
+    }
+
+    /// Add the file at `path`, read right away, as `application/octet-stream`.
+    pub fn file<P: AsRef<Path>>(self, name: &str, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        let filename = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(self.bytes(name,
+                      &filename,
+                      Mime(TopLevel::Application, SubLevel::Ext("octet-stream".to_owned()), vec![]),
+                      data))
+    }
+
+    /// Return the content type, with its boundary, and the bytes of the body. The boundary is
+    /// chosen so that it does not appear in any part.
+    pub fn encode(self) -> (Mime, Vec<u8>) {
+        let boundary = (0..)
+            .map(|n| format!("bambou-boundary-{}", n))
+            .find(|boundary| !self.parts.iter().any(|part| contains(&part.data, boundary)))
+            .unwrap();
+        let mut body = Vec::new();
+        for part in self.parts {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"",
+                                          quoted(&part.name));
+            if let Some(ref filename) = part.filename {
+                disposition.push_str(&format!("; filename=\"{}\"", quoted(filename)));
+            }
+            body.extend_from_slice(disposition.as_bytes());
+            body.extend_from_slice(b"\r\n");
+            if let Some(ref content_type) = part.content_type {
+                body.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        let content_type = Mime(TopLevel::Multipart,
+                                SubLevel::FormData,
+                                vec![(Attr::Boundary, Value::Ext(boundary))]);
+        (content_type, body)
+    }
+}
+
+/// Return true if `data` contains `pattern`.
+fn contains(data: &[u8], pattern: &str) -> bool {
+    data.windows(pattern.len())
+        .any(|window| window == pattern.as_bytes())
+}
+
+/// Escape the quotes and the line breaks of a name in a `Content-Disposition` header, as
+/// browsers do.
+fn quoted(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::mime::{Mime, SubLevel, TopLevel};
+
+    use super::*;
+
+    fn body(form: Form) -> (String, String) {
+        let (content_type, body) = form.encode();
+        (content_type.to_string(), String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn text_and_file() {
+        let form = Form::new()
+            .text("name", "vsd")
+            .bytes("cert",
+                   "vsd.pem",
+                   Mime(TopLevel::Text, SubLevel::Plain, vec![]),
+                   b"PEM".to_vec());
+        let (content_type, body) = body(form);
+        assert_eq!(content_type, "multipart/form-data; boundary=bambou-boundary-0");
+        assert_eq!(body,
+                   "--bambou-boundary-0\r\n\
+                    Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                    vsd\r\n\
+                    --bambou-boundary-0\r\n\
+                    Content-Disposition: form-data; name=\"cert\"; filename=\"vsd.pem\"\r\n\
+                    Content-Type: text/plain\r\n\r\n\
+                    PEM\r\n\
+                    --bambou-boundary-0--\r\n");
+    }
+
+    #[test]
+    fn boundary_not_in_parts() {
+        let form = Form::new().text("text", "--bambou-boundary-0");
+        let (content_type, body) = body(form);
+        assert_eq!(content_type, "multipart/form-data; boundary=bambou-boundary-1");
+        assert!(body.ends_with("--bambou-boundary-1--\r\n"));
+    }
+
+    #[test]
+    fn quoted_names() {
+        let (_, body) = body(Form::new().text("a \"b\"\r\n", ""));
+        assert!(body.contains("name=\"a %22b%22%0D%0A\"\r\n"));
+    }
+
+    #[test]
+    fn raw() {
+        let mime = Mime(TopLevel::Image, SubLevel::Png, vec![]);
+        let (content_type, data) = Upload::raw(mime.clone(), vec![1, 2]).into_body();
+        assert_eq!(content_type, mime);
+        assert_eq!(data, vec![1, 2]);
+    }
+}
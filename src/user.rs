@@ -0,0 +1,18 @@
+/// The user a session is authenticated as, as described by the root entity returned when
+/// connecting.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrentUser {
+    #[serde(rename = "ID")]
+    pub id: Option<String>,
+    #[serde(rename = "userName", default)]
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(rename = "enterpriseID", default)]
+    pub enterprise_id: Option<String>,
+    #[serde(rename = "enterpriseName", default)]
+    pub enterprise_name: Option<String>,
+    /// The expiry date of the API key, in milliseconds since the Unix epoch.
+    #[serde(rename = "APIKeyExpiry", default)]
+    pub api_key_expiry: Option<i64>,
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// The type of an attribute, as described by the API specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    List,
+    Object,
+    /// Any JSON value is accepted.
+    Any,
+}
+
+impl AttributeType {
+    fn matches(&self, value: &Value) -> bool {
+        match *self {
+            AttributeType::String => value.is_string(),
+            AttributeType::Integer => value.is_i64() || value.is_u64(),
+            AttributeType::Float => value.is_number(),
+            AttributeType::Boolean => value.is_boolean(),
+            AttributeType::List => value.is_array(),
+            AttributeType::Object => value.is_object(),
+            AttributeType::Any => true,
+        }
+    }
+}
+
+/// The specification of an entity attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttributeSpec {
+    /// The serialized name of the attribute, e.g. `"externalID"`.
+    pub name: &'static str,
+    /// The expected type of the attribute. `null` is always accepted for optional attributes.
+    pub kind: AttributeType,
+    /// Whether the attribute must be present in responses.
+    pub required: bool,
+}
+
+/// A difference between a response and the specification of the entity it contains.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    /// The path of the entity, as returned by `RestEntity::path()`.
+    pub entity: &'static str,
+    /// The name of the offending attribute.
+    pub attribute: String,
+    pub kind: MismatchKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MismatchKind {
+    /// A required attribute is missing or `null`.
+    Missing,
+    /// The attribute does not have the expected type.
+    WrongType { expected: AttributeType, found: Value },
+    /// The attribute is not part of the specification.
+    Unknown,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            MismatchKind::Missing => {
+                write!(f, "{}: missing attribute {}", self.entity, self.attribute)
+            }
+            MismatchKind::WrongType { expected, ref found } => {
+                write!(f,
+                       "{}: attribute {} should be of type {:?}, found {}",
+                       self.entity,
+                       self.attribute,
+                       expected,
+                       found)
+            }
+            MismatchKind::Unknown => {
+                write!(f, "{}: unknown attribute {}", self.entity, self.attribute)
+            }
+        }
+    }
+}
+
+/// Validate a response body, which is expected to be an array of entities, against the
+/// attributes of the entity. An empty specification disables the validation.
+pub fn validate(entity: &'static str, specs: &[AttributeSpec], body: &Value) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    if specs.is_empty() {
+        return mismatches;
+    }
+
+    let objects = body.as_array()
+        .into_iter()
+        .flat_map(|entities| entities.iter())
+        .filter_map(|entity| entity.as_object());
+
+    for object in objects {
+        for spec in specs {
+            match object.get(spec.name) {
+                None | Some(&Value::Null) => {
+                    if spec.required {
+                        mismatches.push(Mismatch {
+                                            entity: entity,
+                                            attribute: spec.name.to_string(),
+                                            kind: MismatchKind::Missing,
+                                        });
+                    }
+                }
+                Some(value) => {
+                    if !spec.kind.matches(value) {
+                        mismatches.push(Mismatch {
+                                            entity: entity,
+                                            attribute: spec.name.to_string(),
+                                            kind: MismatchKind::WrongType {
+                                                expected: spec.kind,
+                                                found: value.clone(),
+                                            },
+                                        });
+                    }
+                }
+            }
+        }
+        for name in object.keys() {
+            if !specs.iter().any(|spec| spec.name == name) {
+                mismatches.push(Mismatch {
+                                    entity: entity,
+                                    attribute: name.clone(),
+                                    kind: MismatchKind::Unknown,
+                                });
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, Value};
+
+    use super::*;
+
+    const SPECS: &'static [AttributeSpec] = &[AttributeSpec {
+                                                  name: "ID",
+                                                  kind: AttributeType::String,
+                                                  required: true,
+                                              },
+                                              AttributeSpec {
+                                                  name: "count",
+                                                  kind: AttributeType::Integer,
+                                                  required: false,
+                                              }];
+
+    fn check(body: &str) -> Vec<Mismatch> {
+        validate("enterprise", SPECS, &serde_json::from_str(body).unwrap())
+    }
+
+    fn mismatch(attribute: &str, kind: MismatchKind) -> Mismatch {
+        Mismatch {
+            entity: "enterprise",
+            attribute: attribute.to_string(),
+            kind: kind,
+        }
+    }
+
+    #[test]
+    fn valid() {
+        assert!(check(r#"[{"ID": "1", "count": 2}, {"ID": "2", "count": null}]"#).is_empty());
+    }
+
+    #[test]
+    fn missing() {
+        assert_eq!(check(r#"[{"count": 2}, {"ID": null}]"#),
+                   vec![mismatch("ID", MismatchKind::Missing),
+                        mismatch("ID", MismatchKind::Missing)]);
+    }
+
+    #[test]
+    fn wrong_type() {
+        let mismatches = check(r#"[{"ID": 1, "count": 2.5}]"#);
+        assert_eq!(mismatches,
+                   vec![mismatch("ID",
+                                 MismatchKind::WrongType {
+                                     expected: AttributeType::String,
+                                     found: Value::from(1),
+                                 }),
+                        mismatch("count",
+                                 MismatchKind::WrongType {
+                                     expected: AttributeType::Integer,
+                                     found: Value::from(2.5),
+                                 })]);
+        assert_eq!(mismatches[0].to_string(),
+                   "enterprise: attribute ID should be of type String, found 1");
+    }
+
+    #[test]
+    fn unknown() {
+        let mismatches = check(r#"[{"ID": "1", "name": "acme"}]"#);
+        assert_eq!(mismatches, vec![mismatch("name", MismatchKind::Unknown)]);
+        assert_eq!(mismatches[0].to_string(), "enterprise: unknown attribute name");
+    }
+
+    #[test]
+    fn no_specs() {
+        let body = serde_json::from_str(r#"[{"name": "acme"}]"#).unwrap();
+        assert!(validate("enterprise", &[], &body).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! Entities implemented with `#[derive(RestEntity)]`.
+
+extern crate bambou;
+#[macro_use]
+extern crate bambou_derive;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use bambou::{EntityTag, RestEntity, RestRootEntity, Session};
+
+#[derive(Clone, Debug, Serialize, Deserialize, RestEntity)]
+#[bambou(path = "enterprise", group = "enterprises", key = "name", display, builder)]
+pub struct Enterprise {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip)]
+    etag: Option<EntityTag>,
+    #[serde(skip)]
+    original: Option<serde_json::Value>,
+    #[bambou(required, key_attribute)]
+    name: String,
+    #[serde(default)]
+    #[bambou(key_attribute)]
+    description: Option<String>,
+    #[serde(rename = "externalID", default)]
+    external_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, RestEntity)]
+#[bambou(path = "me", root)]
+struct Me {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default)]
+    id: Option<String>,
+    #[serde(rename = "APIKey", default)]
+    #[bambou(api_key)]
+    key: Option<String>,
+}
+
+#[test]
+fn paths() {
+    assert_eq!(Enterprise::path(), "enterprise");
+    assert_eq!(Enterprise::group_path(), "enterprises");
+    assert_eq!(Enterprise::alternate_key(), Some("name"));
+    assert_eq!(Me::path(), "me");
+    assert_eq!(Me::group_path(), "me");
+    assert_eq!(Me::alternate_key(), None);
+}
+
+#[test]
+fn id_and_root() {
+    let enterprise = Enterprise::builder().id("42").name("acme").build();
+    assert_eq!(enterprise.id(), Some("42"));
+    assert!(!enterprise.is_root());
+    assert!(enterprise.get_session().is_none());
+
+    let me = Me {
+        session: None,
+        id: None,
+        key: Some("secret".to_string()),
+    };
+    assert!(me.is_root());
+    assert_eq!(me.get_api_key(), Some("secret"));
+}
+
+#[test]
+fn builder() {
+    let enterprise = Enterprise::builder()
+        .description("corporation")
+        .name("acme")
+        .external_id("ext-1")
+        .build();
+    assert_eq!(enterprise.name, "acme");
+    assert_eq!(enterprise.description, Some("corporation".to_string()));
+    assert_eq!(enterprise.external_id, Some("ext-1".to_string()));
+    assert_eq!(enterprise.id, None);
+    assert!(enterprise.etag.is_none());
+    assert!(enterprise.original.is_none());
+}
+
+#[test]
+fn pretty_and_display() {
+    let enterprise = Enterprise::builder().id("42").name("acme").build();
+    assert_eq!(enterprise.key_attributes(), vec![("name", "acme".to_string())]);
+    assert_eq!(enterprise.pretty(), "enterprise 42 (name: acme)");
+
+    let enterprise = Enterprise::builder()
+        .name("acme")
+        .description("corporation")
+        .build();
+    assert_eq!(enterprise.to_string(),
+               "enterprise <no ID> (name: acme, description: corporation)");
+}
+
+#[test]
+fn etag() {
+    let mut enterprise = Enterprise::builder().name("acme").build();
+    assert_eq!(enterprise.etag(), None);
+    let etag = EntityTag::new(true, "3".to_string());
+    enterprise.set_etag(Some(etag.clone()));
+    assert_eq!(enterprise.etag(), Some(&etag));
+    enterprise.set_etag(None);
+    assert_eq!(enterprise.etag(), None);
+}
+
+#[test]
+fn changes() {
+    assert!(Enterprise::tracks_changes());
+    assert!(!Me::tracks_changes());
+
+    let mut enterprise = Enterprise::builder().id("42").name("acme").build();
+    assert!(enterprise.changes().unwrap().is_empty());
+    let original = serde_json::to_value(&enterprise).unwrap();
+    enterprise.set_original(original.clone());
+    assert_eq!(enterprise.original(), Some(&original));
+
+    enterprise.name = "acme corp".to_string();
+    let changes = enterprise.changes().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "name");
+    assert_eq!(changes[0].new, serde_json::Value::String("acme corp".to_string()));
+}
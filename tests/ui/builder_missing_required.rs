@@ -0,0 +1,24 @@
+extern crate bambou;
+#[macro_use]
+extern crate bambou_derive;
+#[macro_use]
+extern crate serde_derive;
+
+use bambou::Session;
+
+#[derive(Serialize, Deserialize, RestEntity)]
+#[bambou(path = "enterprise", group = "enterprises", builder)]
+pub struct Enterprise {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default)]
+    id: Option<String>,
+    #[bambou(required)]
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+fn main() {
+    let _enterprise = Enterprise::builder().description("corporation").build();
+}
@@ -0,0 +1,42 @@
+extern crate bambou;
+#[macro_use]
+extern crate bambou_derive;
+#[macro_use]
+extern crate serde_derive;
+
+use bambou::Session;
+
+#[derive(Serialize, Deserialize, RestEntity)]
+#[bambou(path = "enterprise", group = "enterprises", builder)]
+pub struct Enterprise {
+    #[serde(skip)]
+    session: Option<Session>,
+    #[serde(rename = "ID", default)]
+    id: Option<String>,
+    #[bambou(required)]
+    name: String,
+    #[serde(rename = "enterpriseProfileID")]
+    #[bambou(required)]
+    enterprise_profile_id: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+fn main() {
+    // The required fields can be set in any order, and more than once.
+    let enterprise = Enterprise::builder()
+        .enterprise_profile_id("1")
+        .description("corporation")
+        .name("acme")
+        .name("acme corp")
+        .build();
+    assert_eq!(enterprise.name, "acme corp");
+    assert_eq!(enterprise.enterprise_profile_id, "1");
+    assert_eq!(enterprise.description, Some("corporation".to_string()));
+
+    let enterprise = Enterprise::builder()
+        .name("acme")
+        .enterprise_profile_id("1")
+        .build();
+    assert_eq!(enterprise.description, None);
+}